@@ -0,0 +1,79 @@
+//! `cargo bench` times each program in benches/programs three ways: the
+//! tree-walking interpreter, the bytecode vm and the rust rustc builds
+//! from it. each is run a few times and the fastest run is reported
+
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use oxide::Options;
+
+const RUNS: usize = 5;
+
+fn main() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/programs");
+    let mut programs: Vec<PathBuf> = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "oxi"))
+        .collect();
+
+    programs.sort();
+
+    println!("{:<12} {:>14} {:>14} {:>14}", "program", "interpreter", "vm", "rustc -O");
+
+    for path in programs {
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        let source = fs::read_to_string(&path).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let interpreter = fastest(|| {
+            oxide::interpret_file(&path, &source, &mut io::sink(), &mut io::sink()).unwrap();
+        });
+
+        // lowering to bytecode is part of what the vm costs
+        let vm = fastest(|| {
+            let program = oxide::bytecode::compile_file(&path, &source).unwrap();
+            oxide::vm::run(&program, &path, &mut io::sink(), &mut io::sink());
+        });
+
+        let binary = build(&name, &path, &source);
+        let compiled = fastest(|| {
+            Command::new(&binary).output().unwrap();
+        });
+
+        println!("{name:<12} {:>14?} {:>14?} {:>14?}", interpreter, vm, compiled);
+    }
+}
+
+fn fastest(mut run: impl FnMut()) -> Duration {
+    return (0..RUNS).map(|_| {
+        let start = Instant::now();
+        run();
+        start.elapsed()
+    }).min().unwrap();
+}
+
+/// the program built with optimisations, rustc isn't timed
+fn build(name: &str, path: &str, source: &str) -> PathBuf {
+    let options = Options { ordered_maps: true, ..Options::default() };
+    let output = oxide::compile_file(path, source, &options).unwrap();
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("benches");
+    fs::create_dir_all(&dir).unwrap();
+
+    let rust_path = dir.join(format!("{name}.rs"));
+    let binary = dir.join(name);
+    fs::write(&rust_path, output.code).unwrap();
+
+    let status = Command::new("rustc")
+        .args(["--edition", "2021", "-O", "-A", "warnings", "-o"])
+        .arg(&binary)
+        .arg(&rust_path)
+        .status()
+        .unwrap();
+
+    assert!(status.success(), "rustc failed on {path}");
+    return binary;
+}
@@ -0,0 +1,42 @@
+use crate::emitter::Emitter;
+use crate::ir::{Body, Const, Function};
+
+/// the language the generator writes
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Target {
+    #[default]
+    Rust,
+    /// c99 with the runtime in `c_runtime.h` pasted in front
+    C,
+    /// an es module with the runtime in `js_runtime.js` pasted in front
+    Js,
+    /// webassembly text, only for programs that stick to `int` and `bool`
+    Wat,
+}
+
+/// an imported file's generated source and where it came from
+pub(crate) struct Linked {
+    pub(crate) name: String,
+    pub(crate) path: String,
+    pub(crate) source: String,
+}
+
+/// how the generator writes one language. the generator lowers each
+/// file to the ir and hands a backend its procs, a backend writes them and
+/// puts the pieces together
+pub(crate) trait Backend {
+    /// a proc, written at module level
+    fn function(&mut self, out: &mut Emitter, function: &Function);
+
+    /// a `const`, most targets write its value wherever it's used instead
+    fn constant(&mut self, _out: &mut Emitter, _constant: &Const) {}
+
+    /// `import`, most targets bundle the imported files in `link` instead
+    fn import(&mut self, _out: &mut Emitter, _name: &str) {}
+
+    /// `module` has the procs, `top_level` the statements outside them
+    fn finish(&mut self, module: Emitter, top_level: &Body) -> String;
+
+    /// the entry file's generated source with the files it imports
+    fn link(&self, entry: String, entry_path: &str, imports: Vec<Linked>) -> String;
+}
@@ -0,0 +1,89 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::Options;
+
+/// for a crate's `build.rs`: transpiles every `.ox` and `.oxi` file under
+/// `dir` into `OUT_DIR`, keeping the directory layout, so `dir/math.ox`
+/// can be pulled in with
+///
+/// ```ignore
+/// mod math {
+///     include!(concat!(env!("OUT_DIR"), "/math.rs"));
+/// }
+/// ```
+///
+/// procs come out `pub`. diagnostics are printed as
+/// `cargo:warning=file.oxi:3:5: message` and the files they're in are
+/// skipped, then an error is returned so the build stops. gives back the
+/// files it wrote
+pub fn transpile_dir(dir: impl AsRef<Path>, options: &Options) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let out_dir = match env::var_os("OUT_DIR") {
+        Some(out_dir) => PathBuf::from(out_dir),
+        None => return Err(io::Error::other("OUT_DIR isn't set, this should be called from build.rs")),
+    };
+
+    let options = Options { module: true, source_map: false, ..options.clone() };
+    let mut sources = Vec::new();
+    find_sources(dir, &mut sources)?;
+
+    // a new file showing up in the directory needs a rebuild too
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut written = Vec::new();
+    let mut failed = 0;
+
+    for source in sources {
+        println!("cargo:rerun-if-changed={}", source.display());
+
+        let contents = fs::read_to_string(&source)?;
+        let output = match crate::compile_file(&source.to_string_lossy(), &contents, &options) {
+            Ok(output) => output,
+            Err(errors) => {
+                for error in errors {
+                    println!("cargo:warning={}", error.located(&source.display().to_string()));
+                }
+
+                failed += 1;
+                continue;
+            },
+        };
+
+        let relative = source.strip_prefix(dir).unwrap_or(&source);
+        let target = out_dir.join(relative).with_extension("rs");
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&target, output.code)?;
+        written.push(target);
+    }
+
+    if failed > 0 {
+        return Err(io::Error::other(format!("{failed} oxide file{} failed to compile", if failed == 1 { "" } else { "s" })));
+    }
+
+    return Ok(written);
+}
+
+fn find_sources(dir: &Path, sources: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+
+    // the same order on every machine
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            find_sources(&path, sources)?;
+        } else if path.extension().is_some_and(|extension| extension == "ox" || extension == "oxi") {
+            sources.push(path);
+        }
+    }
+
+    return Ok(());
+}
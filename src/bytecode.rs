@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::{Diagnostic, Options};
+use crate::expression::{BinaryOp, Piece, format_string};
+use crate::interpreter::{Value, literal};
+use crate::ir;
+use crate::parser::Span;
+use crate::program;
+
+/// one instruction for the vm. jumps are to an index in the same function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// push a value from the constants pool
+    Const(u32),
+    Load(u32),
+    Store(u32),
+    Pop,
+    Binary(BinaryOp),
+    Neg,
+    Not,
+    Jump(u32),
+    /// pops a bool and jumps when it's false
+    JumpIfFalse(u32),
+    /// the function and how many arguments are on the stack for it
+    Call(u32, u32),
+    Return,
+    Some,
+    Ok,
+    Fail,
+    Tuple(u32),
+    Map(u32),
+    Field(u32),
+    Get,
+    Contains,
+    Keys,
+    Values,
+    /// map methods that change the map in a local
+    Insert(u32),
+    Remove(u32),
+    /// `?`, returns a `fail` from the function and unwraps an `ok`
+    Try,
+    /// pops an `opt`, pushes what's inside a `some` and jumps on `none`
+    MatchSome(u32),
+    /// what a loop goes over as a list, a map's are (key, value) tuples
+    Items,
+    Len,
+    /// pops an index and a list, pushes the item at the index
+    Index,
+    /// pushes every field of a tuple
+    Unpack(u32),
+    /// the format string and how many arguments are on the stack for it
+    Print(u32, u32),
+}
+
+/// a proc, or the top level of a program without `proc main`
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: u32,
+    /// params included
+    pub locals: u32,
+    pub code: Vec<Op>,
+    /// the oxide statement each instruction came from
+    pub spans: Vec<Span>,
+    /// the imported file the function is in, `None` for the entry file
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub constants: Vec<Value>,
+    /// format strings with every `{}` and `{name}` turned into an index
+    pub formats: Vec<Vec<Piece>>,
+    pub functions: Vec<Function>,
+    pub entry: u32,
+    /// `proc main` returns a `result`, a `fail` from it exits with 1
+    pub main_fails: bool,
+}
+
+/// lowers the program at `path` and every file it imports to bytecode
+pub fn compile_file(path: &str, source: &str) -> Result<Program, Vec<Diagnostic>> {
+    let modules = program::load(path, source, &Options::default())?;
+    let entry = modules.len() - 1;
+    let mut program = Program { constants: Vec::new(), formats: Vec::new(), functions: Vec::new(), entry: 0, main_fails: false };
+    let mut bodies = Vec::new();
+    let mut indexes = HashMap::new();
+    let mut top_level = Vec::new();
+
+    // every proc gets its index before any are compiled, so they can call each other
+    for (index, module) in modules.into_iter().enumerate() {
+        let prefix = if index == entry { String::new() } else { format!("{}::", module.name) };
+        let file = if index == entry { None } else { Some(module.path) };
+
+        for item in module.lowered.items {
+            let ir::Item::Function(function) = item else {
+                continue;
+            };
+
+            let name = format!("{prefix}{}", function.name);
+            if index == entry && function.name == "main" {
+                program.main_fails = function.returns.starts_with("Result<");
+            }
+
+            indexes.insert(name.clone(), program.functions.len() as u32);
+            program.functions.push(Function {
+                name,
+                params: function.params.len() as u32,
+                locals: 0,
+                code: Vec::new(),
+                spans: Vec::new(),
+                file: file.clone(),
+            });
+            bodies.push((prefix.clone(), function.params, function.body));
+        }
+
+        if index == entry {
+            top_level = module.lowered.top_level;
+        }
+    }
+
+    program.entry = match indexes.get("main") {
+        Some(main) => *main,
+        None => {
+            let top = Function { name: String::from("<top level>"), params: 0, locals: 0, code: Vec::new(), spans: Vec::new(), file: None };
+            program.functions.push(top);
+            bodies.push((String::new(), Vec::new(), top_level));
+            program.functions.len() as u32 - 1
+        },
+    };
+
+    let mut errors = Vec::new();
+
+    for (index, (prefix, params, body)) in bodies.into_iter().enumerate() {
+        let file = program.functions[index].file.clone();
+        let mut compiler = Compiler {
+            program: &mut program,
+            indexes: &indexes,
+            prefix,
+            code: Vec::new(),
+            spans: Vec::new(),
+            slots: HashMap::new(),
+            breaks: Vec::new(),
+            span: Span { line: 1, column: 1 },
+        };
+
+        for param in &params {
+            compiler.declare(param);
+        }
+
+        if let Err((span, message)) = compiler.statements(&body) {
+            errors.push(Diagnostic { span: Some(span), message, file });
+            continue;
+        }
+
+        // falling off the end of a function gives back `()`
+        compiler.push_constant(Value::Unit);
+        compiler.emit(Op::Return);
+
+        let locals = compiler.slots.len() as u32;
+        let (code, spans) = (compiler.code, compiler.spans);
+        let function = &mut program.functions[index];
+        function.code = code;
+        function.spans = spans;
+        function.locals = locals;
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    return Ok(program);
+}
+
+type Compiled = Result<(), (Span, String)>;
+
+struct Compiler<'a> {
+    program: &'a mut Program,
+    indexes: &'a HashMap<String, u32>,
+    /// `math::` in an imported file, calls in it are to its own procs
+    prefix: String,
+    code: Vec<Op>,
+    spans: Vec<Span>,
+    /// the local each variable lives in, by its unique name. a temp is
+    /// kept apart from a variable that happens to have the same name
+    slots: HashMap<(bool, String), u32>,
+    /// the jumps out of each loop being compiled, the innermost last
+    breaks: Vec<Vec<usize>>,
+    /// the statement being compiled
+    span: Span,
+}
+
+impl Compiler<'_> {
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.spans.push(self.span);
+        return self.code.len() - 1;
+    }
+
+    /// points a jump emitted earlier at the next instruction
+    fn patch(&mut self, at: usize) {
+        let here = self.code.len() as u32;
+        self.code[at] = match self.code[at] {
+            Op::Jump(_) => Op::Jump(here),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(here),
+            Op::MatchSome(_) => Op::MatchSome(here),
+            op => op,
+        };
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, (Span, String)> {
+        return Err((self.span, message));
+    }
+
+    fn constant(&mut self, value: Value) -> u32 {
+        if let Some(index) = self.program.constants.iter().position(|constant| *constant == value) {
+            return index as u32;
+        }
+
+        self.program.constants.push(value);
+        return self.program.constants.len() as u32 - 1;
+    }
+
+    fn push_constant(&mut self, value: Value) {
+        let index = self.constant(value);
+        self.emit(Op::Const(index));
+    }
+
+    /// the local a variable lives in, a new one the first time it's seen
+    fn declare(&mut self, var: &ir::Var) -> u32 {
+        let next = self.slots.len() as u32;
+        return *self.slots.entry((var.temp, var.unique.clone())).or_insert(next);
+    }
+
+    fn lookup(&self, var: &ir::Var) -> Result<u32, (Span, String)> {
+        return match self.slots.get(&(var.temp, var.unique.clone())) {
+            Some(slot) => Ok(*slot),
+            None => self.error(format!("`{}` isn't declared", var.name)),
+        };
+    }
+
+    fn statements(&mut self, body: &[(Span, ir::Stmt)]) -> Compiled {
+        for (span, stmt) in body {
+            self.span = *span;
+            self.statement(stmt)?;
+        }
+
+        return Ok(());
+    }
+
+    fn statement(&mut self, stmt: &ir::Stmt) -> Compiled {
+        match stmt {
+            ir::Stmt::Let(var, value) => {
+                self.expression(value)?;
+                let slot = self.declare(var);
+                self.emit(Op::Store(slot));
+            },
+            ir::Stmt::Assign(var, value) => {
+                self.expression(value)?;
+                let slot = self.lookup(var)?;
+                self.emit(Op::Store(slot));
+            },
+            ir::Stmt::Unpack(vars, value) => {
+                self.expression(value)?;
+                self.emit(Op::Unpack(vars.len() as u32));
+
+                // the last field is on top
+                let slots: Vec<u32> = vars.iter().map(|var| self.declare(var)).collect();
+                for slot in slots.into_iter().rev() {
+                    self.emit(Op::Store(slot));
+                }
+            },
+            ir::Stmt::Expr(value) => {
+                self.expression(value)?;
+                self.emit(Op::Pop);
+            },
+            ir::Stmt::Print(pieces, args) => {
+                for arg in args {
+                    self.expression(arg)?;
+                }
+
+                self.program.formats.push(pieces.clone());
+                let format = self.program.formats.len() as u32 - 1;
+                self.emit(Op::Print(format, args.len() as u32));
+            },
+            ir::Stmt::Return(value) => {
+                match value {
+                    Some(value) => self.expression(value)?,
+                    None => self.push_constant(Value::Unit),
+                }
+
+                self.emit(Op::Return);
+            },
+            ir::Stmt::If(cond, then, otherwise, _) => {
+                let skip = match cond {
+                    ir::Cond::Bool(condition) => {
+                        self.expression(condition)?;
+                        self.emit(Op::JumpIfFalse(0))
+                    },
+                    ir::Cond::Some(var, value) => {
+                        self.expression(value)?;
+                        let skip = self.emit(Op::MatchSome(0));
+                        let slot = self.declare(var);
+                        self.emit(Op::Store(slot));
+                        skip
+                    },
+                };
+
+                self.statements(then)?;
+
+                if otherwise.is_empty() {
+                    self.patch(skip);
+                } else {
+                    let end = self.emit(Op::Jump(0));
+                    self.patch(skip);
+                    self.statements(otherwise)?;
+                    self.patch(end);
+                }
+            },
+            ir::Stmt::Loop(body) => {
+                let start = self.code.len() as u32;
+                self.breaks.push(Vec::new());
+                self.statements(body)?;
+                self.emit(Op::Jump(start));
+
+                for jump in self.breaks.pop().unwrap_or_default() {
+                    self.patch(jump);
+                }
+            },
+            ir::Stmt::Break => {
+                let jump = self.emit(Op::Jump(0));
+                match self.breaks.last_mut() {
+                    Some(breaks) => breaks.push(jump),
+                    None => return self.error(String::from("`break` outside of a loop")),
+                }
+            },
+            ir::Stmt::Block(body) => self.statements(body)?,
+        }
+
+        return Ok(());
+    }
+
+    fn expression(&mut self, expr: &ir::Expr) -> Compiled {
+        match expr {
+            ir::Expr::Int(value) => self.push_constant(Value::Int(*value)),
+            ir::Expr::Str(value) => self.push_constant(Value::Str(value.to_owned())),
+            ir::Expr::Bool(value) => self.push_constant(Value::Bool(*value)),
+            ir::Expr::None => self.push_constant(Value::Opt(None)),
+            ir::Expr::Local(var) => {
+                let slot = self.lookup(var)?;
+                self.emit(Op::Load(slot));
+            },
+            // a `const` is written in where it's used
+            ir::Expr::Const(_, value) => self.push_constant(literal(value)),
+            ir::Expr::Tuple(items) => {
+                for item in items {
+                    self.expression(item)?;
+                }
+
+                self.emit(Op::Tuple(items.len() as u32));
+            },
+            ir::Expr::Map(entries) => {
+                for (key, value) in entries {
+                    self.expression(key)?;
+                    self.expression(value)?;
+                }
+
+                self.emit(Op::Map(entries.len() as u32));
+            },
+            ir::Expr::Some(inner) => self.unary(inner, Op::Some)?,
+            ir::Expr::Ok(inner) => self.unary(inner, Op::Ok)?,
+            ir::Expr::Fail(inner) => self.unary(inner, Op::Fail)?,
+            ir::Expr::Call(name, args, _) => {
+                for arg in args {
+                    self.expression(arg)?;
+                }
+
+                let qualified = if name.contains("::") { name.to_owned() } else { format!("{}{name}", self.prefix) };
+                let Some(function) = self.indexes.get(&qualified) else {
+                    return self.error(format!("there's no proc called `{name}`"));
+                };
+
+                self.emit(Op::Call(*function, args.len() as u32));
+            },
+            ir::Expr::Method(method @ (ir::Method::Insert | ir::Method::Remove), receiver, args) => {
+                let ir::Expr::Local(var) = receiver.as_ref() else {
+                    return self.error(format!("`{}` has to change a variable", method.name()));
+                };
+
+                let slot = self.lookup(var)?;
+                for arg in args {
+                    self.expression(arg)?;
+                }
+
+                self.emit(if *method == ir::Method::Insert { Op::Insert(slot) } else { Op::Remove(slot) });
+            },
+            ir::Expr::Method(method, receiver, args) => {
+                self.expression(receiver)?;
+                for arg in args {
+                    self.expression(arg)?;
+                }
+
+                self.emit(match method {
+                    ir::Method::Get => Op::Get,
+                    ir::Method::Contains => Op::Contains,
+                    ir::Method::Keys => Op::Keys,
+                    _ => Op::Values,
+                });
+            },
+            ir::Expr::Field(tuple, index) => {
+                self.expression(tuple)?;
+                self.emit(Op::Field(*index as u32));
+            },
+            ir::Expr::Try(inner) => self.unary(inner, Op::Try)?,
+            ir::Expr::Neg(inner) => self.unary(inner, Op::Neg)?,
+            ir::Expr::Not(inner) => self.unary(inner, Op::Not)?,
+            ir::Expr::Binary(op, left, right) => {
+                self.expression(left)?;
+                self.expression(right)?;
+                self.emit(Op::Binary(*op));
+            },
+            ir::Expr::And(left, right) => {
+                self.expression(left)?;
+                let short = self.emit(Op::JumpIfFalse(0));
+                self.expression(right)?;
+                let end = self.emit(Op::Jump(0));
+                self.patch(short);
+                self.push_constant(Value::Bool(false));
+                self.patch(end);
+            },
+            ir::Expr::Or(left, right) => {
+                self.expression(left)?;
+                let long = self.emit(Op::JumpIfFalse(0));
+                self.push_constant(Value::Bool(true));
+                let end = self.emit(Op::Jump(0));
+                self.patch(long);
+                self.expression(right)?;
+                self.patch(end);
+            },
+            ir::Expr::Items(inner) => self.unary(inner, Op::Items)?,
+            ir::Expr::Len(inner) => self.unary(inner, Op::Len)?,
+            ir::Expr::Index(list, index) => {
+                self.expression(list)?;
+                self.expression(index)?;
+                self.emit(Op::Index);
+            },
+        }
+
+        return Ok(());
+    }
+
+    /// an instruction that works on one value
+    fn unary(&mut self, inner: &ir::Expr, op: Op) -> Compiled {
+        self.expression(inner)?;
+        self.emit(op);
+        return Ok(());
+    }
+}
+
+/// what `--emit=bytecode` prints
+pub fn disassemble(program: &Program) -> String {
+    let mut output = String::from("constants:\n");
+
+    for (index, constant) in program.constants.iter().enumerate() {
+        let _ = writeln!(output, "    {index:>4}  {}", constant.debug());
+    }
+
+    for (index, function) in program.functions.iter().enumerate() {
+        let entry = if index as u32 == program.entry { ", entry" } else { "" };
+        let _ = writeln!(output, "\nfn {index} {} ({} params, {} locals{entry}):", function.name, function.params, function.locals);
+
+        let mut last_line = 0;
+        for (at, (op, span)) in function.code.iter().zip(&function.spans).enumerate() {
+            // the source line is only shown when it changes
+            let line = if span.line != last_line { format!("{}:{}", span.line, span.column) } else { String::new() };
+            last_line = span.line;
+
+            let operand = match op {
+                Op::Const(constant) => format!("{constant} ({})", program.constants[*constant as usize].debug()),
+                Op::Call(target, count) => format!("{} ({count} args)", program.functions[*target as usize].name),
+                Op::Print(format, count) => format!("{:?} ({count} args)", format_string(&program.formats[*format as usize], true)),
+                Op::Binary(op) => op.symbol().to_string(),
+                Op::Load(slot) | Op::Store(slot) | Op::Insert(slot) | Op::Remove(slot) => format!("local {slot}"),
+                Op::Jump(target) | Op::JumpIfFalse(target) | Op::MatchSome(target) => format!("-> {target:04}"),
+                Op::Tuple(count) | Op::Map(count) | Op::Field(count) | Op::Unpack(count) => count.to_string(),
+                _ => String::new(),
+            };
+
+            // `JumpIfFalse(6)` is shown as `jump_if_false`
+            let mut name = String::new();
+            for c in format!("{op:?}").chars().take_while(|c| *c != '(') {
+                if c.is_uppercase() && !name.is_empty() {
+                    name.push('_');
+                }
+
+                name.push(c.to_ascii_lowercase());
+            }
+
+            let _ = writeln!(output, "    {at:04}  {line:<8} {name:<14} {operand}");
+        }
+    }
+
+    return output.lines().map(str::trim_end).collect::<Vec<&str>>().join("\n") + "\n";
+}
+
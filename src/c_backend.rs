@@ -0,0 +1,458 @@
+use crate::backend::{Backend, Linked};
+use crate::checker::map_types;
+use crate::expression::{BinaryOp, format_string};
+use crate::emitter::Emitter;
+use crate::generator::Unit;
+use crate::ir::{Body, Cond, Expr, Function, Method, Stmt, Var};
+use crate::parser::Span;
+use crate::sourcemap::tag_markers;
+
+/// what every generated c file starts with, see `c_runtime.h`
+pub(crate) const RUNTIME: &str = include_str!("c_runtime.h");
+
+/// writes c99 against the runtime in `c_runtime.h`. every value is an
+/// `ox_value` so oxide's types don't need to be spelled out, the checker
+/// has already made sure they line up
+pub(crate) struct C {
+    unit: Unit,
+    /// `math__` in a file imported as `math`, procs share one namespace in c
+    prefix: String,
+    has_main: bool,
+    /// a declaration for every proc so they can be called from anywhere
+    prototypes: Vec<String>,
+    temps: usize,
+    /// the maps in the proc being generated that `insert` or `remove`
+    /// change in place, each holds a copy no other value shares
+    owned: Vec<String>,
+}
+
+impl C {
+    pub(crate) fn new(unit: Unit, module: Option<&str>) -> C {
+        let prefix = match module {
+            Some(module) => format!("{}__", c_identifier(module)),
+            None => String::new(),
+        };
+
+        return C { unit, prefix, has_main: false, prototypes: Vec::new(), temps: 0, owned: Vec::new() };
+    }
+
+    fn temp(&mut self, name: &str) -> String {
+        self.temps += 1;
+        return format!("ox_{name}_{}", self.temps);
+    }
+
+    /// a map that's going to be changed in place gets a copy of its own,
+    /// gives back the c name of the variable
+    fn own(&mut self, var: &Var) -> Option<String> {
+        if !var.mutable || map_types(&var.type_class).is_none() {
+            return None;
+        }
+
+        self.owned.push(name(var));
+        return Some(name(var));
+    }
+
+    /// an expression that's only looked at, an owned map doesn't need
+    /// copying for it
+    fn read(&mut self, expr: &Expr, before: &mut Vec<String>) -> String {
+        return match expr {
+            Expr::Local(var) => name(var),
+            _ => self.expr(expr, before),
+        };
+    }
+
+    /// the c name of a proc, `math::double` is `math__double`
+    fn proc_name(&self, name: &str) -> String {
+        if let Some((module, name)) = name.rsplit_once("::") {
+            let module = module.rsplit("::").next().unwrap_or(module);
+            return format!("{}__{}", c_identifier(module), c_identifier(name));
+        }
+
+        // `main` is wrapped by the real `int main`
+        if name == "main" && self.unit == Unit::Program {
+            return String::from("oxide_main");
+        }
+
+        return format!("{}{}", self.prefix, c_identifier(name));
+    }
+
+    /// an expression as a c one. `?` can't be written inside an
+    /// expression in c, so each one becomes a temp declared in `before`
+    /// that returns early when it holds a `fail`
+    fn expr(&mut self, expr: &Expr, before: &mut Vec<String>) -> String {
+        match expr {
+            Expr::Int(value) => format!("ox_int({value})"),
+            Expr::Str(value) => format!("ox_str(\"{}\")", escape(value)),
+            Expr::Bool(value) => format!("ox_bool({})", *value as u8),
+            Expr::None => String::from("ox_none()"),
+            // anything holding on to an owned map mustn't see it change
+            Expr::Local(var) if self.owned.contains(&name(var)) => format!("ox_map_copy({})", name(var)),
+            // anything holding on to an owned map mustn't see it change
+            Expr::Local(var) if self.owned.contains(&name(var)) => format!("ox_map_copy({})", name(var)),
+            Expr::Local(var) => name(var),
+            Expr::Const(_, value) => self.expr(value, before),
+            Expr::Tuple(items) => {
+                let mut args = vec![items.len().to_string()];
+                for item in items {
+                    args.push(self.expr(item, before));
+                }
+
+                format!("ox_tuple({})", args.join(", "))
+            },
+            Expr::Map(entries) => {
+                let mut args = vec![entries.len().to_string()];
+                for (key, value) in entries {
+                    args.push(self.expr(key, before));
+                    args.push(self.expr(value, before));
+                }
+
+                format!("ox_map({})", args.join(", "))
+            },
+            Expr::Some(inner) => format!("ox_some({})", self.expr(inner, before)),
+            Expr::Ok(inner) => format!("ox_ok({})", self.expr(inner, before)),
+            Expr::Fail(inner) => format!("ox_fail({})", self.expr(inner, before)),
+            Expr::Call(callee, args, _) => {
+                let args: Vec<String> = args.iter().map(|arg| self.expr(arg, before)).collect();
+                format!("{}({})", self.proc_name(callee), args.join(", "))
+            },
+            Expr::Method(method, receiver, args) => {
+                let mut args: Vec<String> = args.iter().map(|arg| self.expr(arg, before)).collect();
+
+                // `insert` and `remove` change the map in the variable
+                let receiver = self.read(receiver, before);
+                if matches!(method, Method::Insert | Method::Remove) {
+                    args.insert(0, format!("&{receiver}"));
+                } else {
+                    args.insert(0, receiver);
+                }
+
+                format!("ox_map_{}({})", method.name(), args.join(", "))
+            },
+            Expr::Field(tuple, index) => format!("ox_field({}, {index})", self.expr(tuple, before)),
+            Expr::Try(inner) => {
+                let inner = self.expr(inner, before);
+                let temp = self.temp("try");
+
+                before.push(format!("ox_value {temp} = {inner};"));
+                before.push(format!("if (!ox_is_ok({temp})) return {temp};"));
+                format!("ox_unwrap({temp})")
+            },
+            Expr::Neg(inner) => format!("ox_neg({})", self.expr(inner, before)),
+            Expr::Not(inner) => format!("ox_not({})", self.expr(inner, before)),
+            Expr::Binary(op, left, right) => {
+                let left = self.read(left, before);
+                let right = self.read(right, before);
+                format!("{}({left}, {right})", binary_function(*op))
+            },
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                let op = if matches!(expr, Expr::And(..)) { "&&" } else { "||" };
+                let left = self.expr(left, before);
+                let right = self.expr(right, before);
+                format!("ox_bool(ox_cond({left}) {op} ox_cond({right}))")
+            },
+            Expr::Items(inner) => format!("ox_items({})", self.read(inner, before)),
+            Expr::Len(inner) => format!("ox_len({})", self.read(inner, before)),
+            Expr::Index(list, index) => format!("ox_index({}, {})", self.expr(list, before), self.expr(index, before)),
+        }
+    }
+
+    fn statements(&mut self, out: &mut Emitter, body: &Body) {
+        for (span, stmt) in body {
+            out.at(Some(*span));
+            self.statement(out, *span, stmt);
+        }
+    }
+
+    fn statement(&mut self, out: &mut Emitter, span: Span, stmt: &Stmt) {
+        let mut before = Vec::new();
+
+        match stmt {
+            Stmt::Let(var, value) => {
+                let copied = match value {
+                    Expr::Map(_) => true,
+                    Expr::Local(local) => self.owned.contains(&name(local)),
+                    _ => false,
+                };
+
+                let mut value = self.expr(value, &mut before);
+                if self.own(var).is_some() && !copied {
+                    value = format!("ox_map_copy({value})");
+                }
+
+                C::lines(out, span, before, &format!("ox_value {} = {value};", name(var)));
+            },
+            Stmt::Unpack(vars, value) => {
+                let value = self.expr(value, &mut before);
+                let tuple = self.temp("tuple");
+                before.push(format!("ox_value {tuple} = {value};"));
+
+                let fields: Vec<String> = vars.iter().enumerate()
+                    .map(|(index, var)| match self.own(var) {
+                        Some(owned) => format!("ox_value {owned} = ox_map_copy(ox_field({tuple}, {index}));"),
+                        None => format!("ox_value {} = ox_field({tuple}, {index});", name(var)),
+                    })
+                    .collect();
+
+                C::lines(out, span, before, &fields.join(" "));
+            },
+            Stmt::Assign(var, value) => {
+                let value = self.expr(value, &mut before);
+                C::lines(out, span, before, &format!("{} = {value};", name(var)));
+            },
+            Stmt::Expr(value) => {
+                let value = self.expr(value, &mut before);
+                C::lines(out, span, before, &format!("{value};"));
+            },
+            Stmt::Print(pieces, args) => {
+                let mut values: Vec<String> = args.iter().map(|arg| self.read(arg, &mut before)).collect();
+                values.insert(0, values.len().to_string());
+                values.insert(0, format!("\"{}\"", escape(&format_string(pieces, true))));
+                C::lines(out, span, before, &format!("ox_print({});", values.join(", ")));
+            },
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value, &mut before),
+                    None => String::from("ox_unit()"),
+                };
+
+                C::lines(out, span, before, &format!("return {value};"));
+            },
+            Stmt::If(..) => self.branch(out, span, stmt, false),
+            Stmt::Loop(body) => {
+                out.open("for (;;) {");
+                self.statements(out, body);
+                out.close();
+            },
+            Stmt::Break => out.line("break;"),
+            Stmt::Block(body) => {
+                out.open("{");
+                self.statements(out, body);
+                out.close();
+            },
+        }
+    }
+
+    /// an `if`, `chained` when it's all there is in the `else` before it.
+    /// anything that has to run before the condition needs the `else` to
+    /// be a block of its own
+    fn branch(&mut self, out: &mut Emitter, span: Span, stmt: &Stmt, chained: bool) {
+        let Stmt::If(condition, then, otherwise, else_at) = stmt else {
+            return;
+        };
+
+        let mut before = Vec::new();
+        let mut wrapped = false;
+
+        match condition {
+            Cond::Bool(value) => {
+                let value = self.expr(value, &mut before);
+                let head = format!("if (ox_cond(({}, {value}))) {{", at(span));
+
+                if chained && before.is_empty() {
+                    out.chain(&format!("else {head}"));
+                } else {
+                    if chained {
+                        out.chain("else {");
+                        wrapped = true;
+                    }
+
+                    C::hoist(out, span, before);
+                    out.open(&head);
+                }
+            },
+            Cond::Some(var, value) => {
+                let value = self.expr(value, &mut before);
+                let opt = self.temp("opt");
+
+                if chained {
+                    out.chain("else {");
+                    wrapped = true;
+                }
+
+                C::lines(out, span, before, &format!("ox_value {opt} = {value};"));
+                out.open(&format!("if (ox_is_some({opt})) {{"));
+                out.line(&format!("ox_value {} = ox_unwrap({opt});", name(var)));
+            },
+        }
+
+        self.statements(out, then);
+        out.close();
+
+        match otherwise.as_slice() {
+            [] => (),
+            [(span, stmt @ Stmt::If(..))] => {
+                out.at(*else_at);
+                self.branch(out, *span, stmt, true);
+            },
+            _ => {
+                out.at(*else_at);
+                out.chain("else {");
+                self.statements(out, otherwise);
+                out.close();
+            },
+        }
+
+        if wrapped {
+            out.close();
+        }
+    }
+
+    /// lines hoisted out of a statement's expressions, then the statement
+    fn lines(out: &mut Emitter, span: Span, before: Vec<String>, line: &str) {
+        let at = C::hoist(out, span, before);
+        out.line(&format!("{at}{line}"));
+    }
+
+    /// gives back the `OX_AT` the next line still needs if nothing was hoisted
+    fn hoist(out: &mut Emitter, span: Span, before: Vec<String>) -> String {
+        let mut at = format!("{}; ", at(span));
+
+        for hoisted in before {
+            if hoisted.starts_with('#') {
+                out.line(&hoisted);
+            } else {
+                out.line(&format!("{at}{hoisted}"));
+                at.clear();
+            }
+        }
+
+        return at;
+    }
+}
+
+impl Backend for C {
+    fn function(&mut self, out: &mut Emitter, function: &Function) {
+        let proc_name = self.proc_name(&function.name);
+        let params: Vec<String> = function.params.iter().map(|param| format!("ox_value {}", name(param))).collect();
+
+        if proc_name == "oxide_main" {
+            self.has_main = true;
+        }
+
+        // only `pub` procs can be called from the file importing this one
+        let linkage = if self.unit == Unit::Import && !function.public { "static " } else { "" };
+        let params = if params.is_empty() { String::from("void") } else { params.join(", ") };
+        let signature = format!("{linkage}ox_value {proc_name}({params})");
+
+        self.prototypes.push(format!("{signature};"));
+        out.open(&format!("{signature} {{"));
+
+        // the caller's map is left as it was
+        self.owned.clear();
+        for param in &function.params {
+            if let Some(owned) = self.own(param) {
+                out.line(&format!("{owned} = ox_map_copy({owned});"));
+            }
+        }
+
+        self.statements(out, &function.body);
+        out.line("return ox_unit();");
+        out.close();
+    }
+
+    fn finish(&mut self, mut module: Emitter, top_level: &Body) -> String {
+        if self.unit == Unit::Program {
+            module.blank_line();
+            module.open("int main(void) {");
+
+            // the checker has already rejected top level statements next to a `proc main`
+            if self.has_main {
+                module.line("return ox_exit_code(oxide_main());");
+            } else {
+                self.owned.clear();
+                self.statements(&mut module, top_level);
+                module.at(None);
+                module.line("return 0;");
+            }
+
+            module.close();
+        }
+
+        let module = module.finish();
+        if self.prototypes.is_empty() {
+            return module;
+        }
+
+        return format!("{}\n{module}", self.prototypes.join("\n") + "\n");
+    }
+
+    /// imported files go first, each reporting runtime errors against its own path
+    fn link(&self, entry: String, entry_path: &str, imports: Vec<Linked>) -> String {
+        let mut output = String::new();
+
+        for import in imports {
+            output.push_str(&format!("\n#undef OX_FILE\n#define OX_FILE \"{}\"\n\n", escape(&import.path)));
+            output.push_str(&tag_markers(&import.source, &import.path));
+        }
+
+        output.push_str(&format!("\n#undef OX_FILE\n#define OX_FILE \"{}\"\n\n", escape(entry_path)));
+        output.push_str(&entry);
+        return output;
+    }
+}
+
+/// records where a runtime error would be reported
+fn at(span: Span) -> String {
+    return format!("OX_AT({}, {})", span.line, span.column);
+}
+
+fn binary_function(op: BinaryOp) -> &'static str {
+    return match op {
+        BinaryOp::Add => "ox_add",
+        BinaryOp::Sub => "ox_sub",
+        BinaryOp::Mul => "ox_mul",
+        BinaryOp::Div => "ox_div",
+        BinaryOp::Rem => "ox_rem",
+        BinaryOp::Eq => "ox_eq",
+        BinaryOp::Ne => "ox_ne",
+        BinaryOp::Lt => "ox_lt",
+        BinaryOp::Gt => "ox_gt",
+        BinaryOp::Le => "ox_le",
+        BinaryOp::Ge => "ox_ge",
+    };
+}
+
+/// temps the lowering made up are `ox_`, which `c_identifier` keeps
+/// clear of oxide names
+fn name(var: &Var) -> String {
+    return if var.temp { format!("ox_{}", var.unique) } else { c_identifier(&var.unique) };
+}
+
+/// c keywords and names from the headers the runtime includes
+const RESERVED: [&str; 52] = [
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double",
+    "else", "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long",
+    "register", "restrict", "return", "short", "signed", "sizeof", "static", "struct",
+    "switch", "typedef", "union", "unsigned", "void", "volatile", "while", "main",
+    "exit", "free", "malloc", "realloc", "printf", "puts", "sprintf", "fprintf",
+    "fflush", "strlen", "strcmp", "strchr", "memcpy", "stdout", "stderr", "stdin", "NULL",
+];
+
+/// the name an oxide variable or proc goes by in the generated c. the
+/// runtime's names all start with `ox_`, so anything that could clash
+/// gets one more
+fn c_identifier(name: &str) -> String {
+    if RESERVED.contains(&name) || name.starts_with("ox_") || name.starts_with("OX_") {
+        return format!("ox_{name}");
+    }
+
+    return name.to_string();
+}
+
+/// the inside of a c string literal
+fn escape(text: &str) -> String {
+    let mut output = String::new();
+
+    for byte in text.bytes() {
+        match byte {
+            b'"' => output.push_str("\\\""),
+            b'\\' => output.push_str("\\\\"),
+            b'\n' => output.push_str("\\n"),
+            b'\t' => output.push_str("\\t"),
+            b' '..=b'~' => output.push(byte as char),
+            _ => output.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+
+    return output;
+}
@@ -0,0 +1,694 @@
+/* the oxide runtime for generated c99. every oxide value is an ox_value,
+   values are never changed once made, so they can share what they point
+   to, and nothing is ever freed. the one exception is a map in a variable
+   that `insert`s or `remove`s, which gets a copy of its own to change.
+   runtime errors print the oxide file, line and column set by OX_AT and
+   exit with 101 like a rust panic */
+
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* in the same order as the interpreter's values, so they sort the same */
+typedef enum {
+    OX_INT,
+    OX_STR,
+    OX_BOOL,
+    OX_TUPLE,
+    OX_OPT,
+    OX_RES,
+    OX_MAP,
+    OX_VEC,
+    OX_UNIT
+} ox_kind;
+
+typedef struct ox_value {
+    ox_kind kind;
+    /* an int or a bool, for an opt whether it's some, for a res whether it's ok */
+    int32_t i;
+    const char *s;
+    /* tuple fields, what's in a some, ok or fail, a map's keys and values
+       one after the other, or a vector's items */
+    struct ox_value *items;
+    /* how many fields or items, for a map how many entries */
+    size_t len;
+    /* how many entries a map has room for before it has to grow */
+    size_t cap;
+} ox_value;
+
+static const char *ox_file = "<source>";
+static int ox_line = 0;
+static int ox_col = 0;
+
+#define OX_FILE "<source>"
+#define OX_AT(line, col) (ox_file = OX_FILE, ox_line = (line), ox_col = (col))
+
+static void ox_panic(const char *message) {
+    fflush(stdout);
+    fprintf(stderr, "error: %s:%d:%d: %s\n", ox_file, ox_line, ox_col, message);
+    exit(101);
+}
+
+static ox_value *ox_alloc(size_t count) {
+    ox_value *items = malloc((count ? count : 1) * sizeof(ox_value));
+    if (!items) {
+        ox_panic("out of memory");
+    }
+
+    return items;
+}
+
+static ox_value ox_make(ox_kind kind, int32_t i, ox_value *items, size_t len) {
+    ox_value value;
+    value.kind = kind;
+    value.i = i;
+    value.s = "";
+    value.items = items;
+    value.len = len;
+    value.cap = len;
+    return value;
+}
+
+static ox_value ox_unit(void) { return ox_make(OX_UNIT, 0, NULL, 0); }
+static ox_value ox_int(int32_t i) { return ox_make(OX_INT, i, NULL, 0); }
+static ox_value ox_bool(int b) { return ox_make(OX_BOOL, b != 0, NULL, 0); }
+
+static ox_value ox_str(const char *s) {
+    ox_value value = ox_make(OX_STR, 0, NULL, 0);
+    value.s = s;
+    return value;
+}
+
+static ox_value ox_wrap(ox_kind kind, int32_t i, ox_value inner) {
+    ox_value *items = ox_alloc(1);
+    items[0] = inner;
+    return ox_make(kind, i, items, 1);
+}
+
+static ox_value ox_some(ox_value inner) { return ox_wrap(OX_OPT, 1, inner); }
+static ox_value ox_none(void) { return ox_make(OX_OPT, 0, NULL, 0); }
+static ox_value ox_ok(ox_value inner) { return ox_wrap(OX_RES, 1, inner); }
+static ox_value ox_fail(ox_value inner) { return ox_wrap(OX_RES, 0, inner); }
+
+static ox_value ox_tuple(size_t len, ...) {
+    ox_value *items = ox_alloc(len);
+    va_list args;
+    size_t i;
+
+    va_start(args, len);
+    for (i = 0; i < len; i++) {
+        items[i] = va_arg(args, ox_value);
+    }
+    va_end(args);
+
+    return ox_make(OX_TUPLE, 0, items, len);
+}
+
+static int ox_cmp(ox_value a, ox_value b) {
+    size_t i;
+    size_t count;
+
+    if (a.kind != b.kind) {
+        return a.kind < b.kind ? -1 : 1;
+    }
+
+    switch (a.kind) {
+        case OX_INT:
+        case OX_BOOL:
+            return a.i < b.i ? -1 : a.i > b.i;
+        case OX_STR: {
+            int order = strcmp(a.s, b.s);
+            return order < 0 ? -1 : order > 0;
+        }
+        case OX_OPT:
+        case OX_RES:
+            /* none before some, ok before fail */
+            if (a.i != b.i) {
+                return (a.kind == OX_OPT) == (a.i < b.i) ? -1 : 1;
+            }
+            break;
+        default:
+            break;
+    }
+
+    count = a.kind == OX_MAP ? 2 * a.len : a.len;
+    for (i = 0; i < count && i < (b.kind == OX_MAP ? 2 * b.len : b.len); i++) {
+        int order = ox_cmp(a.items[i], b.items[i]);
+        if (order != 0) {
+            return order;
+        }
+    }
+
+    return a.len < b.len ? -1 : a.len > b.len;
+}
+
+/* the index of the entry with the key, or where it would go */
+static size_t ox_map_find(ox_value map, ox_value key, int *found) {
+    size_t i;
+
+    for (i = 0; i < map.len; i++) {
+        int order = ox_cmp(map.items[2 * i], key);
+        if (order >= 0) {
+            *found = order == 0;
+            return i;
+        }
+    }
+
+    *found = 0;
+    return map.len;
+}
+
+static ox_value ox_map_get(ox_value map, ox_value key) {
+    int found;
+    size_t at = ox_map_find(map, key, &found);
+    return found ? ox_some(map.items[2 * at + 1]) : ox_none();
+}
+
+static ox_value ox_map_contains(ox_value map, ox_value key) {
+    int found;
+    ox_map_find(map, key, &found);
+    return ox_bool(found);
+}
+
+/* a map a variable can change without anything else seeing it */
+static ox_value ox_map_copy(ox_value map) {
+    ox_value *items = ox_alloc(2 * map.len);
+    memcpy(items, map.items, 2 * map.len * sizeof(ox_value));
+    return ox_make(OX_MAP, 0, items, map.len);
+}
+
+/* changes the map in place, it has to be one the variable owns */
+static ox_value ox_map_insert(ox_value *map, ox_value key, ox_value value) {
+    int found;
+    size_t at = ox_map_find(*map, key, &found);
+
+    if (found) {
+        ox_value old = ox_some(map->items[2 * at + 1]);
+        map->items[2 * at + 1] = value;
+        return old;
+    }
+
+    /* doubling keeps a run of inserts linear */
+    if (map->len == map->cap) {
+        map->cap = map->cap ? 2 * map->cap : 4;
+        map->items = realloc(map->items, 2 * map->cap * sizeof(ox_value));
+        if (!map->items) {
+            ox_panic("out of memory");
+        }
+    }
+
+    memmove(map->items + 2 * at + 2, map->items + 2 * at, 2 * (map->len - at) * sizeof(ox_value));
+    map->items[2 * at] = key;
+    map->items[2 * at + 1] = value;
+    map->len++;
+    return ox_none();
+}
+
+static ox_value ox_map_remove(ox_value *map, ox_value key) {
+    int found;
+    size_t at = ox_map_find(*map, key, &found);
+    ox_value old;
+
+    if (!found) {
+        return ox_none();
+    }
+
+    old = ox_some(map->items[2 * at + 1]);
+    memmove(map->items + 2 * at, map->items + 2 * at + 2, 2 * (map->len - at - 1) * sizeof(ox_value));
+    map->len--;
+    return old;
+}
+
+static ox_value ox_map(size_t len, ...) {
+    ox_value map = ox_make(OX_MAP, 0, NULL, 0);
+    va_list args;
+    size_t i;
+
+    va_start(args, len);
+    for (i = 0; i < len; i++) {
+        ox_value key = va_arg(args, ox_value);
+        ox_value value = va_arg(args, ox_value);
+        ox_map_insert(&map, key, value);
+    }
+    va_end(args);
+
+    return map;
+}
+
+static ox_value ox_map_items(ox_value map, size_t offset) {
+    ox_value *items = ox_alloc(map.len);
+    size_t i;
+
+    if (map.kind != OX_MAP) {
+        ox_panic("only maps have keys and values");
+    }
+
+    for (i = 0; i < map.len; i++) {
+        items[i] = map.items[2 * i + offset];
+    }
+
+    return ox_make(OX_VEC, 0, items, map.len);
+}
+
+static ox_value ox_map_keys(ox_value map) { return ox_map_items(map, 0); }
+static ox_value ox_map_values(ox_value map) { return ox_map_items(map, 1); }
+
+static ox_value ox_field(ox_value tuple, size_t index) {
+    if (tuple.kind != OX_TUPLE || index >= tuple.len) {
+        ox_panic("there's no field with that index");
+    }
+
+    return tuple.items[index];
+}
+
+/* the value in a some or an ok */
+static ox_value ox_unwrap(ox_value value) {
+    return value.items[0];
+}
+
+static int ox_is_some(ox_value value) {
+    if (value.kind != OX_OPT) {
+        ox_panic("`if let` only works on an `opt`");
+    }
+
+    return value.i;
+}
+
+static int ox_is_ok(ox_value value) {
+    if (value.kind != OX_RES) {
+        ox_panic("`?` only works on a `result`");
+    }
+
+    return value.i;
+}
+
+static int ox_cond(ox_value value) {
+    if (value.kind != OX_BOOL) {
+        ox_panic("conditions have to be a bool");
+    }
+
+    return value.i;
+}
+
+static int32_t ox_checked(int64_t result, const char *message) {
+    if (result < INT32_MIN || result > INT32_MAX) {
+        ox_panic(message);
+    }
+
+    return (int32_t) result;
+}
+
+static ox_value ox_add(ox_value a, ox_value b) {
+    if (a.kind == OX_STR && b.kind == OX_STR) {
+        size_t left = strlen(a.s);
+        size_t right = strlen(b.s);
+        char *joined = malloc(left + right + 1);
+
+        if (!joined) {
+            ox_panic("out of memory");
+        }
+
+        memcpy(joined, a.s, left);
+        memcpy(joined + left, b.s, right + 1);
+        return ox_str(joined);
+    }
+
+    return ox_int(ox_checked((int64_t) a.i + b.i, "attempt to add with overflow"));
+}
+
+static ox_value ox_sub(ox_value a, ox_value b) {
+    return ox_int(ox_checked((int64_t) a.i - b.i, "attempt to subtract with overflow"));
+}
+
+static ox_value ox_mul(ox_value a, ox_value b) {
+    return ox_int(ox_checked((int64_t) a.i * b.i, "attempt to multiply with overflow"));
+}
+
+static ox_value ox_div(ox_value a, ox_value b) {
+    if (b.i == 0) {
+        ox_panic("attempt to divide by zero");
+    }
+
+    return ox_int(ox_checked((int64_t) a.i / b.i, "attempt to divide with overflow"));
+}
+
+static ox_value ox_rem(ox_value a, ox_value b) {
+    if (b.i == 0) {
+        ox_panic("attempt to calculate the remainder with a divisor of zero");
+    }
+
+    if (a.i == INT32_MIN && b.i == -1) {
+        ox_panic("attempt to calculate the remainder with overflow");
+    }
+
+    return ox_int(a.i % b.i);
+}
+
+static ox_value ox_neg(ox_value a) {
+    return ox_int(ox_checked(-(int64_t) a.i, "attempt to negate with overflow"));
+}
+
+static ox_value ox_not(ox_value a) {
+    return a.kind == OX_BOOL ? ox_bool(!a.i) : ox_int(~a.i);
+}
+
+static ox_value ox_eq(ox_value a, ox_value b) { return ox_bool(ox_cmp(a, b) == 0); }
+static ox_value ox_ne(ox_value a, ox_value b) { return ox_bool(ox_cmp(a, b) != 0); }
+static ox_value ox_lt(ox_value a, ox_value b) { return ox_bool(ox_cmp(a, b) < 0); }
+static ox_value ox_gt(ox_value a, ox_value b) { return ox_bool(ox_cmp(a, b) > 0); }
+static ox_value ox_le(ox_value a, ox_value b) { return ox_bool(ox_cmp(a, b) <= 0); }
+static ox_value ox_ge(ox_value a, ox_value b) { return ox_bool(ox_cmp(a, b) >= 0); }
+
+/* what a for loop goes over, a map's items are key and value pairs */
+static ox_value ox_items(ox_value value) {
+    ox_value list;
+    size_t i;
+
+    if (value.kind == OX_VEC) {
+        return value;
+    }
+
+    if (value.kind != OX_MAP) {
+        ox_panic("only maps and vectors can be looped over");
+    }
+
+    list = ox_make(OX_VEC, 0, ox_alloc(value.len), value.len);
+    for (i = 0; i < value.len; i++) {
+        list.items[i] = ox_tuple(2, value.items[2 * i], value.items[2 * i + 1]);
+    }
+
+    return list;
+}
+
+static ox_value ox_len(ox_value list) { return ox_int((int32_t)list.len); }
+
+static ox_value ox_index(ox_value list, ox_value index) {
+    if (index.i < 0 || (size_t)index.i >= list.len) {
+        ox_panic("index out of bounds");
+    }
+
+    return list.items[index.i];
+}
+
+typedef struct {
+    char *data;
+    size_t len;
+    size_t cap;
+} ox_buffer;
+
+static void ox_push(ox_buffer *buffer, const char *text, size_t len) {
+    if (buffer->len + len + 1 > buffer->cap) {
+        buffer->cap = 2 * (buffer->len + len + 1);
+        buffer->data = realloc(buffer->data, buffer->cap);
+
+        if (!buffer->data) {
+            ox_panic("out of memory");
+        }
+    }
+
+    memcpy(buffer->data + buffer->len, text, len);
+    buffer->len += len;
+    buffer->data[buffer->len] = '\0';
+}
+
+static void ox_puts(ox_buffer *buffer, const char *text) {
+    ox_push(buffer, text, strlen(text));
+}
+
+/* `{}` when `debug` is 0 and `{:?}` otherwise, the same as rust prints them */
+static void ox_write(ox_buffer *buffer, ox_value value, int debug) {
+    char number[16];
+    const char *c;
+    size_t i;
+
+    switch (value.kind) {
+        case OX_INT:
+            sprintf(number, "%d", (int) value.i);
+            ox_puts(buffer, number);
+            return;
+        case OX_BOOL:
+            ox_puts(buffer, value.i ? "true" : "false");
+            return;
+        case OX_STR:
+            if (!debug) {
+                ox_puts(buffer, value.s);
+                return;
+            }
+
+            ox_puts(buffer, "\"");
+            for (c = value.s; *c; c++) {
+                switch (*c) {
+                    case '"': ox_puts(buffer, "\\\""); break;
+                    case '\\': ox_puts(buffer, "\\\\"); break;
+                    case '\n': ox_puts(buffer, "\\n"); break;
+                    case '\t': ox_puts(buffer, "\\t"); break;
+                    case '\r': ox_puts(buffer, "\\r"); break;
+                    default: ox_push(buffer, c, 1); break;
+                }
+            }
+            ox_puts(buffer, "\"");
+            return;
+        case OX_TUPLE:
+            ox_puts(buffer, "(");
+            for (i = 0; i < value.len; i++) {
+                if (i > 0) {
+                    ox_puts(buffer, ", ");
+                }
+                ox_write(buffer, value.items[i], 1);
+            }
+            ox_puts(buffer, value.len == 1 ? ",)" : ")");
+            return;
+        case OX_OPT:
+        case OX_RES:
+            if (value.kind == OX_OPT && !value.i) {
+                ox_puts(buffer, "None");
+                return;
+            }
+
+            ox_puts(buffer, value.kind == OX_OPT ? "Some(" : value.i ? "Ok(" : "Err(");
+            ox_write(buffer, value.items[0], 1);
+            ox_puts(buffer, ")");
+            return;
+        case OX_MAP:
+            ox_puts(buffer, "{");
+            for (i = 0; i < value.len; i++) {
+                if (i > 0) {
+                    ox_puts(buffer, ", ");
+                }
+                ox_write(buffer, value.items[2 * i], 1);
+                ox_puts(buffer, ": ");
+                ox_write(buffer, value.items[2 * i + 1], 1);
+            }
+            ox_puts(buffer, "}");
+            return;
+        case OX_VEC:
+            ox_puts(buffer, "[");
+            for (i = 0; i < value.len; i++) {
+                if (i > 0) {
+                    ox_puts(buffer, ", ");
+                }
+                ox_write(buffer, value.items[i], 1);
+            }
+            ox_puts(buffer, "]");
+            return;
+        case OX_UNIT:
+            ox_puts(buffer, "()");
+            return;
+    }
+}
+
+/* how many characters the first `len` bytes of utf-8 in `text` are */
+static size_t ox_chars(const char *text, size_t len) {
+    size_t count = 0, i;
+
+    for (i = 0; i < len; i++) {
+        count += ((unsigned char) text[i] & 0xC0) != 0x80;
+    }
+
+    return count;
+}
+
+/* where character `count` of the utf-8 in `text` starts */
+static size_t ox_char_at(const char *text, size_t count) {
+    size_t i = 0;
+
+    while (text[i] && count > 0) {
+        i++;
+        while (((unsigned char) text[i] & 0xC0) == 0x80) {
+            i++;
+        }
+        count--;
+    }
+
+    return i;
+}
+
+/* the spec after the `:` in `{0:*>5}`, the same parts `Spec` in the
+   compiler has */
+typedef struct {
+    const char *fill;
+    size_t fill_len;
+    char align;
+    int sign;
+    int zero;
+    size_t width;
+    long precision;
+    int debug;
+} ox_spec;
+
+static void ox_parse_spec(const char *c, ox_spec *spec) {
+    size_t fill_len = ox_char_at(c, 1);
+
+    spec->fill = " ";
+    spec->fill_len = 1;
+    spec->align = 0;
+    spec->sign = 0;
+    spec->zero = 0;
+    spec->width = 0;
+    spec->precision = -1;
+    spec->debug = 0;
+
+    if (*c == '?') {
+        spec->debug = 1;
+        return;
+    }
+
+    if (c[fill_len] && strchr("<^>", c[fill_len])) {
+        spec->fill = c;
+        spec->fill_len = fill_len;
+        spec->align = c[fill_len];
+        c += fill_len + 1;
+    } else if (*c && strchr("<^>", *c)) {
+        spec->align = *c++;
+    }
+
+    if (*c == '+') {
+        spec->sign = 1;
+        c++;
+    }
+    if (*c == '0') {
+        spec->zero = 1;
+        c++;
+    }
+    while (*c >= '0' && *c <= '9') {
+        spec->width = 10 * spec->width + (size_t)(*c++ - '0');
+    }
+    if (*c == '.') {
+        spec->precision = 0;
+        for (c++; *c >= '0' && *c <= '9'; c++) {
+            spec->precision = 10 * spec->precision + (*c - '0');
+        }
+    }
+}
+
+/* writes `value` the way rust pads and cuts it for `spec` */
+static void ox_write_spec(ox_buffer *buffer, ox_value value, const ox_spec *spec) {
+    ox_buffer text = { NULL, 0, 0 };
+    const char *digits;
+    const char *sign = "";
+    size_t len, length, padding, before, after, i;
+    int number = value.kind == OX_INT;
+    char align;
+
+    ox_puts(&text, "");
+    ox_write(&text, value, spec->debug);
+    digits = text.data;
+    len = text.len;
+
+    if (number && digits[0] == '-') {
+        sign = "-";
+        digits++;
+        len--;
+    } else if (number && spec->sign) {
+        sign = "+";
+    } else if (!number && spec->precision >= 0) {
+        len = ox_char_at(digits, (size_t) spec->precision);
+    }
+
+    length = strlen(sign) + ox_chars(digits, len);
+    padding = spec->width > length ? spec->width - length : 0;
+    align = spec->align ? spec->align : number ? '>' : '<';
+
+    if (number && spec->zero) {
+        before = 0;
+        after = 0;
+        ox_puts(buffer, sign);
+        for (i = 0; i < padding; i++) {
+            ox_puts(buffer, "0");
+        }
+        sign = "";
+    } else {
+        before = align == '<' ? 0 : align == '^' ? padding / 2 : padding;
+        after = padding - before;
+    }
+
+    for (i = 0; i < before; i++) {
+        ox_push(buffer, spec->fill, spec->fill_len);
+    }
+    ox_puts(buffer, sign);
+    ox_push(buffer, digits, len);
+    for (i = 0; i < after; i++) {
+        ox_push(buffer, spec->fill, spec->fill_len);
+    }
+
+    free(text.data);
+}
+
+/* `format` only has `{{`, `}}` and `{0}` with an optional spec, the
+   generator numbers every argument */
+static void ox_print(const char *format, size_t count, ...) {
+    ox_value *args = ox_alloc(count);
+    ox_buffer line = { NULL, 0, 0 };
+    const char *c;
+    va_list list;
+    size_t i;
+
+    va_start(list, count);
+    for (i = 0; i < count; i++) {
+        args[i] = va_arg(list, ox_value);
+    }
+    va_end(list);
+
+    ox_puts(&line, "");
+
+    for (c = format; *c; c++) {
+        if ((c[0] == '{' && c[1] == '{') || (c[0] == '}' && c[1] == '}')) {
+            ox_push(&line, c, 1);
+            c++;
+        } else if (c[0] == '{') {
+            char *end;
+            size_t index = strtoul(c + 1, &end, 10);
+            ox_spec spec;
+
+            if (index >= count) {
+                ox_panic("nothing to fill in an argument with");
+            }
+
+            ox_parse_spec(end[0] == ':' ? end + 1 : "", &spec);
+            ox_write_spec(&line, args[index], &spec);
+            c = strchr(end, '}');
+        } else {
+            ox_push(&line, c, 1);
+        }
+    }
+
+    puts(line.data);
+    free(line.data);
+    free(args);
+}
+
+/* what a program with `proc main` exits with */
+static int ox_exit_code(ox_value result) {
+    ox_buffer message = { NULL, 0, 0 };
+
+    if (result.kind != OX_RES || result.i) {
+        return 0;
+    }
+
+    ox_write(&message, result.items[0], 0);
+    fflush(stdout);
+    fprintf(stderr, "error: %s\n", message.data);
+    return 1;
+}
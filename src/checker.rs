@@ -0,0 +1,517 @@
+use crate::expression::{Expr, parse_expression};
+use crate::ir::{self, constant};
+use crate::parser::{Parsed, Span, FunctionCall, VariableDeclare, Destructure, IfLet, ForLoop, split_top_level};
+
+/// names in scope mapped to their rust type, innermost scope last
+struct Scopes {
+    scopes: Vec<Vec<(String, String)>>,
+    /// every `const` in the file, seen from every scope
+    consts: Vec<(String, String)>,
+}
+
+impl Scopes {
+    fn declare(&mut self, name: &str, type_class: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name.to_string(), type_class.to_string()));
+        }
+    }
+
+    /// declared at the top level and not shadowed by anything closer
+    fn is_top_level(&self, name: &str) -> bool {
+        let shadowed = self.scopes.iter().skip(1).any(|scope| scope.iter().any(|(declared, _)| declared == name));
+        return !shadowed && self.scopes[0].iter().any(|(declared, _)| declared == name);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&String> {
+        for scope in self.scopes.iter().rev() {
+            for (declared, type_class) in scope.iter().rev() {
+                if declared == name {
+                    return Some(type_class);
+                }
+            }
+        }
+
+        return self.consts.iter().find(|(declared, _)| declared == name).map(|(_, type_class)| type_class);
+    }
+}
+
+/// `module` is for code that's `include!`d into a rust crate, which can
+/// only declare procs
+pub fn checker(parsed: &[(Span, Parsed)], module: bool) -> Vec<(Span, String)> {
+    let mut errors = Vec::new();
+    let mut scopes = Scopes { scopes: vec![Vec::new()], consts: Vec::new() };
+    // the value of each `const` checked so far, a `const` can only use the ones before it
+    let mut consts: Vec<(String, ir::Expr)> = Vec::new();
+    let mut procs: Vec<(String, Vec<String>, String)> = Vec::new();
+    let mut current_return: Option<String> = None;
+    let mut has_main = false;
+    let mut first_top_level: Option<Span> = None;
+
+    for (_, elem) in parsed {
+        if let Parsed::FunctionDeclare(declare) = elem {
+            let types = declare.params().into_iter().map(|(_, type_class)| type_class).collect();
+            procs.push((declare.name.to_owned(), types, declare.return_type.to_owned()));
+        }
+
+        if let Parsed::Const(VariableDeclare { name, type_class, .. }) = elem {
+            scopes.consts.push((name.to_owned(), type_class.to_owned()));
+        }
+    }
+
+    let returns_result = |current_return: &Option<String>| {
+        current_return.as_ref().is_some_and(|type_class| type_class.starts_with("Result<"))
+    };
+
+    for (span, elem) in parsed {
+        let mut used_methods = Vec::new();
+
+        match elem {
+            Parsed::FunctionDeclare(declare) => has_main = has_main || declare.name == "main",
+            Parsed::RSquirly => (),
+            Parsed::Return(_) if current_return.is_none() => errors.push((*span, String::from("`return` can only be used inside a proc"))),
+            Parsed::Import(_) if current_return.is_some() => errors.push((*span, String::from("`import` has to be outside of any proc"))),
+            Parsed::Import(_) => (),
+            Parsed::Const(_) => (),
+            _ if current_return.is_none() => first_top_level = first_top_level.or(Some(*span)),
+            _ => (),
+        }
+
+        let expression = match elem {
+            Parsed::VariableDeclare(VariableDeclare { value, .. }) | Parsed::Destructure(Destructure { value, .. }) |
+            Parsed::Return(value) | Parsed::If(value) | Parsed::OrIf(value) |
+            Parsed::IfLet(IfLet { value, .. }) | Parsed::FunctionCall(FunctionCall { parameters: value, .. }) |
+            Parsed::For(ForLoop { iterable: value, .. }) => value.to_owned(),
+            Parsed::Print(body) => split_top_level(body).iter().skip(1).cloned().collect::<Vec<String>>().join(","),
+            _ => String::new(),
+        };
+
+        let propagates = matches!(elem, Parsed::FunctionCall(FunctionCall { propagate: true, .. }));
+        if (propagates || has_question(&expression)) && !returns_result(&current_return) {
+            errors.push((*span, String::from("`?` can only be used inside a proc that returns `result`")));
+        }
+
+        // procs can't see names declared outside of them, the top level becomes a local `fn main`
+        if current_return.is_some() {
+            for ident in identifiers(&expression) {
+                if scopes.is_top_level(&ident) {
+                    errors.push((*span, format!("`{ident}` is declared outside of any proc so procs can't use it, pass it in as a parameter")));
+                }
+            }
+        }
+
+        // a `const` is seen from everywhere, so nothing else can have its name
+        let mut declared = elem.names();
+        if let Parsed::FunctionDeclare(declare) = elem {
+            declared.push(declare.name.to_owned());
+        }
+
+        for name in declared {
+            if scopes.consts.iter().any(|(declared, _)| *declared == name) {
+                errors.push((*span, format!("`{name}` is already a `const`, pick another name")));
+            }
+        }
+
+        match elem {
+            Parsed::VariableDeclare(VariableDeclare { value, .. }) | Parsed::Return(value) |
+            Parsed::Print(value) | Parsed::If(value) | Parsed::OrIf(value) => used_methods = method_calls(value),
+            Parsed::FunctionCall(FunctionCall { name, parameters, .. }) => {
+                used_methods = method_calls(&format!("{name}({parameters})"));
+            },
+            _ => (),
+        }
+
+        for (receiver, method) in used_methods {
+            match scopes.lookup(&receiver) {
+                Some(found) if map_types(found).is_some() && !MAP_METHODS.contains(&method.as_str()) => {
+                    errors.push((*span, format!("maps have no `{method}`, try one of {}", MAP_METHODS.join(", "))));
+                },
+                Some(found) if map_types(found).is_some() => (),
+                Some(found) => errors.push((*span, format!("`{receiver}` is `{}` which has no `{method}`", oxide_type(found)))),
+                None => (),
+            }
+        }
+
+        match elem {
+            Parsed::VariableDeclare(VariableDeclare { type_class, name, value }) => {
+                if let Some(found) = call_type(value, &scopes, &procs) {
+                    if option_inner(&found).is_some() && option_inner(type_class).is_none() {
+                        errors.push((*span, format!("`{}` gives `{}` and must be unwrapped before it can be used as `{}`", value.trim(), oxide_type(&found), oxide_type(type_class))));
+                    }
+                }
+
+                if let Some((proc_name, returned)) = called_proc(value, &procs) {
+                    if returned.starts_with("Result<") && returned != type_class && !has_question(value) {
+                        errors.push((*span, format!("`{proc_name}` returns `{}`, use `?` to get the value out for `{name}`", oxide_type(returned))));
+                    }
+                }
+
+                if let Some((tuple, field)) = value.trim().split_once('.') {
+                    let elements = scopes.lookup(tuple).and_then(|found| tuple_types(found));
+
+                    if let (Some(elements), Ok(index)) = (elements, field.parse::<usize>()) {
+                        match elements.get(index) {
+                            Some(element) if element != type_class => {
+                                errors.push((*span, format!("`{}` is `{}` but `{name}` is `{}`", value.trim(), oxide_type(element), oxide_type(type_class))));
+                            },
+                            Some(_) => (),
+                            None => errors.push((*span, format!("`{tuple}` has no field `{index}`, it only has {}", elements.len()))),
+                        }
+                    }
+                }
+
+                if let Some(inner) = option_inner(type_class) {
+                    let value = value.trim();
+
+                    if value.parse::<i32>().is_ok() || value.starts_with('"') || value == "true" || value == "false" {
+                        errors.push((*span, format!("`{name}` is `{}` but was given a plain `{}`, wrap it with `some(...)`", oxide_type(type_class), oxide_type(inner))));
+                    } else if let Some(found) = scopes.lookup(value) {
+                        if found != type_class {
+                            errors.push((*span, format!("`{name}` is `{}` but `{value}` is `{}`", oxide_type(type_class), oxide_type(found))));
+                        }
+                    }
+                } else {
+                    for (ident, found) in optional(value, &scopes) {
+                        errors.push((*span, format!("`{ident}` is `{found}` and must be unwrapped before it can be used as `{}`", oxide_type(type_class))));
+                    }
+                }
+
+                scopes.declare(name, type_class);
+            },
+            Parsed::Destructure(Destructure { names, value }) => {
+                let value = value.trim();
+                let found = match called_proc(value, &procs) {
+                    Some((_, returned)) => Some(returned.to_owned()),
+                    None => scopes.lookup(value).cloned(),
+                };
+                let mut elements = Vec::new();
+
+                if let Some(found) = found {
+                    match tuple_types(&found) {
+                        Some(types) if types.len() != names.len() => {
+                            errors.push((*span, format!("`{value}` has {} values but {} names were given", types.len(), names.len())));
+                        },
+                        Some(types) => elements = types,
+                        None => errors.push((*span, format!("`{value}` is `{}`, only tuples can be destructured", oxide_type(&found)))),
+                    }
+                }
+
+                for (index, name) in names.iter().enumerate() {
+                    let type_class = elements.get(index).cloned().unwrap_or_default();
+                    scopes.declare(name, &type_class);
+                }
+            },
+            Parsed::FunctionDeclare(declare) => {
+                current_return = Some(declare.return_type.to_owned());
+                scopes.scopes.push(Vec::new());
+                for (name, type_class) in declare.params() {
+                    scopes.declare(&name, &type_class);
+                }
+            },
+            Parsed::FunctionCall(FunctionCall { name, .. }) if name.contains('.') => (),
+            Parsed::FunctionCall(FunctionCall { name, parameters, .. }) => {
+                let expected = procs.iter()
+                    .find(|(proc_name, _, _)| proc_name == name)
+                    .map(|(_, types, _)| types.clone())
+                    .unwrap_or_default();
+
+                for (index, arg) in split_top_level(parameters).iter().enumerate() {
+                    let arg = arg.trim();
+                    let wanted = expected.get(index);
+
+                    if let Some(found) = scopes.lookup(arg) {
+                        if option_inner(found).is_some() && wanted.is_some_and(|wanted| wanted != found) {
+                            errors.push((*span, format!("`{arg}` is `{}` and must be unwrapped before it can be passed to `{name}`", oxide_type(found))));
+                        }
+                    }
+                }
+            },
+            Parsed::Print(body) => {
+                for arg in split_top_level(body).iter().skip(1) {
+                    for ident in identifiers(arg) {
+                        if let Some(found) = scopes.lookup(&ident) {
+                            if option_inner(found).is_some() {
+                                errors.push((*span, format!("`{ident}` is `{}` and must be unwrapped before it can be printed", oxide_type(found))));
+                            } else if tuple_types(found).is_some() && arg.trim() == ident {
+                                errors.push((*span, format!("`{ident}` is `{}`, print its fields like `{ident}.0` instead", oxide_type(found))));
+                            }
+                        }
+                    }
+                }
+            },
+            Parsed::Return(value) => {
+                let value = value.trim();
+
+                if (value.starts_with("ok(") || value.starts_with("fail(")) && !returns_result(&current_return) {
+                    errors.push((*span, format!("`return {value}` needs the proc to return a `result`")));
+                }
+
+                // a proc that gives back an `opt`, even inside a `result`, can return one as it is
+                if let Some(returned) = current_return.as_ref().filter(|returned| !returned.is_empty() && !returned.contains("Option<")) {
+                    for (ident, found) in optional(value, &scopes) {
+                        errors.push((*span, format!("`{ident}` is `{found}` and must be unwrapped before it can be returned as `{}`", oxide_type(returned))));
+                    }
+                }
+            },
+            Parsed::IfLet(IfLet { binding, value }) => {
+                let value = value.trim();
+                let inner = match scopes.lookup(value) {
+                    Some(found) => match option_inner(found) {
+                        Some(inner) => inner.to_string(),
+                        None => {
+                            errors.push((*span, format!("`{value}` is `{}`, only `opt` values can be unwrapped", oxide_type(found))));
+                            found.to_owned()
+                        },
+                    },
+                    None => String::new(),
+                };
+
+                scopes.scopes.push(Vec::new());
+                scopes.declare(binding, &inner);
+            },
+            Parsed::For(ForLoop { bindings, iterable }) => {
+                let iterable = iterable.trim();
+                let mut types = Vec::new();
+
+                if let Some((receiver, method)) = iterable.strip_suffix("()").and_then(|call| call.split_once('.')) {
+                    if let Some((key, value)) = scopes.lookup(receiver).and_then(|found| map_types(found)) {
+                        match method {
+                            "keys" => types.push(key),
+                            "values" => types.push(value),
+                            _ => errors.push((*span, format!("`{iterable}` can't be looped over, try `{receiver}.keys()` or `{receiver}.values()`"))),
+                        }
+                    }
+                } else if let Some(found) = scopes.lookup(iterable) {
+                    match map_types(found) {
+                        Some((key, value)) => types.extend([key, value]),
+                        None => errors.push((*span, format!("`{iterable}` is `{}`, only maps can be looped over", oxide_type(found)))),
+                    }
+                }
+
+                let names: Vec<&str> = bindings.split(',').map(|binding| binding.trim()).collect();
+                if types.len() == 2 && names.len() != 2 {
+                    errors.push((*span, format!("looping over `{iterable}` gives a key and a value, write `for (key, value) in {iterable}`")));
+                }
+
+                scopes.scopes.push(Vec::new());
+                for (index, binding) in names.iter().enumerate() {
+                    let type_class = types.get(index).cloned().unwrap_or_default();
+                    scopes.declare(binding, &type_class);
+                }
+            },
+            Parsed::If(condition) | Parsed::OrIf(condition) => {
+                // `y == none` and `y == some(3)` compare an `opt` with an `opt`
+                if !has_word(condition, &["none", "some"]) {
+                    for (ident, found) in optional(condition, &scopes) {
+                        errors.push((*span, format!("`{ident}` is `{found}` and must be unwrapped before it can be used in a condition, try `if let some(value) = {ident}`")));
+                    }
+                }
+
+                scopes.scopes.push(Vec::new());
+            },
+            Parsed::Else => scopes.scopes.push(Vec::new()),
+            Parsed::RSquirly => {
+                if scopes.scopes.len() > 1 {
+                    scopes.scopes.pop();
+                }
+
+                if scopes.scopes.len() == 1 {
+                    current_return = None;
+                }
+            },
+            Parsed::Import(_) => (),
+            Parsed::Const(VariableDeclare { type_class, name, value }) => {
+                if scopes.scopes.len() > 1 {
+                    errors.push((*span, String::from("`const` has to be outside of any proc or block")));
+                }
+
+                if !matches!(type_class.as_str(), "i32" | "String" | "bool") {
+                    errors.push((*span, format!("a `const` can only be `int`, `string` or `bool`, not `{}`", oxide_type(type_class))));
+                    continue;
+                }
+
+                if consts.iter().any(|(declared, _)| declared == name) {
+                    errors.push((*span, format!("there's already a `const` called `{name}`")));
+                    continue;
+                }
+
+                match constant(value, &consts) {
+                    Ok(found) if found.type_class() != *type_class => {
+                        errors.push((*span, format!("`{name}` is `{}` but its value is `{}`", oxide_type(type_class), oxide_type(&found.type_class()))));
+                    },
+                    Ok(found) => consts.push((name.to_owned(), found)),
+                    Err(message) => errors.push((*span, format!("`{name}` needs a value that's known before the program runs: {message}"))),
+                }
+            },
+        }
+    }
+
+    if let (true, Some(span)) = (module, first_top_level) {
+        errors.push((span, String::from("statements outside of a proc can't go in a module, move them into a proc")));
+    } else if let (true, Some(span)) = (has_main, first_top_level) {
+        errors.push((span, String::from("statements outside of a proc can't be mixed with `proc main`, move them into main")));
+    }
+
+    return errors;
+}
+
+/// every variable in an expression that's an `opt`, along with its oxide type
+fn optional(expr: &str, scopes: &Scopes) -> Vec<(String, String)> {
+    return identifiers(expr).into_iter()
+        .filter_map(|ident| scopes.lookup(&ident).filter(|found| option_inner(found).is_some()).map(|found| (ident, oxide_type(found))))
+        .collect();
+}
+
+/// whether an expression uses the `?` operator outside of a string
+fn has_question(expr: &str) -> bool {
+    let mut inside_quotes = false;
+
+    for c in expr.chars() {
+        match c {
+            '"' => inside_quotes = !inside_quotes,
+            '?' if !inside_quotes => return true,
+            _ => (),
+        }
+    }
+
+    return false;
+}
+
+/// the proc called by a value like `parse(x)?` and what it returns
+fn called_proc<'a>(value: &str, procs: &'a [(String, Vec<String>, String)]) -> Option<(&'a String, &'a String)> {
+    let (callee, _) = value.trim().split_once('(')?;
+
+    return procs.iter()
+        .find(|(proc_name, _, _)| proc_name == callee.trim())
+        .map(|(proc_name, _, returned)| (proc_name, returned));
+}
+
+const MAP_METHODS: [&str; 6] = ["get", "insert", "remove", "contains", "keys", "values"];
+
+/// every `receiver.method(` in an expression
+fn method_calls(expr: &str) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let mut word = String::new();
+    let mut inside_quotes = false;
+
+    for c in expr.chars() {
+        if inside_quotes {
+            inside_quotes = c != '"';
+        } else if c.is_alphanumeric() || c == '_' || c == '.' {
+            word.push(c);
+        } else {
+            if c == '(' {
+                if let Some((receiver, method)) = word.rsplit_once('.') {
+                    found.push((receiver.to_string(), method.to_string()));
+                }
+            }
+
+            word.clear();
+            inside_quotes = c == '"';
+        }
+    }
+
+    return found;
+}
+
+/// the rust type of a value that is a single call, to a proc in the same
+/// file or a map method
+fn call_type(value: &str, scopes: &Scopes, procs: &[(String, Vec<String>, String)]) -> Option<String> {
+    return match parse_expression(value)? {
+        Expr::Call(name, _) => procs.iter().find(|(proc_name, _, _)| *proc_name == name).map(|(_, _, returned)| returned.to_owned()),
+        Expr::Method(..) => method_type(value, scopes),
+        _ => None,
+    };
+}
+
+/// the rust type of a value that is a single map method call
+fn method_type(value: &str, scopes: &Scopes) -> Option<String> {
+    let (callee, _) = value.trim().split_once('(')?;
+    let (receiver, method) = callee.split_once('.')?;
+    let (_, value_type) = map_types(scopes.lookup(receiver)?)?;
+
+    return match method {
+        "get" | "remove" => Some(format!("Option<{value_type}>")),
+        "contains" => Some(String::from("bool")),
+        _ => None,
+    };
+}
+
+/// the key and value types of a map
+pub(crate) fn map_types(type_class: &str) -> Option<(String, String)> {
+    let inner = type_class.strip_prefix("HashMap<").and_then(|t| t.strip_suffix('>'))?;
+    let types = split_top_level(inner);
+
+    return match types.as_slice() {
+        [key, value] => Some((key.trim().to_string(), value.trim().to_string())),
+        _ => None,
+    };
+}
+
+/// the element types of a tuple
+pub(crate) fn tuple_types(type_class: &str) -> Option<Vec<String>> {
+    let inner = type_class.strip_prefix('(').and_then(|t| t.strip_suffix(')'))?;
+    return Some(split_top_level(inner).iter().map(|t| t.trim().to_string()).collect());
+}
+
+pub(crate) fn option_inner(type_class: &str) -> Option<&str> {
+    return type_class.strip_prefix("Option<").and_then(|t| t.strip_suffix('>'));
+}
+
+/// turns a rust type from the parser back into how it's written in oxide
+pub(crate) fn oxide_type(type_class: &str) -> String {
+    return type_class
+        .replace("Option<", "opt<")
+        .replace("Result<", "result<")
+        .replace("HashMap<", "map<")
+        .replace("String", "string")
+        .replace("i32", "int");
+}
+
+/// whether any of `wanted` is a whole word of an expression outside of
+/// its strings
+fn has_word(expr: &str, wanted: &[&str]) -> bool {
+    let mut word = String::new();
+    let mut inside_quotes = false;
+
+    for c in expr.chars().chain(std::iter::once(' ')) {
+        if inside_quotes {
+            inside_quotes = c != '"';
+        } else if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            if wanted.contains(&word.as_str()) {
+                return true;
+            }
+
+            word.clear();
+            inside_quotes = c == '"';
+        }
+    }
+
+    return false;
+}
+
+/// names referenced by an expression, skipping string literals and keywords
+fn identifiers(expr: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut word = String::new();
+    let mut inside_quotes = false;
+
+    for c in expr.chars().chain(std::iter::once(' ')) {
+        if inside_quotes {
+            inside_quotes = c != '"';
+        } else if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            let starts_alpha = word.chars().next().is_some_and(|first| first.is_alphabetic() || first == '_');
+            if starts_alpha && !matches!(word.as_str(), "some" | "none" | "ok" | "fail" | "true" | "false") {
+                found.push(word.clone());
+            }
+
+            word.clear();
+            inside_quotes = c == '"';
+        }
+    }
+
+    return found;
+}
@@ -0,0 +1,217 @@
+use oxide::Target;
+
+/// what to do with the source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Check,
+    /// report code that's likely a mistake
+    Lint,
+    Build,
+    Run,
+    /// run with the interpreter, no rustc needed
+    Interp,
+    /// compile to bytecode and run it on the vm
+    Vm,
+    /// read and run oxide a line at a time
+    Repl,
+    Fmt,
+    Emit,
+    Remap,
+    New,
+}
+
+/// which stage `oxide emit` prints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    /// the lowered ir every backend is written from
+    Ir,
+    Rust,
+    /// the vm's instructions
+    Bytecode,
+    /// a cargo project around the generated rust
+    Cargo,
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    /// the oxide file, `-` reads it from stdin. the project name for `oxide new`
+    pub input: String,
+    pub output: Option<String>,
+    pub emit: Emit,
+    pub target: Target,
+    pub quiet: bool,
+    pub verbose: bool,
+    pub ordered_maps: bool,
+    pub optimise: bool,
+    /// `check` and `lint` fail when there are warnings
+    pub deny_warnings: bool,
+    pub rustfmt: bool,
+    pub source_map: bool,
+    /// the generated rust `oxide remap` reads the markers from
+    pub remap: Option<String>,
+    /// everything after the input, handed to the program by `oxide run`
+    pub program_args: Vec<String>,
+}
+
+pub const USAGE: &str = "usage: oxide <command> [options] <file.oxi | ->
+       oxide new <name>
+       oxide repl
+
+commands:
+    check           check the source without generating anything
+    lint            report code that's likely a mistake, a `# allow(code)`
+                    comment turns a warning off for its line or proc
+    build           compile to a binary with rustc, or cc for --target=c
+    run             build then run, passing on any arguments after the file
+    interp          run with the built-in interpreter instead of rustc
+    vm              run on the bytecode vm instead of rustc
+    repl            run oxide interactively
+    fmt             reindent the source in place
+    emit            print a stage of the compiler, rust by default
+    remap <gen.rs>  point rustc output on stdin at the oxide source
+    new <name>      start a cargo project written in oxide
+
+options:
+    -o, --output <path>       where to write the output
+    --emit=tokens|ast|rust    the stage `emit` prints
+    --emit=ir                 the lowered ir every backend is written from
+    --emit=bytecode           the instructions `vm` runs
+    --emit=cargo              write a cargo project next to the source,
+                              with the source copied to src/main.oxi.
+                              after that only the two are rewritten
+    --target=rust|c|js|wat    the language to generate, c is built with
+                              $CC or cc, js is an es module run with node,
+                              wat is webassembly text for int and bool code
+    --ordered-maps            back map<K, V> with a BTreeMap
+    -O                        fold constants and drop code that can never
+                              run, arithmetic that always fails is an error
+    --deny-warnings           make `check` and `lint` fail on warnings
+    --rustfmt                 run the generated rust through rustfmt
+    --source-map              keep the // ox:line:col markers
+    -q, --quiet               only print errors
+    -v, --verbose             print the tokens and ast while compiling
+";
+
+/// exit codes, anything a program run by `oxide run` exits with is passed on as is
+pub const EXIT_ERRORS: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+
+pub fn parse_args(args: &[String]) -> Result<Args, String> {
+    let command = match args.first().map(String::as_str) {
+        Some("check") => Command::Check,
+        Some("lint") => Command::Lint,
+        Some("build") => Command::Build,
+        Some("run") => Command::Run,
+        Some("interp") => Command::Interp,
+        Some("vm") => Command::Vm,
+        Some("repl") => Command::Repl,
+        Some("fmt") => Command::Fmt,
+        Some("emit") => Command::Emit,
+        Some("remap") => Command::Remap,
+        Some("new") => Command::New,
+        Some(other) => return Err(format!("unknown command `{other}`")),
+        None => return Err(String::from("no command given")),
+    };
+
+    let mut parsed = Args {
+        command,
+        input: String::new(),
+        output: None,
+        emit: Emit::Rust,
+        target: Target::Rust,
+        quiet: false,
+        verbose: false,
+        ordered_maps: false,
+        optimise: false,
+        deny_warnings: false,
+        rustfmt: false,
+        source_map: false,
+        remap: None,
+        program_args: Vec::new(),
+    };
+
+    let mut inputs = Vec::new();
+    let mut i = 1;
+
+    while i < args.len() {
+        let arg = args[i].as_str();
+
+        match arg {
+            "-o" | "--output" => {
+                i += 1;
+                match args.get(i) {
+                    Some(output) => parsed.output = Some(output.clone()),
+                    None => return Err(format!("{arg} needs a path")),
+                }
+            },
+            "-q" | "--quiet" => parsed.quiet = true,
+            "-v" | "--verbose" => parsed.verbose = true,
+            "--ordered-maps" => parsed.ordered_maps = true,
+            "-O" => parsed.optimise = true,
+            "--deny-warnings" => parsed.deny_warnings = true,
+            "--rustfmt" => parsed.rustfmt = true,
+            "--source-map" => parsed.source_map = true,
+            "--" => {
+                parsed.program_args.extend(args[i + 1..].iter().cloned());
+                break;
+            },
+            _ if arg.starts_with("--emit=") => {
+                parsed.emit = match &arg["--emit=".len()..] {
+                    "tokens" => Emit::Tokens,
+                    "ast" => Emit::Ast,
+                    "ir" => Emit::Ir,
+                    "rust" => Emit::Rust,
+                    "bytecode" => Emit::Bytecode,
+                    "cargo" => Emit::Cargo,
+                    other => return Err(format!("unknown stage `{other}`, expected tokens, ast, ir, rust, bytecode or cargo")),
+                };
+            },
+            _ if arg.starts_with("--target=") => {
+                parsed.target = match &arg["--target=".len()..] {
+                    "rust" => Target::Rust,
+                    "c" => Target::C,
+                    "js" => Target::Js,
+                    "wat" => Target::Wat,
+                    other => return Err(format!("unknown target `{other}`, expected rust, c, js or wat")),
+                };
+            },
+            _ if arg.starts_with("--output=") => parsed.output = Some(arg["--output=".len()..].to_string()),
+            _ if arg.starts_with('-') && arg != "-" => return Err(format!("unknown option `{arg}`")),
+            _ => {
+                inputs.push(arg.to_string());
+
+                // the program gets everything after its source
+                if command == Command::Run {
+                    parsed.program_args.extend(args[i + 1..].iter().cloned());
+                    break;
+                }
+            },
+        }
+
+        i += 1;
+    }
+
+    let mut inputs = inputs.into_iter();
+    parsed.input = match inputs.next() {
+        Some(input) => input,
+        None if command == Command::New => return Err(String::from("no project name given")),
+        None if command == Command::Repl => String::new(),
+        None => return Err(String::from("no input file given")),
+    };
+
+    if command == Command::Remap {
+        parsed.remap = inputs.next();
+        if parsed.remap.is_none() {
+            return Err(String::from("remap needs the generated rust file"));
+        }
+    }
+
+    if let Some(extra) = inputs.next() {
+        return Err(format!("unexpected argument `{extra}`"));
+    }
+
+    return Ok(parsed);
+}
@@ -0,0 +1,120 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use crate::parser::Span;
+use crate::sourcemap::MARKER;
+
+const INDENT: &str = "    ";
+
+/// builds up generated source a line at a time, keeping track of how
+/// deeply nested the current block is and which oxide statement each
+/// line came from
+pub struct Emitter {
+    output: String,
+    indent: usize,
+    span: Option<Span>,
+}
+
+impl Emitter {
+    pub fn new() -> Emitter {
+        return Emitter { output: String::new(), indent: 0, span: None };
+    }
+
+    /// the oxide statement the following lines are generated from
+    pub fn at(&mut self, span: Option<Span>) {
+        self.span = span;
+    }
+
+    fn marker(&self) -> String {
+        return match self.span {
+            Some(span) => format!("{MARKER}{}:{}", span.line, span.column),
+            None => String::new(),
+        };
+    }
+
+    /// a single statement on its own line
+    pub fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+
+        self.output.push_str(text);
+        self.output.push_str(&self.marker());
+        self.output.push('\n');
+    }
+
+    /// a line ending in `{`, everything after it is indented
+    pub fn open(&mut self, text: &str) {
+        self.line(text);
+        self.indent += 1;
+    }
+
+    /// the `}` of the innermost block
+    pub fn close(&mut self) {
+        self.close_with("}");
+    }
+
+    /// ends the innermost block with `text` instead of `}`
+    pub fn close_with(&mut self, text: &str) {
+        let span = self.span.take();
+        self.indent = self.indent.saturating_sub(1);
+        self.line(text);
+        self.span = span;
+    }
+
+    /// a line like `else` that ends one block and starts the next
+    pub fn reopen(&mut self, text: &str) {
+        self.indent = self.indent.saturating_sub(1);
+        self.open(text);
+    }
+
+    /// `else {` carries on from the `}` before it, giving `} else {`
+    pub fn chain(&mut self, text: &str) {
+        if self.output.ends_with("}\n") {
+            self.output.pop();
+            self.output.push(' ');
+            self.output.push_str(text);
+            self.output.push_str(&self.marker());
+            self.output.push('\n');
+            self.indent += 1;
+        } else {
+            self.open(text);
+        }
+    }
+
+    pub fn blank_line(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    pub fn finish(self) -> String {
+        return self.output;
+    }
+}
+
+/// runs the source through rustfmt if it's installed, leaving it as it
+/// was if it isn't or if rustfmt doesn't like it
+pub fn rustfmt(source: String) -> String {
+    let child = Command::new("rustfmt")
+        .args(["--edition", "2021"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => return source,
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        if stdin.write_all(source.as_bytes()).is_err() {
+            return source;
+        }
+    }
+
+    match child.wait_with_output() {
+        Ok(output) if output.status.success() => String::from_utf8(output.stdout).unwrap_or(source),
+        _ => source,
+    }
+}
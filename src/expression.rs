@@ -0,0 +1,575 @@
+//! the expressions and format strings inside oxide statements. the
+//! parser keeps these as text, everything after it parses them here
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// a piece of a format string
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Text(String),
+    /// `{0}`, and `{}` numbered the way rust does
+    Index(usize, Spec),
+    /// `{name}`
+    Name(String, Spec),
+}
+
+/// what's after the `:` in `{:>5}`, the parts of rust's format spec that
+/// every backend can follow. `{:?}` can't be combined with any of the rest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spec {
+    pub fill: char,
+    pub align: Option<Align>,
+    /// `+`, a sign in front of positive numbers too
+    pub sign: bool,
+    /// `0`, numbers are padded with zeros after their sign
+    pub zero: bool,
+    pub width: Option<usize>,
+    /// cuts strings and bools short, numbers ignore it
+    pub precision: Option<usize>,
+    pub debug: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Default for Spec {
+    fn default() -> Spec {
+        return Spec { fill: ' ', align: None, sign: false, zero: false, width: None, precision: None, debug: false };
+    }
+}
+
+impl Spec {
+    /// the text after the `:`, an error for anything not every backend
+    /// can follow
+    fn parse(text: &str) -> Result<Spec, String> {
+        let unsupported = || format!("`{{:{text}}}` isn't supported, a format can have a fill and alignment, `+`, `0`, a width and a precision, or be `{{:?}}`");
+        let mut spec = Spec::default();
+
+        if text == "?" {
+            spec.debug = true;
+            return Ok(spec);
+        }
+
+        let chars: Vec<char> = text.chars().collect();
+        let align = |c: Option<&char>| match c {
+            Some('<') => Some(Align::Left),
+            Some('^') => Some(Align::Center),
+            Some('>') => Some(Align::Right),
+            _ => None,
+        };
+
+        let mut i = 0;
+        if let Some(found) = align(chars.get(1)).filter(|_| chars[0] != '{') {
+            spec.fill = chars[0];
+            spec.align = Some(found);
+            i = 2;
+        } else if let Some(found) = align(chars.first()) {
+            spec.align = Some(found);
+            i = 1;
+        }
+
+        if chars.get(i) == Some(&'+') {
+            spec.sign = true;
+            i += 1;
+        }
+
+        if chars.get(i) == Some(&'0') {
+            spec.zero = true;
+            i += 1;
+        }
+
+        let digits = |i: &mut usize| -> Option<usize> {
+            let start = *i;
+            while chars.get(*i).is_some_and(char::is_ascii_digit) {
+                *i += 1;
+            }
+
+            return chars[start..*i].iter().collect::<String>().parse().ok();
+        };
+
+        spec.width = digits(&mut i);
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            spec.precision = Some(digits(&mut i).ok_or_else(unsupported)?);
+        }
+
+        if i != chars.len() {
+            return Err(unsupported());
+        }
+
+        return Ok(spec);
+    }
+
+    /// `text` padded and cut the way rust would, `number` is set for an
+    /// `int`'s digits, which are signed and padded with zeros rather than cut
+    pub fn pad(&self, text: &str, number: bool) -> String {
+        let mut text = text.to_string();
+        let mut sign = String::new();
+
+        if number {
+            if text.starts_with('-') {
+                sign = text.remove(0).to_string();
+            } else if self.sign {
+                sign = String::from("+");
+            }
+        } else if let Some(precision) = self.precision {
+            text = text.chars().take(precision).collect();
+        }
+
+        let length = sign.chars().count() + text.chars().count();
+        let padding = self.width.unwrap_or(0).saturating_sub(length);
+
+        if number && self.zero {
+            return format!("{sign}{}{text}", "0".repeat(padding));
+        }
+
+        let default = if number { Align::Right } else { Align::Left };
+        let (before, after) = match self.align.unwrap_or(default) {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+
+        let fill = |count: usize| self.fill.to_string().repeat(count);
+        return format!("{}{sign}{text}{}", fill(before), fill(after));
+    }
+}
+
+impl fmt::Display for Spec {
+    /// the spec back as it'd be written after the `:`, empty for `{}`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(align) = self.align {
+            let symbol = match align {
+                Align::Left => '<',
+                Align::Center => '^',
+                Align::Right => '>',
+            };
+
+            if self.fill != ' ' {
+                write!(f, "{}", self.fill)?;
+            }
+
+            write!(f, "{symbol}")?;
+        }
+
+        if self.sign {
+            write!(f, "+")?;
+        }
+        if self.zero {
+            write!(f, "0")?;
+        }
+        if let Some(width) = self.width {
+            write!(f, "{width}")?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{precision}")?;
+        }
+        if self.debug {
+            write!(f, "?")?;
+        }
+
+        return Ok(());
+    }
+}
+
+/// the pieces of a format string, an error for a spec that isn't supported
+pub(crate) fn parse_format(format: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut next = 0;
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            },
+            '{' => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+
+                let inside: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let (argument, spec) = inside.split_once(':').unwrap_or((&inside, ""));
+                let spec = if spec.is_empty() { Spec::default() } else { Spec::parse(spec)? };
+
+                pieces.push(if argument.is_empty() {
+                    next += 1;
+                    Piece::Index(next - 1, spec)
+                } else if let Ok(index) = argument.parse::<usize>() {
+                    Piece::Index(index, spec)
+                } else {
+                    Piece::Name(argument.to_string(), spec)
+                });
+            },
+            _ => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+
+    return Ok(pieces);
+}
+
+/// a print's format string back as it'd be written. `numbered` gives
+/// `{0}` and `{0:>5}` for every argument, what the c and js runtimes
+/// understand, otherwise arguments in order are written `{}`
+pub(crate) fn format_string(pieces: &[Piece], numbered: bool) -> String {
+    let mut next = 0;
+    let in_order = pieces.iter().all(|piece| match piece {
+        Piece::Index(index, _) => {
+            next += 1;
+            *index == next - 1
+        },
+        _ => true,
+    });
+
+    let spec = |spec: &Spec| if *spec == Spec::default() { String::new() } else { format!(":{spec}") };
+
+    return pieces.iter().map(|piece| match piece {
+        Piece::Text(text) => text.replace('{', "{{").replace('}', "}}"),
+        Piece::Index(index, found) => {
+            let index = if numbered || !in_order { index.to_string() } else { String::new() };
+            format!("{{{index}{}}}", spec(found))
+        },
+        Piece::Name(name, found) => format!("{{{name}{}}}", spec(found)),
+    }).collect();
+}
+
+/// an arithmetic or comparison operator, `&&` and `||` short circuit
+/// so they're kept apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        return match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::Le => "<=",
+            BinaryOp::Ge => ">=",
+        };
+    }
+
+    pub(crate) fn from_symbol(symbol: &str) -> Option<BinaryOp> {
+        return [
+            BinaryOp::Add, BinaryOp::Sub, BinaryOp::Mul, BinaryOp::Div, BinaryOp::Rem, BinaryOp::Eq,
+            BinaryOp::Ne, BinaryOp::Lt, BinaryOp::Gt, BinaryOp::Le, BinaryOp::Ge,
+        ].into_iter().find(|op| op.symbol() == symbol);
+    }
+
+    /// `left op right` for arithmetic, failing the same way rust's debug
+    /// builds do. `None` for a comparison
+    pub(crate) fn arithmetic(self, left: i32, right: i32) -> Option<Result<i32, &'static str>> {
+        let (result, message) = match self {
+            BinaryOp::Add => (left.checked_add(right), "attempt to add with overflow"),
+            BinaryOp::Sub => (left.checked_sub(right), "attempt to subtract with overflow"),
+            BinaryOp::Mul => (left.checked_mul(right), "attempt to multiply with overflow"),
+            BinaryOp::Div if right == 0 => (None, "attempt to divide by zero"),
+            BinaryOp::Div => (left.checked_div(right), "attempt to divide with overflow"),
+            BinaryOp::Rem if right == 0 => (None, "attempt to calculate the remainder with a divisor of zero"),
+            BinaryOp::Rem => (left.checked_rem(right), "attempt to calculate the remainder with overflow"),
+            _ => return None,
+        };
+
+        return Some(result.ok_or(message));
+    }
+
+    /// whether a comparison holds for two values that compare as
+    /// `ordering`. `None` for arithmetic
+    pub(crate) fn compare(self, ordering: Ordering) -> Option<bool> {
+        return match self {
+            BinaryOp::Eq => Some(ordering.is_eq()),
+            BinaryOp::Ne => Some(ordering.is_ne()),
+            BinaryOp::Lt => Some(ordering.is_lt()),
+            BinaryOp::Gt => Some(ordering.is_gt()),
+            BinaryOp::Le => Some(ordering.is_le()),
+            BinaryOp::Ge => Some(ordering.is_ge()),
+            _ => None,
+        };
+    }
+}
+
+/// an expression as it's written, before anything knows its types
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    Int(i32),
+    Str(String),
+    Bool(bool),
+    Name(String),
+    Tuple(Vec<Expr>),
+    Map(Vec<(Expr, Expr)>),
+    /// `double(2)`, `math::double(2)`, `some(2)`
+    Call(String, Vec<Expr>),
+    /// `ages.get("amy")`
+    Method(Box<Expr>, String, Vec<Expr>),
+    /// `pair.0`
+    Field(Box<Expr>, usize),
+    Question(Box<Expr>),
+    Unary(char, Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Lexeme {
+    Int(i32),
+    Str(String),
+    Name(String),
+    Symbol(String),
+}
+
+const SYMBOLS: [&str; 22] = [
+    "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">",
+    "!", "(", ")", "{", "}", ",", ":", ".", "?",
+];
+
+fn lex(text: &str) -> Option<Vec<Lexeme>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut lexemes = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+
+            let digits: String = chars[start..i].iter().collect();
+            lexemes.push(Lexeme::Int(digits.parse().ok()?));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || (chars[i] == ':' && chars.get(i + 1) == Some(&':'))) {
+                i += if chars[i] == ':' { 2 } else { 1 };
+            }
+
+            lexemes.push(Lexeme::Name(chars[start..i].iter().collect()));
+        } else if c == '"' {
+            let mut value = String::new();
+            i += 1;
+
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    i += 1;
+                    value.push(match chars[i] {
+                        'n' => '\n',
+                        't' => '\t',
+                        other => other,
+                    });
+                } else {
+                    value.push(chars[i]);
+                }
+
+                i += 1;
+            }
+
+            lexemes.push(Lexeme::Str(value));
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol))?;
+            lexemes.push(Lexeme::Symbol(symbol.to_string()));
+            i += symbol.len();
+        }
+    }
+
+    return Some(lexemes);
+}
+
+pub(crate) fn parse_expression(text: &str) -> Option<Expr> {
+    let lexemes = lex(text)?;
+    let mut parser = ExprParser { lexemes, position: 0 };
+    let expr = parser.expression(0)?;
+
+    if parser.position != parser.lexemes.len() {
+        return None;
+    }
+
+    return Some(expr);
+}
+
+struct ExprParser {
+    lexemes: Vec<Lexeme>,
+    position: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Lexeme> {
+        return self.lexemes.get(self.position);
+    }
+
+    fn next(&mut self) -> Option<Lexeme> {
+        let lexeme = self.lexemes.get(self.position).cloned();
+        self.position += 1;
+        return lexeme;
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if self.peek() == Some(&Lexeme::Symbol(symbol.to_string())) {
+            self.position += 1;
+            return true;
+        }
+
+        return false;
+    }
+
+    fn expect(&mut self, symbol: &str) -> Option<()> {
+        return self.eat(symbol).then_some(());
+    }
+
+    /// binary operators, tightest last
+    fn precedence(op: &str) -> Option<u8> {
+        return match op {
+            "||" => Some(1),
+            "&&" => Some(2),
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => Some(3),
+            "+" | "-" => Some(4),
+            "*" | "/" | "%" => Some(5),
+            _ => None,
+        };
+    }
+
+    fn expression(&mut self, min: u8) -> Option<Expr> {
+        let mut left = self.unary()?;
+
+        while let Some(Lexeme::Symbol(op)) = self.peek().cloned() {
+            let Some(precedence) = ExprParser::precedence(&op) else {
+                break;
+            };
+
+            if precedence <= min {
+                break;
+            }
+
+            self.position += 1;
+            let right = self.expression(precedence)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+
+        return Some(left);
+    }
+
+    fn unary(&mut self) -> Option<Expr> {
+        for op in ['-', '!'] {
+            if self.eat(&op.to_string()) {
+                return Some(Expr::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.eat("?") {
+                expr = Expr::Question(Box::new(expr));
+            } else if self.eat(".") {
+                match self.next()? {
+                    Lexeme::Int(index) => expr = Expr::Field(Box::new(expr), index as usize),
+                    Lexeme::Name(method) => {
+                        self.expect("(")?;
+                        let args = self.list(")")?;
+                        expr = Expr::Method(Box::new(expr), method, args);
+                    },
+                    _ => return None,
+                }
+            } else {
+                return Some(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Option<Expr> {
+        match self.next()? {
+            Lexeme::Int(value) => return Some(Expr::Int(value)),
+            Lexeme::Str(value) => return Some(Expr::Str(value)),
+            Lexeme::Name(name) if name == "true" => return Some(Expr::Bool(true)),
+            Lexeme::Name(name) if name == "false" => return Some(Expr::Bool(false)),
+            Lexeme::Name(name) => {
+                if self.eat("(") {
+                    return Some(Expr::Call(name, self.list(")")?));
+                }
+
+                return Some(Expr::Name(name));
+            },
+            Lexeme::Symbol(symbol) if symbol == "(" => {
+                let mut items = self.list(")")?;
+
+                // `(a)` is just `a`, `(a,)` and `(a, b)` are tuples
+                let trailing_comma = self.lexemes.get(self.position - 2) == Some(&Lexeme::Symbol(String::from(",")));
+                if items.len() == 1 && !trailing_comma {
+                    return items.pop();
+                }
+
+                return Some(Expr::Tuple(items));
+            },
+            Lexeme::Symbol(symbol) if symbol == "{" => {
+                let mut entries = Vec::new();
+
+                while !self.eat("}") {
+                    let key = self.expression(0)?;
+                    self.expect(":")?;
+                    let value = self.expression(0)?;
+                    entries.push((key, value));
+
+                    if !self.eat(",") {
+                        self.expect("}")?;
+                        break;
+                    }
+                }
+
+                return Some(Expr::Map(entries));
+            },
+            _ => return None,
+        }
+    }
+
+    /// comma separated expressions up to `close`
+    fn list(&mut self, close: &str) -> Option<Vec<Expr>> {
+        let mut items = Vec::new();
+
+        while !self.eat(close) {
+            items.push(self.expression(0)?);
+
+            if !self.eat(",") {
+                self.expect(close)?;
+                break;
+            }
+        }
+
+        return Some(items);
+    }
+}
@@ -0,0 +1,63 @@
+const INDENT: &str = "    ";
+
+/// reindents oxide source by how deeply nested each line is, trims
+/// trailing whitespace and squashes runs of blank lines into one
+pub fn format(source: &str) -> String {
+    let mut output = String::new();
+    let mut depth: usize = 0;
+    let mut blank = false;
+
+    for line in source.lines() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            if !blank && !output.is_empty() {
+                output.push('\n');
+            }
+
+            blank = true;
+            continue;
+        }
+
+        blank = false;
+
+        let (opens, closes) = braces(line);
+        let leading = if line.starts_with('}') { 1 } else { 0 };
+        let indent = depth.saturating_sub(leading);
+
+        for _ in 0..indent {
+            output.push_str(INDENT);
+        }
+
+        output.push_str(line);
+        output.push('\n');
+
+        depth = (depth + opens).saturating_sub(closes);
+    }
+
+    // no blank line at the end of the file
+    while output.ends_with("\n\n") {
+        output.pop();
+    }
+
+    return output;
+}
+
+/// the squirlies on a line that aren't in a string or a comment
+fn braces(line: &str) -> (usize, usize) {
+    let mut inside_quotes = false;
+    let mut opens = 0;
+    let mut closes = 0;
+
+    for c in line.chars() {
+        match c {
+            '"' => inside_quotes = !inside_quotes,
+            '#' if !inside_quotes => break,
+            '{' if !inside_quotes => opens += 1,
+            '}' if !inside_quotes => closes += 1,
+            _ => (),
+        }
+    }
+
+    return (opens, closes);
+}
@@ -1,78 +1,447 @@
-use crate::parser::{Parsed, FunctionCall, VariableDeclare};
-
-pub fn generator(parsed: Vec<Parsed>) -> String {
-    let mut gen = String::new();
-
-    for elem in parsed {
-        match elem {
-            Parsed::VariableDeclare(VariableDeclare { type_class, name, value }) => {
-                let mut to_rust = String::new();
-
-                if type_class == String::from("String") {
-                    to_rust = format!("let {name}: {type_class} = String::from(\"{value}\");\n");
-                } else if type_class == String::from("i32") {
-                    to_rust = format!("let {name}: {type_class} = {value};\n");
-                } else if type_class == String::from("bool") {
-                    to_rust = format!("let {name}: {type_class} = {value};")
+use crate::Options;
+use crate::backend::{Backend, Linked, Target};
+use crate::c_backend::C;
+use crate::js_backend::Js;
+use crate::wat_backend::Wat;
+use crate::emitter::Emitter;
+use crate::sourcemap::tag_markers;
+use crate::expression::{BinaryOp, format_string};
+use crate::naming::rust_identifier;
+use crate::parser::Span;
+use crate::ir::{self, Body, Cond, Const, Expr, ForEach, Function, Item, Method, Stmt, Var, infix, operand};
+
+/// what the generated rust is going to be part of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    /// a whole program, with a `fn main`
+    Program,
+    /// a file imported by another, becomes a `mod` where only `pub proc`s are `pub`
+    Import,
+    /// a module for a rust crate to `include!`, every proc is `pub`
+    Include,
+}
+
+/// every line generated from a statement ends with a `// ox:line:col`
+/// marker, see `sourcemap`. `module` is the name an imported file is
+/// imported as
+pub fn generator(lowered: &ir::Module, options: &Options, unit: Unit, module: Option<&str>) -> String {
+    let mut backend = backend(options, unit, module);
+    let mut gen = Emitter::new();
+
+    // procs, imports and `const`s live at module level, anything else outside a proc goes into `fn main`
+    for (index, item) in lowered.items.iter().enumerate() {
+        match item {
+            Item::Function(function) => {
+                gen.blank_line();
+                gen.at(Some(function.span));
+                backend.function(&mut gen, function);
+            },
+            Item::Import(span, name) => {
+                gen.at(Some(*span));
+                backend.import(&mut gen, name);
+            },
+            Item::Const(constant) => {
+                // `const`s one after another stay together
+                if index == 0 || !matches!(lowered.items[index - 1], Item::Const(_)) {
+                    gen.blank_line();
                 }
 
-                gen.push_str(&to_rust);
+                gen.at(Some(constant.span));
+                backend.constant(&mut gen, constant);
             },
-            Parsed::FunctionDeclare(declare) => {
-                let content_change = vec![
-                    (String::from("string"), String::from("String")),
-                    (String::from("int"), String::from("i32")),
-                    (String::from("bool"), String::from("bool")),
-                ];
+        }
+    }
 
-                let re_params = declare.sanitise_params(content_change);
+    gen.at(None);
+    return backend.finish(gen, &lowered.top_level);
+}
 
-                let to_rust = format!("fn {}({re_params}) {{", declare.name);
-                gen.push_str(&to_rust);
-            },
-            Parsed::FunctionCall(FunctionCall { name, parameters }) => {
-                let mut output = String::new();
-                let mut inside_quotes = false;
-                let mut word_start = 0;
-
-                for (i, c) in parameters.char_indices() {
-                    if c == '"' {
-                        inside_quotes = !inside_quotes;
-                        if inside_quotes {
-                            output.push_str("String::from(\"");
-                            word_start = i + 1;
-                        } else {
-                            let word = &parameters[word_start..i];
-                            output.push_str(word);
-                            output.push_str("\")");
-                        }
-                    } else if !inside_quotes {
-                        output.push(c);
-                    }
+/// puts an entry file's generated source together with its imports
+pub(crate) fn link(options: &Options, entry: String, entry_path: &str, imports: Vec<Linked>) -> String {
+    return backend(options, Unit::Program, None).link(entry, entry_path, imports);
+}
+
+fn backend(options: &Options, unit: Unit, module: Option<&str>) -> Box<dyn Backend> {
+    return match options.target {
+        Target::Rust => Box::new(Rust { ordered_maps: options.ordered_maps, unit, has_main: false, main_fails: false }),
+        Target::C => Box::new(C::new(unit, module)),
+        Target::Js => Box::new(Js::new(unit, module)),
+        Target::Wat => Box::new(Wat::new(unit, module)),
+    };
+}
+
+struct Rust {
+    ordered_maps: bool,
+    unit: Unit,
+    has_main: bool,
+    main_fails: bool,
+}
+
+impl Rust {
+    fn statements(&mut self, out: &mut Emitter, body: &[(Span, Stmt)]) {
+        let mut index = 0;
+
+        while index < body.len() {
+            let (span, stmt) = &body[index];
+            out.at(Some(*span));
+
+            if let Some(for_each) = ir::for_each(&body[index..]) {
+                self.for_each(out, &for_each);
+                index += 3;
+                continue;
+            }
+
+            self.statement(out, stmt);
+            index += 1;
+        }
+    }
+
+    /// a `for` loop as a rust `for`, rather than the index the ir keeps
+    fn for_each(&mut self, out: &mut Emitter, for_each: &ForEach) {
+        let pattern = match for_each.binding {
+            Stmt::Unpack(vars, _) => format!("({})", vars.iter().map(binding).collect::<Vec<String>>().join(", ")),
+            Stmt::Let(var, _) => binding(var),
+            _ => return,
+        };
+
+        let items = match for_each.items {
+            // looping over a variable shouldn't move it
+            Expr::Items(inner) if matches!(inner.as_ref(), Expr::Local(_)) => format!("{}.clone()", expr(inner)),
+            // keys and values are collected first, so the loop can change the map
+            Expr::Items(inner) if !matches!(inner.as_ref(), Expr::Method(Method::Keys | Method::Values, ..)) => expr(inner),
+            items => expr(items),
+        };
+
+        out.open(&format!("for {pattern} in {items} {{"));
+        self.statements(out, for_each.body);
+        out.close();
+    }
+
+    fn statement(&mut self, out: &mut Emitter, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let(var, value) if var.type_class.is_empty() => out.line(&format!("let {} = {};", binding(var), expr(value))),
+            Stmt::Let(var, value) => out.line(&format!("let {}: {} = {};", binding(var), var.type_class, expr(value))),
+            Stmt::Unpack(vars, value) => {
+                let mut tuple = expr(value);
+
+                // destructuring a tuple by name shouldn't move it
+                if let Expr::Local(_) = value {
+                    tuple.push_str(".clone()");
                 }
 
-                let to_rust = format!("{name}({output});");
-                gen.push_str(&to_rust);
+                let names: Vec<String> = vars.iter().map(binding).collect();
+                out.line(&format!("let ({}) = {tuple};", names.join(", ")));
             },
-            Parsed::Print(body) => {
-                let to_rust = format!("println!({body});");
-                gen.push_str(&to_rust);
+            Stmt::Assign(var, value) => out.line(&format!("{} = {};", name(var), expr(value))),
+            Stmt::Expr(value) => out.line(&format!("{};", expr(value))),
+            Stmt::Print(pieces, args) => {
+                let mut args: Vec<String> = args.iter().map(expr).collect();
+                args.insert(0, format!("{:?}", format_string(pieces, false)));
+                out.line(&format!("println!({});", args.join(", ")));
             },
-            Parsed::RSquirly(_) => gen.push_str("}"),
-            Parsed::If(body) => {
-                let to_rust = format!("if {body} {{");
-                gen.push_str(&to_rust);
+            Stmt::Return(Some(value)) => out.line(&format!("return {};", expr(value))),
+            Stmt::Return(None) => out.line("return;"),
+            Stmt::If(..) => self.branch(out, stmt, false),
+            Stmt::Loop(body) => {
+                out.open("loop {");
+                self.statements(out, body);
+                out.close();
             },
-            Parsed::OrIf(body) => {
-                let to_rust = format!("else if {body} {{");
-                gen.push_str(&to_rust);
+            Stmt::Break => out.line("break;"),
+            Stmt::Block(body) => {
+                out.open("{");
+                self.statements(out, body);
+                out.close();
             },
-            Parsed::Else => {
-                let to_rust = format!("else {{");
-                gen.push_str(&to_rust);
+        }
+    }
+
+    /// an `if`, `chained` when it's all there is in the `else` before it
+    fn branch(&mut self, out: &mut Emitter, stmt: &Stmt, chained: bool) {
+        let Stmt::If(condition, then, otherwise, else_at) = stmt else {
+            return;
+        };
+
+        let head = match condition {
+            Cond::Bool(condition) => format!("if {} {{", expr(condition)),
+            Cond::Some(var, value) => format!("if let Some({}) = {}.clone() {{", binding(var), operand(value, expr(value), false)),
+        };
+
+        if chained {
+            out.chain(&format!("else {head}"));
+        } else {
+            out.open(&head);
+        }
+
+        self.statements(out, then);
+        out.close();
+
+        match otherwise.as_slice() {
+            [] => (),
+            [(_, stmt @ Stmt::If(..))] => {
+                out.at(*else_at);
+                self.branch(out, stmt, true);
+            },
+            _ => {
+                out.at(*else_at);
+                out.chain("else {");
+                self.statements(out, otherwise);
+                out.close();
             },
         }
     }
+}
+
+impl Backend for Rust {
+    fn function(&mut self, out: &mut Emitter, function: &Function) {
+        let params: Vec<String> = function.params.iter().map(|param| format!("{}: {}", binding(param), param.type_class)).collect();
+        let mut name = rust_identifier(&function.name);
+        let mut returns = String::new();
+
+        if !function.returns.is_empty() {
+            returns = format!(" -> {}", function.returns);
+        }
+
+        // a failing main is wrapped so the error is printed and the exit code is set
+        if name == "main" && self.unit == Unit::Program {
+            self.has_main = true;
+
+            if function.returns.starts_with("Result<") {
+                name = String::from("oxide_main");
+                self.main_fails = true;
+            }
+        }
+
+        // procs in an included module are called from the crate around it
+        let visibility = if function.public || self.unit == Unit::Include { "pub " } else { "" };
+        out.open(&format!("{visibility}fn {name}({}){returns} {{", params.join(", ")));
+        self.statements(out, &function.body);
+        out.close();
+    }
+
+    /// a `String` can't be made in a rust `const`, so a string is a `&str`
+    /// and anything working with one is written as what it works out to
+    fn constant(&mut self, out: &mut Emitter, constant: &Const) {
+        let mut strings = false;
+        constant.value.visit(&mut |expr| strings = strings || expr.type_class() == "String");
+
+        let (type_class, value) = match (&constant.literal, strings) {
+            (Expr::Str(value), _) => (String::from("&str"), format!("{value:?}")),
+            (literal, true) => (constant.type_class.clone(), expr(literal)),
+            (_, false) => (constant.type_class.clone(), expr(&constant.value)),
+        };
+
+        out.line(&format!("const {}: {type_class} = {value};", rust_identifier(&constant.name)));
+    }
+
+    /// an imported file's module sits next to this one, the program root already sees them all
+    fn import(&mut self, out: &mut Emitter, name: &str) {
+        if self.unit != Unit::Program {
+            out.line(&format!("use super::{};", rust_identifier(name)));
+        }
+    }
+
+    fn finish(&mut self, mut gen: Emitter, top_level: &Body) -> String {
+        // top level statements run from a generated main, the checker has
+        // already rejected them if there's a `proc main` as well
+        if !self.has_main && self.unit == Unit::Program {
+            gen.blank_line();
+            gen.open("fn main() {");
+            self.statements(&mut gen, top_level);
+            gen.at(None);
+            gen.close();
+        }
+
+        if self.main_fails {
+            gen.blank_line();
+            gen.open("fn main() {");
+            gen.open("if let Err(error) = oxide_main() {");
+            gen.line("eprintln!(\"error: {}\", error);");
+            gen.line("std::process::exit(1);");
+            gen.close();
+            gen.close();
+        }
+
+        let gen = gen.finish();
+
+        if gen.contains("HashMap") && self.ordered_maps {
+            return format!("use std::collections::BTreeMap;\n\n{}", ordered(&gen));
+        }
+
+        if gen.contains("HashMap") {
+            return format!("use std::collections::HashMap;\n\n{gen}");
+        }
+
+        return gen;
+    }
+
+    /// each import becomes a `mod` named after it
+    fn link(&self, mut entry: String, _: &str, imports: Vec<Linked>) -> String {
+        for import in imports {
+            let body = tag_markers(&import.source, &import.path);
+
+            entry.push_str(&format!("\nmod {} {{\n", rust_identifier(&import.name)));
+            for line in body.lines() {
+                if !line.is_empty() {
+                    entry.push_str("    ");
+                    entry.push_str(line);
+                }
+
+                entry.push('\n');
+            }
+            entry.push_str("}\n");
+        }
+
+        return entry;
+    }
+}
+
+/// the rust with every `HashMap` a `BTreeMap`, leaving string literals
+/// as they are
+fn ordered(rust: &str) -> String {
+    let mut output = String::new();
+    let mut word = String::new();
+    let (mut quoted, mut escaped) = (false, false);
+
+    for c in rust.chars().chain(std::iter::once('\0')) {
+        if !quoted && (c.is_alphanumeric() || c == '_') {
+            word.push(c);
+            continue;
+        }
+
+        output.push_str(if word == "HashMap" { "BTreeMap" } else { &word });
+        word.clear();
+
+        if quoted {
+            quoted = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else {
+            quoted = c == '"';
+        }
+
+        if c != '\0' {
+            output.push(c);
+        }
+    }
+
+    return output;
+}
+
+/// temps the lowering made up are `ox_`, which no oxide name can be once
+/// it's been through `rust_identifier`'s mangling
+fn name(var: &Var) -> String {
+    return if var.temp { format!("ox_{}", var.unique) } else { rust_identifier(&var.name) };
+}
+
+/// a variable where it's declared
+fn binding(var: &Var) -> String {
+    return if var.mutable { format!("mut {}", name(var)) } else { name(var) };
+}
+
+/// an oxide value as rust: strings are owned `String`s, `some`/`none`
+/// are `Some`/`None`, `ok`/`fail` are `Ok`/`Err`, map literals are
+/// `HashMap`s and map methods are their std equivalents
+fn expr(value: &Expr) -> String {
+    let list = |items: &[Expr]| items.iter().map(expr).collect::<Vec<String>>().join(", ");
+    // map methods borrow their argument
+    let borrow = |arg: &Expr| format!("&{}", operand(arg, expr(arg), true));
+
+    return match value {
+        Expr::Int(value) => value.to_string(),
+        Expr::Str(value) => format!("String::from({value:?})"),
+        Expr::Bool(value) => value.to_string(),
+        Expr::None => String::from("None"),
+        Expr::Local(var) => name(var),
+        Expr::Const(name, value) if matches!(value.as_ref(), Expr::Str(_)) => format!("String::from({})", rust_identifier(name)),
+        Expr::Const(name, _) => rust_identifier(name),
+        Expr::Tuple(items) if items.len() == 1 => format!("({},)", expr(&items[0])),
+        Expr::Tuple(items) => format!("({})", list(items)),
+        Expr::Map(entries) if entries.is_empty() => String::from("HashMap::new()"),
+        Expr::Map(entries) => {
+            let entries: Vec<String> = entries.iter().map(|(key, value)| format!("({}, {})", expr(key), expr(value))).collect();
+            format!("HashMap::from([{}])", entries.join(", "))
+        },
+        Expr::Some(inner) => format!("Some({})", expr(inner)),
+        Expr::Ok(inner) => format!("Ok({})", expr(inner)),
+        Expr::Fail(inner) => format!("Err({})", expr(inner)),
+        Expr::Call(callee, args, _) => {
+            let path: Vec<String> = callee.split("::").map(rust_identifier).collect();
+            format!("{}({})", path.join("::"), list(args))
+        },
+        Expr::Method(method, receiver, args) => {
+            let receiver = operand(receiver, expr(receiver), false);
+
+            match (method, args.as_slice()) {
+                (Method::Get, [key]) => format!("{receiver}.get({}).cloned()", borrow(key)),
+                (Method::Insert, [key, value]) => format!("{receiver}.insert({}, {})", expr(key), expr(value)),
+                (Method::Remove, [key]) => format!("{receiver}.remove({})", borrow(key)),
+                (Method::Contains, [key]) => format!("{receiver}.contains_key({})", borrow(key)),
+                (Method::Keys, _) => format!("{receiver}.keys().cloned()"),
+                (Method::Values, _) => format!("{receiver}.values().cloned()"),
+                (method, _) => format!("{receiver}.{}({})", method.name(), list(args)),
+            }
+        },
+        // reading a tuple field shouldn't move it out of the tuple
+        Expr::Field(tuple, index) => format!("{}.{index}.clone()", operand(tuple, expr(tuple), false)),
+        Expr::Try(inner) => format!("{}?", operand(inner, expr(inner), false)),
+        Expr::Neg(inner) => format!("-{}", operand(inner, expr(inner), true)),
+        Expr::Not(inner) => format!("!{}", operand(inner, expr(inner), true)),
+        // `String + String` doesn't borrow the right side, so strings are joined with `format!`
+        Expr::Binary(BinaryOp::Add, left, right) if value.type_class() == "String" => {
+            format!("format!(\"{{}}{{}}\", {}, {})", expr(left), expr(right))
+        },
+        Expr::Binary(op, left, right) => infix(value, op.symbol(), left, right, &mut expr),
+        Expr::And(left, right) => infix(value, "&&", left, right, &mut expr),
+        Expr::Or(left, right) => infix(value, "||", left, right, &mut expr),
+        // iterating a map by name shouldn't move it
+        Expr::Items(inner) if matches!(inner.as_ref(), Expr::Method(Method::Keys | Method::Values, ..)) => {
+            format!("{}.collect::<Vec<_>>()", expr(inner))
+        },
+        Expr::Items(inner) => format!("{}.clone().into_iter().collect::<Vec<_>>()", operand(inner, expr(inner), false)),
+        Expr::Len(inner) => format!("({}.len() as i32)", operand(inner, expr(inner), false)),
+        Expr::Index(list, index) => format!("{}[{} as usize].clone()", operand(list, expr(list), false), operand(index, expr(index), false)),
+    };
+}
+
+/// every declared name that had to be renamed, as (oxide, rust)
+pub fn renamed(module: &ir::Module) -> Vec<(String, String)> {
+    fn declared(body: &Body, names: &mut Vec<String>) {
+        for (_, stmt) in body {
+            match stmt {
+                Stmt::Let(var, _) | Stmt::If(Cond::Some(var, _), _, _, _) => names.push(var.name.clone()),
+                Stmt::Unpack(vars, _) => names.extend(vars.iter().map(|var| var.name.clone())),
+                _ => (),
+            }
+
+            for inner in stmt.bodies() {
+                declared(inner, names);
+            }
+        }
+    }
+
+    let mut names = Vec::new();
+
+    for item in &module.items {
+        match item {
+            Item::Function(function) => {
+                names.push(function.name.clone());
+                names.extend(function.params.iter().map(|param| param.name.clone()));
+                declared(&function.body, &mut names);
+            },
+            Item::Const(constant) => names.push(constant.name.clone()),
+            Item::Import(..) => (),
+        }
+    }
+
+    declared(&module.top_level, &mut names);
+
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    for name in names {
+        let rust = rust_identifier(&name);
+        if rust != name && !renamed.iter().any(|(oxide, _)| *oxide == name) {
+            renamed.push((name, rust));
+        }
+    }
 
-    return gen;
+    return renamed;
 }
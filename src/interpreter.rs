@@ -0,0 +1,784 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+use crate::{Diagnostic, Options};
+use crate::expression::{BinaryOp, Expr, Piece, parse_expression, parse_format};
+use crate::ir;
+use crate::parser::{Node, Parsed, Span, FunctionCall, VariableDeclare, Destructure, IfLet, ForLoop, nest, split_top_level};
+use crate::program;
+
+/// a value while the program runs
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Value {
+    Int(i32),
+    Str(String),
+    Bool(bool),
+    Tuple(Vec<Value>),
+    Opt(Option<Box<Value>>),
+    Res(Result<Box<Value>, Box<Value>>),
+    /// kept sorted by key, the same order `--ordered-maps` gives
+    Map(Vec<(Value, Value)>),
+    /// what `keys()` and `values()` give
+    Vector(Vec<Value>),
+    /// what a proc without a return type gives back
+    Unit,
+}
+
+impl Value {
+    /// `{}`
+    pub fn display(&self) -> String {
+        return match self {
+            Value::Int(value) => value.to_string(),
+            Value::Str(value) => value.to_owned(),
+            Value::Bool(value) => value.to_string(),
+            _ => self.debug(),
+        };
+    }
+
+    /// how the type is written in oxide, `_` where an empty value can't tell
+    pub fn type_name(&self) -> String {
+        let first = |values: &[Value]| values.first().map(Value::type_name).unwrap_or(String::from("_"));
+
+        return match self {
+            Value::Int(_) => String::from("int"),
+            Value::Str(_) => String::from("string"),
+            Value::Bool(_) => String::from("bool"),
+            Value::Tuple(values) => format!("({})", values.iter().map(Value::type_name).collect::<Vec<String>>().join(", ")),
+            Value::Opt(Some(value)) => format!("opt<{}>", value.type_name()),
+            Value::Opt(None) => String::from("opt<_>"),
+            Value::Res(Ok(value)) => format!("result<{}, _>", value.type_name()),
+            Value::Res(Err(value)) => format!("result<_, {}>", value.type_name()),
+            Value::Map(entries) => match entries.first() {
+                Some((key, value)) => format!("map<{}, {}>", key.type_name(), value.type_name()),
+                None => String::from("map<_, _>"),
+            },
+            Value::Vector(values) => format!("vec<{}>", first(values)),
+            Value::Unit => String::from("()"),
+        };
+    }
+
+    /// whether rust would let the two be compared, the same type as far
+    /// as can be told from the values. an empty map or a `none` can stand
+    /// in for any other
+    fn same_kind(&self, other: &Value) -> bool {
+        let all = |left: &[Value], right: &[Value]| left.len() == right.len() && left.iter().zip(right).all(|(left, right)| left.same_kind(right));
+        let first = |left: &[Value], right: &[Value]| match (left.first(), right.first()) {
+            (Some(left), Some(right)) => left.same_kind(right),
+            _ => true,
+        };
+
+        return match (self, other) {
+            (Value::Int(_), Value::Int(_)) | (Value::Str(_), Value::Str(_)) | (Value::Bool(_), Value::Bool(_)) | (Value::Unit, Value::Unit) => true,
+            (Value::Tuple(left), Value::Tuple(right)) => all(left, right),
+            (Value::Opt(Some(left)), Value::Opt(Some(right))) => left.same_kind(right),
+            (Value::Opt(_), Value::Opt(_)) => true,
+            (Value::Res(Ok(left)), Value::Res(Ok(right))) | (Value::Res(Err(left)), Value::Res(Err(right))) => left.same_kind(right),
+            (Value::Res(_), Value::Res(_)) => true,
+            (Value::Map(left), Value::Map(right)) => match (left.first(), right.first()) {
+                (Some((left_key, left_value)), Some((right_key, right_value))) => left_key.same_kind(right_key) && left_value.same_kind(right_value),
+                _ => true,
+            },
+            (Value::Vector(left), Value::Vector(right)) => first(left, right),
+            _ => false,
+        };
+    }
+
+    /// `{:?}`
+    pub fn debug(&self) -> String {
+        let list = |values: &[Value]| values.iter().map(Value::debug).collect::<Vec<String>>().join(", ");
+
+        return match self {
+            Value::Int(_) | Value::Bool(_) => self.display(),
+            Value::Str(value) => format!("{value:?}"),
+            Value::Tuple(values) if values.len() == 1 => format!("({},)", values[0].debug()),
+            Value::Tuple(values) => format!("({})", list(values)),
+            Value::Opt(Some(value)) => format!("Some({})", value.debug()),
+            Value::Opt(None) => String::from("None"),
+            Value::Res(Ok(value)) => format!("Ok({})", value.debug()),
+            Value::Res(Err(value)) => format!("Err({})", value.debug()),
+            Value::Map(entries) => {
+                let entries: Vec<String> = entries.iter().map(|(key, value)| format!("{}: {}", key.debug(), value.debug())).collect();
+                format!("{{{}}}", entries.join(", "))
+            },
+            Value::Vector(values) => format!("[{}]", list(values)),
+            Value::Unit => String::from("()"),
+        };
+    }
+}
+
+/// why a statement stopped early
+enum Stop {
+    /// a runtime error, the span is filled in by the statement it happened in
+    Error(String, Option<(Option<String>, Span)>),
+    /// `?` on a `fail`, returned from the proc as is
+    Propagate(Value),
+}
+
+enum Flow {
+    Next,
+    Return(Value),
+}
+
+fn error<T>(message: impl Into<String>) -> Result<T, Stop> {
+    return Err(Stop::Error(message.into(), None));
+}
+
+struct Proc {
+    params: Rc<Vec<String>>,
+    body: Rc<Vec<Node>>,
+}
+
+/// one file's procs and `const`s, the entry file is the module called `""`
+struct Module {
+    file: Option<String>,
+    procs: HashMap<String, Proc>,
+    /// each `const`'s literal
+    consts: Vec<(String, ir::Expr)>,
+}
+
+#[derive(Clone)]
+struct Frame {
+    module: String,
+    scopes: Vec<Vec<(String, Value)>>,
+}
+
+impl Frame {
+    fn new(module: &str) -> Frame {
+        return Frame { module: module.to_string(), scopes: vec![Vec::new()] };
+    }
+
+    fn declare(&mut self, name: &str, value: Value) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name.to_string(), value));
+        }
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Value> {
+        for scope in self.scopes.iter_mut().rev() {
+            for (declared, value) in scope.iter_mut().rev() {
+                if declared == name {
+                    return Some(value);
+                }
+            }
+        }
+
+        return None;
+    }
+}
+
+struct Interpreter<'a> {
+    modules: HashMap<String, Module>,
+    expressions: HashMap<String, Expr>,
+    stdout: Box<dyn Write + 'a>,
+    /// set while `:type` works out a type, so procs it calls don't print
+    quiet: bool,
+}
+
+/// runs the program at `path` without going through rust. output goes to
+/// `stdout`, runtime errors and a failing main to `stderr`. gives back
+/// the exit code the compiled program would have had
+pub fn interpret_file(path: &str, source: &str, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, Vec<Diagnostic>> {
+    let loaded = program::load(path, source, &Options::default())?;
+    let entry = loaded.len() - 1;
+    let mut modules = HashMap::new();
+    let mut top_level = Vec::new();
+    let mut main_fails = false;
+
+    for (index, module) in loaded.into_iter().enumerate() {
+        let name = if index == entry { String::new() } else { module.name };
+        let file = if index == entry { None } else { Some(module.path) };
+        let mut procs = HashMap::new();
+        let mut consts = Vec::new();
+
+        for node in nest(module.parsed) {
+            match node {
+                Node::Block(_, Parsed::FunctionDeclare(declare), body) => {
+                    let params = declare.params().into_iter().map(|(name, _)| name).collect();
+                    main_fails = main_fails || (index == entry && declare.name == "main" && declare.return_type.starts_with("Result<"));
+                    procs.insert(declare.name, Proc { params: Rc::new(params), body: Rc::new(body) });
+                },
+                // the checker has already worked each one out
+                Node::Statement(_, Parsed::Const(declare)) => {
+                    if let Ok(value) = ir::constant(&declare.value, &consts) {
+                        consts.push((declare.name, value));
+                    }
+                },
+                node if index == entry => top_level.push(node),
+                _ => (),
+            }
+        }
+
+        modules.insert(name, Module { file, procs, consts });
+    }
+
+    let has_main = modules[""].procs.contains_key("main");
+    let mut interpreter = Interpreter { modules, expressions: HashMap::new(), stdout: Box::new(stdout), quiet: false };
+    let mut frame = Frame::new("");
+
+    let result = if has_main {
+        interpreter.call("main", Vec::new(), "")
+    } else {
+        interpreter.block(&top_level, &mut frame).map(|_| Value::Unit)
+    };
+
+    let _ = interpreter.stdout.flush();
+
+    let failed = match result {
+        Ok(Value::Res(Err(value))) if main_fails => Err(Stop::Propagate(*value)),
+        result => result,
+    };
+
+    return match failed {
+        Err(Stop::Propagate(value)) if main_fails => {
+            let _ = writeln!(stderr, "error: {}", value.display());
+            Ok(1)
+        },
+        Ok(_) | Err(Stop::Propagate(_)) => Ok(0),
+        Err(Stop::Error(message, location)) => {
+            let _ = match location {
+                Some((file, span)) => {
+                    let file = file.as_deref().unwrap_or(path);
+                    writeln!(stderr, "error: {file}:{}:{}: {message}", span.line, span.column)
+                },
+                None => writeln!(stderr, "error: {path}: {message}"),
+            };
+
+            // what a rust panic exits with
+            Ok(101)
+        },
+    };
+}
+
+/// a program that keeps going one input at a time, what `oxide repl` runs.
+/// variables declared at the top level and procs stay around between inputs
+pub struct Session<'a> {
+    interpreter: Interpreter<'a>,
+    frame: Frame,
+    /// the oxide type each top-level variable was declared with
+    types: HashMap<String, String>,
+    /// every input that ran without an error, parsed along with each new input
+    source: String,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(stdout: Box<dyn Write + 'a>) -> Session<'a> {
+        let modules = HashMap::from([(String::new(), Module { file: None, procs: HashMap::new(), consts: Vec::new() })]);
+
+        return Session {
+            interpreter: Interpreter { modules, expressions: HashMap::new(), stdout, quiet: false },
+            frame: Frame::new(""),
+            types: HashMap::new(),
+            source: String::new(),
+        };
+    }
+
+    /// every input that ran without an error, as one oxide program
+    pub fn source(&self) -> &str {
+        return &self.source;
+    }
+
+    /// runs statements and proc declarations. they're checked together with
+    /// everything that ran before, spans are from the start of `input`
+    pub fn run(&mut self, input: &str) -> Result<(), Vec<Diagnostic>> {
+        // the tokeniser and checker need the earlier inputs to know what's declared
+        let lines_before = self.source.lines().count();
+        let whole = format!("{}{}\n", self.source, input.trim_end());
+        let parsed = crate::tokenise(&whole).and_then(|tokens| crate::parse(tokens, &whole)).map_err(|error| vec![error])?;
+
+        let from_input = |span: Span| Span { line: span.line - lines_before, ..span };
+
+        if let Err(errors) = crate::check(&parsed, &Options::default()) {
+            let errors: Vec<Diagnostic> = errors.into_iter()
+                .filter(|error| error.span.is_none_or(|span| span.line > lines_before))
+                .map(|error| Diagnostic { span: error.span.map(from_input), ..error })
+                .collect();
+
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+        }
+
+        let parsed: Vec<(Span, Parsed)> = parsed.into_iter()
+            .filter(|(span, _)| span.line > lines_before)
+            .map(|(span, elem)| (from_input(span), elem))
+            .collect();
+
+        if parsed.iter().any(|(_, elem)| matches!(elem, Parsed::Import(_))) {
+            return Err(vec![Diagnostic { span: None, message: String::from("imports only work in files"), file: None }]);
+        }
+
+        let mut statements = Vec::new();
+
+        for node in nest(parsed) {
+            match node {
+                Node::Block(_, Parsed::FunctionDeclare(declare), body) => {
+                    let params = declare.params().into_iter().map(|(name, _)| name).collect();
+                    let procs = &mut self.interpreter.modules.get_mut("").unwrap().procs;
+                    procs.insert(declare.name, Proc { params: Rc::new(params), body: Rc::new(body) });
+                },
+                Node::Statement(_, Parsed::VariableDeclare(VariableDeclare { ref name, ref type_class, .. })) => {
+                    self.types.insert(name.to_owned(), crate::checker::oxide_type(type_class));
+                    statements.push(node);
+                },
+                Node::Statement(_, Parsed::Const(declare)) => {
+                    self.types.insert(declare.name.to_owned(), crate::checker::oxide_type(&declare.type_class));
+                    let consts = &mut self.interpreter.modules.get_mut("").unwrap().consts;
+                    if let Ok(value) = ir::constant(&declare.value, consts) {
+                        consts.push((declare.name, value));
+                    }
+                },
+                node => statements.push(node),
+            }
+        }
+
+        let result = self.interpreter.statements(&statements, &mut self.frame);
+        let _ = self.interpreter.stdout.flush();
+
+        return match result {
+            Ok(_) => {
+                self.source = whole;
+                Ok(())
+            },
+            Err(Stop::Error(message, location)) => Err(vec![Diagnostic { span: location.map(|(_, span)| span), message, file: None }]),
+            Err(Stop::Propagate(value)) => Err(vec![Diagnostic { span: None, message: format!("`?` got fail({})", value.debug()), file: None }]),
+        };
+    }
+
+    /// the value of an expression and its oxide type
+    pub fn eval(&mut self, expr: &str) -> Result<(Value, String), String> {
+        let result = self.interpreter.eval(expr.trim(), &mut self.frame);
+        let _ = self.interpreter.stdout.flush();
+
+        let value = match result {
+            Ok(value) => value,
+            Err(Stop::Error(message, _)) => return Err(message),
+            Err(Stop::Propagate(value)) => return Err(format!("`?` got fail({})", value.debug())),
+        };
+
+        // a declared variable knows its type even when it's empty
+        let type_name = self.types.get(expr.trim()).cloned().unwrap_or(value.type_name());
+        return Ok((value, type_name));
+    }
+
+    /// the type of an expression, worked out on a copy of the variables so
+    /// nothing changes and nothing is printed
+    pub fn type_of(&mut self, expr: &str) -> Result<String, String> {
+        let frame = self.frame.clone();
+        self.interpreter.quiet = true;
+        let result = self.eval(expr);
+        self.interpreter.quiet = false;
+        self.frame = frame;
+
+        return result.map(|(_, type_name)| type_name);
+    }
+}
+
+impl Interpreter<'_> {
+    fn call(&mut self, name: &str, args: Vec<Value>, module: &str) -> Result<Value, Stop> {
+        // `math::double` is `double` in the module `math`
+        let (module, name) = match name.split_once("::") {
+            Some((module, name)) => (module, name),
+            None => (module, name),
+        };
+
+        let Some(proc) = self.modules.get(module).and_then(|found| found.procs.get(name)) else {
+            return error(format!("there's no proc called `{name}`"));
+        };
+
+        let (params, body) = (proc.params.clone(), proc.body.clone());
+        let mut frame = Frame::new(module);
+
+        for (param, arg) in params.iter().zip(args) {
+            frame.declare(param, arg);
+        }
+
+        let result = self.block(&body, &mut frame);
+
+        return match result {
+            Ok(Flow::Return(value)) => Ok(value),
+            Ok(Flow::Next) => Ok(Value::Unit),
+            Err(Stop::Propagate(value)) => Ok(Value::Res(Err(Box::new(value)))),
+            Err(stop) => Err(stop),
+        };
+    }
+
+    /// runs the statements in a new scope
+    fn block(&mut self, nodes: &[Node], frame: &mut Frame) -> Result<Flow, Stop> {
+        frame.scopes.push(Vec::new());
+        let result = self.statements(nodes, frame);
+        frame.scopes.pop();
+        return result;
+    }
+
+    fn statements(&mut self, nodes: &[Node], frame: &mut Frame) -> Result<Flow, Stop> {
+        // set once a branch of an `if` .. `orif` .. `else` chain has run
+        let mut chain_done = false;
+
+        for node in nodes {
+            let span = match node {
+                Node::Statement(span, _) | Node::Block(span, _, _) => *span,
+            };
+
+            let flow = match node {
+                Node::Statement(_, elem) => self.statement(elem, frame),
+                Node::Block(_, head, body) => self.block_statement(head, body, frame, &mut chain_done),
+            };
+
+            match flow {
+                Ok(Flow::Next) => (),
+                Ok(flow) => return Ok(flow),
+                Err(Stop::Error(message, None)) => {
+                    let file = self.modules.get(&frame.module).and_then(|module| module.file.clone());
+                    return Err(Stop::Error(message, Some((file, span))));
+                },
+                Err(stop) => return Err(stop),
+            }
+        }
+
+        return Ok(Flow::Next);
+    }
+
+    fn statement(&mut self, elem: &Parsed, frame: &mut Frame) -> Result<Flow, Stop> {
+        match elem {
+            Parsed::VariableDeclare(VariableDeclare { name, value, .. }) => {
+                let value = self.eval(value, frame)?;
+                frame.declare(name, value);
+            },
+            Parsed::Destructure(Destructure { names, value }) => {
+                let Value::Tuple(values) = self.eval(value, frame)? else {
+                    return error("only tuples can be destructured");
+                };
+
+                for (name, value) in names.iter().zip(values) {
+                    frame.declare(name, value);
+                }
+            },
+            Parsed::FunctionCall(FunctionCall { name, parameters, propagate }) => {
+                let question = if *propagate { "?" } else { "" };
+                self.eval(&format!("{name}({parameters}){question}"), frame)?;
+            },
+            Parsed::Print(body) => {
+                let mut args = split_top_level(body).into_iter();
+                let format = args.next().unwrap_or_default();
+                let Value::Str(format) = self.eval(&format, frame)? else {
+                    return error("print needs a string to format");
+                };
+
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.eval(arg.trim(), frame)?);
+                }
+
+                let line = self.format(&format, &values, frame)?;
+                if !self.quiet && writeln!(self.stdout, "{line}").is_err() {
+                    return error("couldn't write to stdout");
+                }
+            },
+            Parsed::Return(value) if value.trim().is_empty() => return Ok(Flow::Return(Value::Unit)),
+            Parsed::Return(value) => return Ok(Flow::Return(self.eval(value, frame)?)),
+            _ => (),
+        }
+
+        return Ok(Flow::Next);
+    }
+
+    fn block_statement(&mut self, head: &Parsed, body: &[Node], frame: &mut Frame, chain_done: &mut bool) -> Result<Flow, Stop> {
+        match head {
+            Parsed::If(condition) => {
+                *chain_done = false;
+                return self.branch(condition, body, frame, chain_done);
+            },
+            Parsed::OrIf(condition) if !*chain_done => return self.branch(condition, body, frame, chain_done),
+            Parsed::Else if !*chain_done => {
+                *chain_done = true;
+                return self.block(body, frame);
+            },
+            Parsed::IfLet(IfLet { binding, value }) => {
+                *chain_done = false;
+
+                if let Value::Opt(Some(value)) = self.eval(value, frame)? {
+                    *chain_done = true;
+                    frame.scopes.push(vec![(binding.to_owned(), *value)]);
+                    let flow = self.statements(body, frame);
+                    frame.scopes.pop();
+                    return flow;
+                }
+            },
+            Parsed::For(ForLoop { bindings, iterable }) => {
+                let names: Vec<&str> = bindings.split(',').map(str::trim).collect();
+                let items: Vec<Vec<Value>> = match self.eval(iterable, frame)? {
+                    Value::Map(entries) => entries.into_iter().map(|(key, value)| vec![key, value]).collect(),
+                    Value::Vector(values) => values.into_iter().map(|value| vec![value]).collect(),
+                    _ => return error(format!("`{iterable}` can't be looped over")),
+                };
+
+                for item in items {
+                    frame.scopes.push(names.iter().map(|name| name.to_string()).zip(item).collect());
+                    let flow = self.statements(body, frame);
+                    frame.scopes.pop();
+
+                    if let Flow::Return(value) = flow? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+            },
+            _ => (),
+        }
+
+        return Ok(Flow::Next);
+    }
+
+    fn branch(&mut self, condition: &str, body: &[Node], frame: &mut Frame, chain_done: &mut bool) -> Result<Flow, Stop> {
+        match self.eval(condition, frame)? {
+            Value::Bool(true) => {
+                *chain_done = true;
+                return self.block(body, frame);
+            },
+            Value::Bool(false) => return Ok(Flow::Next),
+            _ => return error(format!("`{condition}` isn't a bool")),
+        }
+    }
+
+    /// rust's `{}`, `{:?}`, `{0}`, `{name}` and the specs `Spec` has
+    fn format(&mut self, format: &str, values: &[Value], frame: &mut Frame) -> Result<String, Stop> {
+        let mut values = values.to_vec();
+        let mut pieces = match parse_format(format) {
+            Ok(pieces) => pieces,
+            Err(message) => return error(message),
+        };
+
+        // `{name}` is filled in from the variable, after the arguments
+        for piece in &mut pieces {
+            if let Piece::Name(name, spec) = piece {
+                let Some(value) = self.variable(name, frame) else {
+                    return error(format!("nothing to fill in `{{{name}}}` with"));
+                };
+
+                values.push(value);
+                *piece = Piece::Index(values.len() - 1, *spec);
+            }
+        }
+
+        return render(&pieces, &values).map_err(|message| Stop::Error(message, None));
+    }
+
+    /// a variable, or a `const` from the file the frame's proc is in
+    fn variable(&self, name: &str, frame: &mut Frame) -> Option<Value> {
+        if let Some(value) = frame.lookup(name) {
+            return Some(value.clone());
+        }
+
+        let consts = &self.modules.get(&frame.module)?.consts;
+        return consts.iter().find(|(declared, _)| declared == name).map(|(_, value)| literal(value));
+    }
+
+    fn eval(&mut self, text: &str, frame: &mut Frame) -> Result<Value, Stop> {
+        if !self.expressions.contains_key(text) {
+            let expr = match parse_expression(text) {
+                Some(expr) => expr,
+                None => return error(format!("can't make sense of `{text}`")),
+            };
+
+            self.expressions.insert(text.to_string(), expr);
+        }
+
+        let expr = self.expressions[text].clone();
+        return self.evaluate(&expr, frame);
+    }
+
+    fn evaluate(&mut self, expr: &Expr, frame: &mut Frame) -> Result<Value, Stop> {
+        match expr {
+            Expr::Int(value) => return Ok(Value::Int(*value)),
+            Expr::Str(value) => return Ok(Value::Str(value.to_owned())),
+            Expr::Bool(value) => return Ok(Value::Bool(*value)),
+            Expr::Name(name) if name == "none" => return Ok(Value::Opt(None)),
+            Expr::Name(name) => match self.variable(name, frame) {
+                Some(value) => return Ok(value),
+                None => return error(format!("`{name}` isn't declared")),
+            },
+            Expr::Tuple(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.evaluate(item, frame)?);
+                }
+
+                return Ok(Value::Tuple(values));
+            },
+            Expr::Map(entries) => {
+                let mut map = Vec::new();
+                for (key, value) in entries {
+                    let key = self.evaluate(key, frame)?;
+                    let value = self.evaluate(value, frame)?;
+                    insert(&mut map, key, value);
+                }
+
+                return Ok(Value::Map(map));
+            },
+            Expr::Call(name, args) => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.evaluate(arg, frame)?);
+                }
+
+                match (name.as_str(), values.len()) {
+                    ("some", 1) => return Ok(Value::Opt(Some(Box::new(values.remove(0))))),
+                    ("ok", 1) => return Ok(Value::Res(Ok(Box::new(values.remove(0))))),
+                    ("fail", 1) => return Ok(Value::Res(Err(Box::new(values.remove(0))))),
+                    _ => {
+                        let module = frame.module.clone();
+                        return self.call(name, values, &module);
+                    },
+                }
+            },
+            Expr::Method(receiver, method, args) => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.evaluate(arg, frame)?);
+                }
+
+                return self.method(receiver, method, values, frame);
+            },
+            Expr::Field(tuple, index) => match self.evaluate(tuple, frame)? {
+                Value::Tuple(mut values) if *index < values.len() => return Ok(values.swap_remove(*index)),
+                _ => return error(format!("there's no field `{index}`")),
+            },
+            Expr::Question(inner) => match self.evaluate(inner, frame)? {
+                Value::Res(Ok(value)) => return Ok(*value),
+                Value::Res(Err(value)) => return Err(Stop::Propagate(*value)),
+                _ => return error("`?` only works on a `result`"),
+            },
+            Expr::Unary(op, inner) => match (op, self.evaluate(inner, frame)?) {
+                ('-', Value::Int(value)) => match value.checked_neg() {
+                    Some(value) => return Ok(Value::Int(value)),
+                    None => return error("attempt to negate with overflow"),
+                },
+                ('!', Value::Bool(value)) => return Ok(Value::Bool(!value)),
+                ('!', Value::Int(value)) => return Ok(Value::Int(!value)),
+                _ => return error(format!("`{op}` doesn't work on that")),
+            },
+            Expr::Binary(op, left, right) => {
+                let left = self.evaluate(left, frame)?;
+
+                // `&&` and `||` only look at the right when they have to
+                match (op.as_str(), &left) {
+                    ("&&", Value::Bool(false)) => return Ok(Value::Bool(false)),
+                    ("||", Value::Bool(true)) => return Ok(Value::Bool(true)),
+                    _ => (),
+                }
+
+                let right = self.evaluate(right, frame)?;
+                return binary(op, left, right).map_err(|message| Stop::Error(message, None));
+            },
+        }
+    }
+
+    /// the built-in map methods, `insert` and `remove` change the map in place
+    fn method(&mut self, receiver: &Expr, method: &str, mut args: Vec<Value>, frame: &mut Frame) -> Result<Value, Stop> {
+        if let ("insert" | "remove", Expr::Name(name)) = (method, receiver) {
+            let Some(Value::Map(map)) = frame.lookup(name) else {
+                return error(format!("`{name}` isn't a map"));
+            };
+
+            return match (method, args.len()) {
+                ("insert", 2) => {
+                    let value = args.pop().unwrap_or(Value::Unit);
+                    let key = args.pop().unwrap_or(Value::Unit);
+                    Ok(Value::Opt(insert(map, key, value).map(Box::new)))
+                },
+                ("remove", 1) => {
+                    let position = map.iter().position(|(key, _)| *key == args[0]);
+                    Ok(Value::Opt(position.map(|position| Box::new(map.remove(position).1))))
+                },
+                _ => error(format!("wrong number of arguments to `{method}`")),
+            };
+        }
+
+        let Value::Map(map) = self.evaluate(receiver, frame)? else {
+            return error(format!("there's no method `{method}`"));
+        };
+
+        return match (method, args.first()) {
+            ("get", Some(wanted)) => {
+                let found = map.into_iter().find(|(key, _)| key == wanted);
+                Ok(Value::Opt(found.map(|(_, value)| Box::new(value))))
+            },
+            ("contains", Some(wanted)) => Ok(Value::Bool(map.iter().any(|(key, _)| key == wanted))),
+            ("keys", None) => Ok(Value::Vector(map.into_iter().map(|(key, _)| key).collect())),
+            ("values", None) => Ok(Value::Vector(map.into_iter().map(|(_, value)| value).collect())),
+            _ => error(format!("maps have no `{method}`")),
+        };
+    }
+}
+
+/// puts an entry where it sorts, giving back what was there before
+pub(crate) fn insert(map: &mut Vec<(Value, Value)>, key: Value, value: Value) -> Option<Value> {
+    for (index, (existing, old)) in map.iter_mut().enumerate() {
+        if *existing == key {
+            return Some(std::mem::replace(old, value));
+        }
+
+        if *existing > key {
+            map.insert(index, (key, value));
+            return None;
+        }
+    }
+
+    map.push((key, value));
+    return None;
+}
+
+/// fills in a parsed format string once every `{name}` is an index
+pub(crate) fn render(pieces: &[Piece], values: &[Value]) -> Result<String, String> {
+    let mut output = String::new();
+
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => output.push_str(text),
+            Piece::Index(index, spec) => match values.get(*index) {
+                Some(value) if spec.debug => output.push_str(&value.debug()),
+                Some(value) => output.push_str(&spec.pad(&value.display(), matches!(value, Value::Int(_)))),
+                None => return Err(format!("nothing to fill in argument {index} with")),
+            },
+            Piece::Name(name, _) => return Err(format!("nothing to fill in `{{{name}}}` with")),
+        }
+    }
+
+    return Ok(output);
+}
+
+/// arithmetic fails the same way rust's debug builds do
+pub(crate) fn binary(op: &str, left: Value, right: Value) -> Result<Value, String> {
+    if let (Value::Int(left), Value::Int(right), Some(op)) = (&left, &right, BinaryOp::from_symbol(op)) {
+        if let Some(result) = op.arithmetic(*left, *right) {
+            return result.map(Value::Int).map_err(String::from);
+        }
+    }
+
+    // rust only compares values of the same type
+    if matches!(op, "==" | "!=" | "<" | ">" | "<=" | ">=") && !left.same_kind(&right) {
+        return Err(format!("`{op}` doesn't work on {} and {}", left.debug(), right.debug()));
+    }
+
+    return match (op, left, right) {
+        ("+", Value::Str(left), Value::Str(right)) => Ok(Value::Str(left + &right)),
+        ("==", left, right) => Ok(Value::Bool(left == right)),
+        ("!=", left, right) => Ok(Value::Bool(left != right)),
+        ("<", left, right) => Ok(Value::Bool(left < right)),
+        (">", left, right) => Ok(Value::Bool(left > right)),
+        ("<=", left, right) => Ok(Value::Bool(left <= right)),
+        (">=", left, right) => Ok(Value::Bool(left >= right)),
+        ("&&", Value::Bool(left), Value::Bool(right)) => Ok(Value::Bool(left && right)),
+        ("||", Value::Bool(left), Value::Bool(right)) => Ok(Value::Bool(left || right)),
+        (op, left, right) => Err(format!("`{op}` doesn't work on {} and {}", left.debug(), right.debug())),
+    };
+}
+
+/// a `const`'s literal from the ir as the value it is when the program runs
+pub(crate) fn literal(expr: &ir::Expr) -> Value {
+    return match expr {
+        ir::Expr::Int(value) => Value::Int(*value),
+        ir::Expr::Str(value) => Value::Str(value.clone()),
+        ir::Expr::Bool(value) => Value::Bool(*value),
+        _ => Value::Unit,
+    };
+}
@@ -0,0 +1,960 @@
+use std::fmt::Write;
+use crate::{Diagnostic, Options};
+use crate::checker::{map_types, tuple_types, option_inner, oxide_type};
+use crate::expression::{self, BinaryOp, Piece, format_string, parse_expression, parse_format};
+use crate::parser::{Node, Parsed, Span, FunctionCall, VariableDeclare, Destructure, FunctionDeclare, IfLet, ForLoop, Import, nest, split_top_level};
+use crate::optimiser;
+use crate::program;
+
+/// one oxide file lowered for the backends. names are resolved, `orif`
+/// chains are nested `if`s, `for` loops are a `Loop` over an index and
+/// format strings have their `{name}`s pulled out into arguments
+#[derive(Debug, Clone, Default)]
+pub struct Module {
+    pub items: Vec<Item>,
+    /// the statements outside any proc, run by the generated main
+    pub top_level: Body,
+}
+
+#[derive(Debug, Clone)]
+pub enum Item {
+    Function(Function),
+    /// `import "lib/math.oxi";` and the name it's imported as
+    Import(Span, String),
+    Const(Const),
+}
+
+/// `const int MAX = 100;`
+#[derive(Debug, Clone)]
+pub struct Const {
+    pub name: String,
+    /// the rust type, `i32`, `String` or `bool`
+    pub type_class: String,
+    /// as it's written, other `const`s in it are `Expr::Const`
+    pub value: Expr,
+    /// what `value` works out to
+    pub literal: Expr,
+    pub span: Span,
+}
+
+/// statements along with where each one came from
+pub type Body = Vec<(Span, Stmt)>;
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    /// as it's declared, backends add a module's prefix
+    pub name: String,
+    pub public: bool,
+    pub params: Vec<Var>,
+    /// the rust type, empty for a proc that doesn't return anything
+    pub returns: String,
+    pub body: Body,
+    pub span: Span,
+}
+
+/// a variable, a parameter or a temp made up by the lowering
+#[derive(Debug, Clone, PartialEq)]
+pub struct Var {
+    /// what it's called in the source
+    pub name: String,
+    /// a name nothing else in the same proc has, for targets that can't
+    /// shadow a variable or can't read the old one in the new one's
+    /// initialiser
+    pub unique: String,
+    /// the rust type, empty when it isn't known
+    pub type_class: String,
+    pub temp: bool,
+    /// assigned to or changed in place after it's declared, only set
+    /// where it's declared
+    pub mutable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Let(Var, Expr),
+    /// a tuple split into a variable for each of its fields
+    Unpack(Vec<Var>, Expr),
+    Assign(Var, Expr),
+    /// run for what it does, a proc call or a map changing
+    Expr(Expr),
+    /// the format string as `Text` and `Index` pieces, and the arguments
+    Print(Vec<Piece>, Vec<Expr>),
+    Return(Option<Expr>),
+    /// an `orif` is an `If` on its own in the `else` block, the span is
+    /// the `else`'s or the `orif`'s
+    If(Cond, Body, Body, Option<Span>),
+    /// runs until a `Break`
+    Loop(Body),
+    Break,
+    /// statements with a scope of their own, what's left of an `if` that
+    /// always runs once its condition is folded away
+    Block(Body),
+}
+
+#[derive(Debug, Clone)]
+pub enum Cond {
+    Bool(Expr),
+    /// `if let some(x) = value`, `x` is only in scope in the first block
+    Some(Var, Expr),
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Int(i32),
+    Str(String),
+    Bool(bool),
+    None,
+    Local(Var),
+    /// a `const` by name and the literal it works out to
+    Const(String, Box<Expr>),
+    Tuple(Vec<Expr>),
+    Map(Vec<(Expr, Expr)>),
+    Some(Box<Expr>),
+    Ok(Box<Expr>),
+    Fail(Box<Expr>),
+    /// the proc as it's written, `math::double`, and the rust type it
+    /// returns if it's in the same file
+    Call(String, Vec<Expr>, String),
+    /// `insert` and `remove` always have a `Local` to change
+    Method(Method, Box<Expr>, Vec<Expr>),
+    Field(Box<Expr>, usize),
+    /// `?`
+    Try(Box<Expr>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `&&` and `||` only run the right when they have to
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    /// what a loop goes over as a vector, a map's are (key, value) tuples
+    Items(Box<Expr>),
+    Len(Box<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+}
+
+/// the built-in map methods
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Get,
+    Insert,
+    Remove,
+    Contains,
+    Keys,
+    Values,
+}
+
+impl Method {
+    pub fn name(self) -> &'static str {
+        return match self {
+            Method::Get => "get",
+            Method::Insert => "insert",
+            Method::Remove => "remove",
+            Method::Contains => "contains",
+            Method::Keys => "keys",
+            Method::Values => "values",
+        };
+    }
+}
+
+impl Expr {
+    /// the rust type of the value, empty when it can't be worked out
+    pub fn type_class(&self) -> String {
+        let boxed = |inner: &Expr, wrap: &str| {
+            let inner = inner.type_class();
+            if inner.is_empty() { inner } else { format!("{wrap}<{inner}>") }
+        };
+
+        return match self {
+            Expr::Int(_) | Expr::Len(_) => String::from("i32"),
+            Expr::Str(_) => String::from("String"),
+            Expr::Bool(_) | Expr::And(..) | Expr::Or(..) => String::from("bool"),
+            Expr::Not(inner) => inner.type_class(),
+            Expr::Local(var) => var.type_class.clone(),
+            Expr::Const(_, value) => value.type_class(),
+            Expr::Tuple(items) => {
+                let types: Vec<String> = items.iter().map(Expr::type_class).collect();
+                if types.iter().any(String::is_empty) { String::new() } else { format!("({})", types.join(", ")) }
+            },
+            Expr::Some(inner) => boxed(inner, "Option"),
+            Expr::Call(_, _, returns) => returns.clone(),
+            Expr::Method(method, receiver, _) => {
+                let Some((key, value)) = map_types(&receiver.type_class()) else {
+                    return String::new();
+                };
+
+                match method {
+                    Method::Get | Method::Insert | Method::Remove => format!("Option<{value}>"),
+                    Method::Contains => String::from("bool"),
+                    Method::Keys => format!("Vec<{key}>"),
+                    Method::Values => format!("Vec<{value}>"),
+                }
+            },
+            Expr::Field(tuple, index) => tuple_types(&tuple.type_class())
+                .and_then(|types| types.get(*index).cloned())
+                .unwrap_or_default(),
+            Expr::Try(inner) => {
+                let inner = inner.type_class();
+                let types = inner.strip_prefix("Result<").and_then(|t| t.strip_suffix('>')).map(split_top_level);
+                types.and_then(|types| types.first().map(|t| t.trim().to_string())).unwrap_or_default()
+            },
+            Expr::Neg(_) => String::from("i32"),
+            Expr::Binary(op, left, _) => match op {
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => left.type_class(),
+                _ => String::from("bool"),
+            },
+            Expr::Items(inner) => {
+                let inner = inner.type_class();
+                match map_types(&inner) {
+                    Some((key, value)) => format!("Vec<({key}, {value})>"),
+                    None if inner.starts_with("Vec<") => inner,
+                    None => String::new(),
+                }
+            },
+            Expr::Index(list, _) => {
+                let list = list.type_class();
+                list.strip_prefix("Vec<").and_then(|t| t.strip_suffix('>')).unwrap_or_default().to_string()
+            },
+            Expr::None | Expr::Map(_) | Expr::Ok(_) | Expr::Fail(_) => String::new(),
+        };
+    }
+
+    /// the expressions directly inside this one
+    pub fn children(&self) -> Vec<&Expr> {
+        return match self {
+            Expr::Tuple(items) | Expr::Call(_, items, _) => items.iter().collect(),
+            Expr::Map(entries) => entries.iter().flat_map(|(key, value)| [key, value]).collect(),
+            Expr::Method(_, receiver, args) => std::iter::once(receiver.as_ref()).chain(args).collect(),
+            Expr::Some(inner) | Expr::Ok(inner) | Expr::Fail(inner) | Expr::Field(inner, _) | Expr::Try(inner) |
+            Expr::Neg(inner) | Expr::Not(inner) | Expr::Items(inner) | Expr::Len(inner) => vec![inner],
+            Expr::Binary(_, left, right) | Expr::And(left, right) | Expr::Or(left, right) | Expr::Index(left, right) => vec![left, right],
+            Expr::Int(_) | Expr::Str(_) | Expr::Bool(_) | Expr::None | Expr::Local(_) | Expr::Const(..) => Vec::new(),
+        };
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+        return match self {
+            Expr::Tuple(items) | Expr::Call(_, items, _) => items.iter_mut().collect(),
+            Expr::Map(entries) => entries.iter_mut().flat_map(|(key, value)| [key, value]).collect(),
+            Expr::Method(_, receiver, args) => std::iter::once(receiver.as_mut()).chain(args).collect(),
+            Expr::Some(inner) | Expr::Ok(inner) | Expr::Fail(inner) | Expr::Field(inner, _) | Expr::Try(inner) |
+            Expr::Neg(inner) | Expr::Not(inner) | Expr::Items(inner) | Expr::Len(inner) => vec![inner],
+            Expr::Binary(_, left, right) | Expr::And(left, right) | Expr::Or(left, right) | Expr::Index(left, right) => vec![left, right],
+            Expr::Int(_) | Expr::Str(_) | Expr::Bool(_) | Expr::None | Expr::Local(_) | Expr::Const(..) => Vec::new(),
+        };
+    }
+
+    /// calls `visit` on this expression and everything inside it
+    pub fn visit(&self, visit: &mut impl FnMut(&Expr)) {
+        visit(self);
+        for child in self.children() {
+            child.visit(visit);
+        }
+    }
+}
+
+impl Stmt {
+    /// the expressions the statement runs itself, not the ones in its blocks
+    pub fn exprs(&self) -> Vec<&Expr> {
+        return match self {
+            Stmt::Let(_, value) | Stmt::Unpack(_, value) | Stmt::Assign(_, value) | Stmt::Expr(value) => vec![value],
+            Stmt::Print(_, args) => args.iter().collect(),
+            Stmt::Return(value) => value.iter().collect(),
+            Stmt::If(Cond::Bool(value) | Cond::Some(_, value), _, _, _) => vec![value],
+            Stmt::Loop(_) | Stmt::Break | Stmt::Block(_) => Vec::new(),
+        };
+    }
+
+    /// the blocks inside the statement
+    pub fn bodies(&self) -> Vec<&Body> {
+        return match self {
+            Stmt::If(_, then, otherwise, _) => vec![then, otherwise],
+            Stmt::Loop(body) | Stmt::Block(body) => vec![body],
+            _ => Vec::new(),
+        };
+    }
+}
+
+/// how tightly an operator binds when it's written between its operands
+pub(crate) fn precedence(expr: &Expr) -> u8 {
+    return match expr {
+        Expr::Or(..) => 1,
+        Expr::And(..) => 2,
+        Expr::Binary(BinaryOp::Add | BinaryOp::Sub, ..) => 4,
+        Expr::Binary(BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem, ..) => 5,
+        Expr::Binary(..) => 3,
+        // a negative literal needs wrapping the same as a `-` does
+        Expr::Neg(_) | Expr::Not(_) => 6,
+        Expr::Int(value) if *value < 0 => 6,
+        Expr::Const(_, value) => precedence(value),
+        _ => 7,
+    };
+}
+
+/// `left op right` with only the parentheses it needs, `show` writes each
+/// side. comparisons don't chain so one inside another is always wrapped
+pub(crate) fn infix(expr: &Expr, op: &str, left: &Expr, right: &Expr, show: &mut impl FnMut(&Expr) -> String) -> String {
+    let at = precedence(expr);
+    let wrap = |side: &Expr, text: String, tighter: bool| {
+        let side = precedence(side);
+        if side < at || (side == at && (tighter || at == 3)) { format!("({text})") } else { text }
+    };
+
+    let left_text = show(left);
+    let right_text = show(right);
+    return format!("{} {op} {}", wrap(left, left_text, false), wrap(right, right_text, true));
+}
+
+/// an operand of `-`, `!`, `.` or `?`, wrapped unless nothing binds tighter
+pub(crate) fn operand(expr: &Expr, text: String, unary: bool) -> String {
+    let needs = if unary { 6 } else { 7 };
+    return if precedence(expr) < needs { format!("({text})") } else { text };
+}
+
+/// lowers a file's checked statements, or gives back everything in them
+/// the checker let through that can't be lowered
+pub(crate) fn lower(parsed: Vec<(Span, Parsed)>) -> Result<Module, Vec<(Span, String)>> {
+    let names = declared_names(&parsed);
+    let mut lowerer = Lowerer { procs: Vec::new(), consts: Vec::new(), names, scopes: Vec::new(), taken: Vec::new(), temps: 0, at: Span { line: 1, column: 1 }, errors: Vec::new() };
+
+    for (_, elem) in &parsed {
+        match elem {
+            Parsed::FunctionDeclare(declare) => lowerer.procs.push((declare.name.clone(), declare.return_type.clone())),
+            // the checker has already worked each one out
+            Parsed::Const(VariableDeclare { name, value, .. }) => {
+                if let Ok(value) = constant(value, &lowerer.consts) {
+                    lowerer.consts.push((name.clone(), value));
+                }
+            },
+            _ => (),
+        }
+    }
+
+    let mut items = Vec::new();
+    let mut top_level = Vec::new();
+
+    for node in nest(parsed) {
+        match node {
+            Node::Block(span, Parsed::FunctionDeclare(declare), body) => items.push(Item::Function(lowerer.function(span, declare, &body))),
+            Node::Statement(span, Parsed::Import(Import { name, .. })) => items.push(Item::Import(span, name)),
+            Node::Statement(span, Parsed::Const(VariableDeclare { type_class, name, value })) => {
+                lowerer.at = span;
+                let value = lowerer.value(&value);
+                let Expr::Const(_, literal) = lowerer.name(&name) else {
+                    continue;
+                };
+
+                items.push(Item::Const(Const { name, type_class, value, literal: *literal, span }));
+            },
+            node => top_level.push(node),
+        }
+    }
+
+    lowerer.start();
+    let mut top_level = lowerer.statements(&top_level);
+    mark_mutable(&mut top_level, &mut Vec::new());
+
+    if !lowerer.errors.is_empty() {
+        return Err(lowerer.errors);
+    }
+
+    return Ok(Module { items, top_level });
+}
+
+/// lowers the program at `path` and every file it imports, imported
+/// files first, each along with its path. `Options::optimise` runs the
+/// optimiser over each one
+pub fn lower_file(path: &str, source: &str, options: &Options) -> Result<Vec<(String, Module)>, Vec<Diagnostic>> {
+    let modules = program::load(path, source, options)?;
+    return Ok(modules.into_iter().map(|module| (module.path, module.lowered)).collect());
+}
+
+/// the literal a `const` works out to, which can only be worked out from
+/// literals, operators and the `const`s in `consts`
+pub(crate) fn constant(text: &str, consts: &[(String, Expr)]) -> Result<Expr, String> {
+    fn fold(expr: &expression::Expr, consts: &[(String, Expr)]) -> Result<Expr, String> {
+        use expression::Expr as Source;
+
+        let expr = match expr {
+            Source::Int(value) => return Ok(Expr::Int(*value)),
+            Source::Str(value) => return Ok(Expr::Str(value.clone())),
+            Source::Bool(value) => return Ok(Expr::Bool(*value)),
+            Source::Name(name) => match consts.iter().find(|(declared, _)| declared == name) {
+                Some((_, value)) => return Ok(value.clone()),
+                None => return Err(format!("`{name}` isn't a `const` declared before this one")),
+            },
+            Source::Unary('-', inner) => Expr::Neg(Box::new(fold(inner, consts)?)),
+            Source::Unary(_, inner) => Expr::Not(Box::new(fold(inner, consts)?)),
+            Source::Binary(op, left, right) => {
+                let (left, right) = (Box::new(fold(left, consts)?), Box::new(fold(right, consts)?));
+                match (op.as_str(), BinaryOp::from_symbol(op)) {
+                    ("&&", _) => Expr::And(left, right),
+                    ("||", _) => Expr::Or(left, right),
+                    (_, Some(op)) => Expr::Binary(op, left, right),
+                    (op, None) => return Err(format!("`{op}` isn't an operator")),
+                }
+            },
+            _ => return Err(String::from("only literals, operators and other `const`s can be used")),
+        };
+
+        return match optimiser::fold(&expr) {
+            Ok(Some(folded @ (Expr::Int(_) | Expr::Str(_) | Expr::Bool(_)))) => Ok(folded),
+            Ok(_) => Err(format!("`{}` mixes values that don't go together", show(&expr))),
+            Err(message) => Err(format!("`{}` always fails: {message}", show(&expr))),
+        };
+    }
+
+    return match parse_expression(text) {
+        Some(expr) => fold(&expr, consts),
+        None => Err(format!("can't make sense of `{}`", text.trim())),
+    };
+}
+
+/// every name the file declares, a unique name can't be one of these
+fn declared_names(parsed: &[(Span, Parsed)]) -> Vec<String> {
+    return parsed.iter().flat_map(|(_, elem)| elem.names()).collect();
+}
+
+struct Lowerer {
+    /// the procs in the file and the rust type each returns
+    procs: Vec<(String, String)>,
+    /// the `const`s in the file and the literal each works out to
+    consts: Vec<(String, Expr)>,
+    names: Vec<String>,
+    scopes: Vec<Vec<Var>>,
+    /// the unique names given out in the proc being lowered
+    taken: Vec<String>,
+    temps: usize,
+    /// the statement being lowered, where an error in it is reported
+    at: Span,
+    errors: Vec<(Span, String)>,
+}
+
+impl Lowerer {
+    /// a proc, or the top level, starts with nothing in scope
+    fn start(&mut self) {
+        self.scopes = vec![Vec::new()];
+        self.taken.clear();
+    }
+
+    fn function(&mut self, span: Span, declare: FunctionDeclare, body: &[Node]) -> Function {
+        self.start();
+        self.at = span;
+        let mut params: Vec<Var> = declare.params().iter().map(|(name, type_class)| self.declare(name, type_class)).collect();
+        let mut body = self.statements(body);
+
+        let mut mutated = Vec::new();
+        mark_mutable(&mut body, &mut mutated);
+        for param in &mut params {
+            param.mutable = mutated.contains(&param.unique);
+        }
+
+        return Function { name: declare.name, public: declare.public, params, returns: declare.return_type, body, span };
+    }
+
+    /// a new variable in the innermost scope
+    fn declare(&mut self, name: &str, type_class: &str) -> Var {
+        let mut unique = name.to_string();
+        let mut count = 1;
+
+        while self.taken.contains(&unique) || (count > 1 && self.names.contains(&unique)) {
+            count += 1;
+            unique = format!("{name}_{count}");
+        }
+
+        self.taken.push(unique.clone());
+        let var = Var { name: name.to_string(), unique, type_class: type_class.to_string(), temp: false, mutable: false };
+        self.scopes.last_mut().unwrap().push(var.clone());
+        return var;
+    }
+
+    /// a variable only the lowering uses, it can't be named in the source
+    fn temp(&mut self, name: &str, type_class: String) -> Var {
+        self.temps += 1;
+        let unique = format!("{name}_{}", self.temps);
+        return Var { name: unique.clone(), unique, type_class, temp: true, mutable: false };
+    }
+
+    /// a variable, or a `const` which nothing else can be called
+    fn name(&mut self, name: &str) -> Expr {
+        if let Some((_, value)) = self.consts.iter().find(|(declared, _)| declared == name) {
+            return Expr::Const(name.to_string(), Box::new(value.clone()));
+        }
+
+        return match self.scopes.iter().flatten().rev().find(|var| var.name == name) {
+            Some(var) => Expr::Local(var.clone()),
+            None => self.invalid(format!("`{name}` isn't declared")),
+        };
+    }
+
+    fn block(&mut self, nodes: &[Node]) -> Body {
+        self.scopes.push(Vec::new());
+        let body = self.statements(nodes);
+        self.scopes.pop();
+        return body;
+    }
+
+    fn statements(&mut self, nodes: &[Node]) -> Body {
+        let mut body = Vec::new();
+        let mut i = 0;
+
+        while i < nodes.len() {
+            let (Node::Statement(span, _) | Node::Block(span, _, _)) = &nodes[i];
+            self.at = *span;
+
+            match &nodes[i] {
+                Node::Statement(span, elem) => {
+                    if let Some(stmt) = self.statement(elem) {
+                        body.push((*span, stmt));
+                    }
+                },
+                Node::Block(span, Parsed::If(_) | Parsed::IfLet(_), _) => {
+                    // the `orif`s and `else` after an `if` are part of the same chain
+                    let mut end = i + 1;
+                    while let Some(Node::Block(_, Parsed::OrIf(_) | Parsed::Else, _)) = nodes.get(end) {
+                        end += 1;
+                    }
+
+                    body.push((*span, self.chain(&nodes[i..end])));
+                    i = end;
+                    continue;
+                },
+                Node::Block(span, Parsed::For(for_loop), nodes) => body.extend(self.for_loop(*span, for_loop, nodes)),
+                Node::Block(_, _, _) => {
+                    self.invalid(String::from("this block has to follow an `if`"));
+                },
+            }
+
+            i += 1;
+        }
+
+        return body;
+    }
+
+    fn statement(&mut self, elem: &Parsed) -> Option<Stmt> {
+        let stmt = match elem {
+            Parsed::VariableDeclare(VariableDeclare { type_class, name, value }) => {
+                let value = self.value(value);
+                Stmt::Let(self.declare(name, type_class), value)
+            },
+            Parsed::Destructure(Destructure { names, value: text }) => {
+                let value = self.value(text);
+                let found = value.type_class();
+                if !found.is_empty() && tuple_types(&found).is_none() {
+                    self.invalid(format!("`{}` is `{}`, only tuples can be destructured", text.trim(), oxide_type(&found)));
+                }
+
+                let types = tuple_types(&found).unwrap_or_default();
+                let vars = names.iter().enumerate()
+                    .map(|(index, name)| self.declare(name, types.get(index).map(String::as_str).unwrap_or_default()))
+                    .collect();
+
+                Stmt::Unpack(vars, value)
+            },
+            Parsed::FunctionCall(FunctionCall { name, parameters, propagate }) => {
+                let question = if *propagate { "?" } else { "" };
+                Stmt::Expr(self.value(&format!("{name}({parameters}){question}")))
+            },
+            Parsed::Print(body) => self.print(body),
+            Parsed::Return(value) if value.trim().is_empty() => Stmt::Return(None),
+            Parsed::Return(value) => Stmt::Return(Some(self.value(value))),
+            _ => return None,
+        };
+
+        return Some(stmt);
+    }
+
+    /// `{name}`s become arguments on the end, every piece left is `Text` or `Index`
+    fn print(&mut self, body: &str) -> Stmt {
+        let mut args = split_top_level(body).into_iter();
+        let format = args.next().unwrap_or_default();
+        let Some(expression::Expr::Str(format)) = parse_expression(&format) else {
+            return Stmt::Expr(self.invalid(String::from("`print` needs a string to format, like `print(\"{}\", x);`")));
+        };
+
+        let mut values: Vec<Expr> = args.map(|arg| self.value(arg.trim())).collect();
+        let mut pieces = match parse_format(&format) {
+            Ok(pieces) => pieces,
+            Err(message) => return Stmt::Expr(self.invalid(message)),
+        };
+
+        // every value has to be used, and every `{}` has to have one
+        let wanted = pieces.iter().filter_map(|piece| match piece {
+            Piece::Index(index, _) => Some(index + 1),
+            _ => None,
+        }).max().unwrap_or(0);
+
+        if wanted != values.len() {
+            let plural = if wanted == 1 { "value" } else { "values" };
+            let given = if values.len() == 1 { "was" } else { "were" };
+            self.invalid(format!("the format string takes {wanted} {plural} but {} {given} given", values.len()));
+        }
+
+        for piece in &mut pieces {
+            if let Piece::Name(name, spec) = piece {
+                values.push(self.name(name));
+                *piece = Piece::Index(values.len() - 1, *spec);
+            }
+        }
+
+        return Stmt::Print(pieces, values);
+    }
+
+    /// an `if` or `if let`, then any `orif`s and an `else`
+    fn chain(&mut self, nodes: &[Node]) -> Stmt {
+        let Some((Node::Block(span, head, body), rest)) = nodes.split_first() else {
+            return Stmt::Expr(self.invalid(String::from("an `if` needs a block")));
+        };
+
+        self.at = *span;
+
+        let (condition, then) = match head {
+            Parsed::IfLet(IfLet { binding, value }) => {
+                let value = self.value(value);
+                let inner = option_inner(&value.type_class()).unwrap_or_default().to_string();
+
+                self.scopes.push(Vec::new());
+                let binding = self.declare(binding, &inner);
+                let then = self.statements(body);
+                self.scopes.pop();
+                (Cond::Some(binding, value), then)
+            },
+            Parsed::If(condition) | Parsed::OrIf(condition) => (Cond::Bool(self.value(condition)), self.block(body)),
+            _ => (Cond::Bool(Expr::Bool(true)), self.block(body)),
+        };
+
+        let (otherwise, at) = match rest.first() {
+            Some(Node::Block(span, Parsed::Else, body)) => (self.block(body), Some(*span)),
+            Some(Node::Block(span, _, _)) => (vec![(*span, self.chain(rest))], Some(*span)),
+            _ => (Vec::new(), None),
+        };
+
+        return Stmt::If(condition, then, otherwise, at);
+    }
+
+    /// `for (k, v) in m` goes over an index into `m`'s items
+    fn for_loop(&mut self, at: Span, for_loop: &ForLoop, nodes: &[Node]) -> Body {
+        let iterable = self.value(&for_loop.iterable);
+        let found = iterable.type_class();
+        if !found.is_empty() && map_types(&found).is_none() && !found.starts_with("Vec<") {
+            self.invalid(format!("`{}` is `{}`, only maps can be looped over", for_loop.iterable.trim(), oxide_type(&found)));
+        }
+
+        let iterable = Expr::Items(Box::new(iterable));
+        let items = self.temp("items", iterable.type_class());
+        let index = self.temp("index", String::from("i32"));
+
+        let local = |var: &Var| Box::new(Expr::Local(var.clone()));
+        let done = Expr::Not(Box::new(Expr::Binary(BinaryOp::Lt, local(&index), Box::new(Expr::Len(local(&items))))));
+        let item = Expr::Index(local(&items), local(&index));
+        let item_type = item.type_class();
+
+        let mut body = vec![(at, Stmt::If(Cond::Bool(done), vec![(at, Stmt::Break)], Vec::new(), None))];
+
+        self.scopes.push(Vec::new());
+        let names: Vec<&str> = for_loop.bindings.split(',').map(str::trim).collect();
+        if names.len() > 1 {
+            let types = tuple_types(&item_type).unwrap_or_default();
+            let vars = names.iter().enumerate()
+                .map(|(i, name)| self.declare(name, types.get(i).map(String::as_str).unwrap_or_default()))
+                .collect();
+
+            body.push((at, Stmt::Unpack(vars, item)));
+        } else {
+            body.push((at, Stmt::Let(self.declare(names[0], &item_type), item)));
+        }
+
+        let next = Expr::Binary(BinaryOp::Add, local(&index), Box::new(Expr::Int(1)));
+        body.push((at, Stmt::Assign(index.clone(), next)));
+        body.extend(self.statements(nodes));
+        self.scopes.pop();
+
+        return vec![(at, Stmt::Let(items, iterable)), (at, Stmt::Let(index, Expr::Int(0))), (at, Stmt::Loop(body))];
+    }
+
+    fn value(&mut self, text: &str) -> Expr {
+        return match parse_expression(text) {
+            Some(expr) => self.expr(&expr),
+            None => self.invalid(format!("can't make sense of `{}`", text.trim())),
+        };
+    }
+
+    /// an error in the statement being lowered, the expression it gives
+    /// back is never generated since `lower` fails
+    fn invalid(&mut self, message: String) -> Expr {
+        self.errors.push((self.at, message));
+        return Expr::Tuple(Vec::new());
+    }
+
+    fn expr(&mut self, expr: &expression::Expr) -> Expr {
+        use expression::Expr as Source;
+        let mut boxed = |expr: &Source| Box::new(self.expr(expr));
+
+        return match expr {
+            Source::Int(value) => Expr::Int(*value),
+            Source::Str(value) => Expr::Str(value.clone()),
+            Source::Bool(value) => Expr::Bool(*value),
+            Source::Name(name) if name == "none" => Expr::None,
+            Source::Name(name) => self.name(name),
+            Source::Tuple(items) => Expr::Tuple(items.iter().map(|item| self.expr(item)).collect()),
+            Source::Map(entries) => Expr::Map(entries.iter().map(|(key, value)| (self.expr(key), self.expr(value))).collect()),
+            Source::Call(name, args) if args.len() == 1 && matches!(name.as_str(), "some" | "ok" | "fail") => {
+                let inner = boxed(&args[0]);
+                match name.as_str() {
+                    "some" => Expr::Some(inner),
+                    "ok" => Expr::Ok(inner),
+                    _ => Expr::Fail(inner),
+                }
+            },
+            Source::Call(name, args) => {
+                let returns = self.procs.iter().find(|(proc_name, _)| proc_name == name).map(|(_, returns)| returns.clone());
+                Expr::Call(name.clone(), args.iter().map(|arg| self.expr(arg)).collect(), returns.unwrap_or_default())
+            },
+            Source::Method(receiver, method, args) => {
+                let method = match (method.as_str(), args.len()) {
+                    ("get", 1) => Method::Get,
+                    ("insert", 2) => Method::Insert,
+                    ("remove", 1) => Method::Remove,
+                    ("contains", 1) => Method::Contains,
+                    ("keys", 0) => Method::Keys,
+                    ("values", 0) => Method::Values,
+                    _ => return self.invalid(format!("maps have no `{method}` taking {} arguments", args.len())),
+                };
+
+                let receiver = self.expr(receiver);
+                if matches!(method, Method::Insert | Method::Remove) && !matches!(receiver, Expr::Local(_)) {
+                    return self.invalid(format!("`{}` needs a map in a variable", method.name()));
+                }
+
+                Expr::Method(method, Box::new(receiver), args.iter().map(|arg| self.expr(arg)).collect())
+            },
+            Source::Field(tuple, index) => Expr::Field(boxed(tuple), *index),
+            Source::Question(inner) => Expr::Try(boxed(inner)),
+            Source::Unary('-', inner) => Expr::Neg(boxed(inner)),
+            Source::Unary(_, inner) => Expr::Not(boxed(inner)),
+            Source::Binary(op, left, right) => {
+                let (left, right) = (boxed(left), boxed(right));
+                match op.as_str() {
+                    "&&" => Expr::And(left, right),
+                    "||" => Expr::Or(left, right),
+                    _ => match BinaryOp::from_symbol(op) {
+                        Some(op) => Expr::Binary(op, left, right),
+                        None => self.invalid(format!("`{op}` isn't an operator")),
+                    },
+                }
+            },
+        };
+    }
+}
+
+/// sets `mutable` on every variable that's assigned or has a map method
+/// change it, `mutated` gets the unique name of each
+fn mark_mutable(body: &mut Body, mutated: &mut Vec<String>) {
+    fn find(body: &Body, mutated: &mut Vec<String>) {
+        for (_, stmt) in body {
+            if let Stmt::Assign(var, _) = stmt {
+                mutated.push(var.unique.clone());
+            }
+
+            for expr in stmt.exprs() {
+                expr.visit(&mut |expr| {
+                    if let Expr::Method(Method::Insert | Method::Remove, receiver, _) = expr {
+                        if let Expr::Local(var) = receiver.as_ref() {
+                            mutated.push(var.unique.clone());
+                        }
+                    }
+                });
+            }
+
+            for inner in stmt.bodies() {
+                find(inner, mutated);
+            }
+        }
+    }
+
+    fn mark(body: &mut Body, mutated: &[String]) {
+        for (_, stmt) in body {
+            match stmt {
+                Stmt::Let(var, _) => var.mutable = mutated.contains(&var.unique),
+                Stmt::Unpack(vars, _) => {
+                    for var in vars {
+                        var.mutable = mutated.contains(&var.unique);
+                    }
+                },
+                Stmt::If(condition, then, otherwise, _) => {
+                    if let Cond::Some(var, _) = condition {
+                        var.mutable = mutated.contains(&var.unique);
+                    }
+
+                    mark(then, mutated);
+                    mark(otherwise, mutated);
+                },
+                Stmt::Loop(inner) | Stmt::Block(inner) => mark(inner, mutated),
+                _ => (),
+            }
+        }
+    }
+
+    find(body, mutated);
+    mark(body, mutated);
+}
+
+/// a `for` loop as the lowering writes it out, for targets with a loop
+/// over a collection of their own
+pub(crate) struct ForEach<'a> {
+    /// the `Items` of what it goes over
+    pub(crate) items: &'a Expr,
+    /// the `Let` or `Unpack` each item is bound with
+    pub(crate) binding: &'a Stmt,
+    pub(crate) body: &'a [(Span, Stmt)],
+}
+
+/// the `for` loop at the front of `body`, it takes up three statements:
+/// the items, the index into them and the `Loop`
+pub(crate) fn for_each(body: &[(Span, Stmt)]) -> Option<ForEach<'_>> {
+    let [(_, Stmt::Let(items_var, items @ Expr::Items(_))), (_, Stmt::Let(index, Expr::Int(0))), (_, Stmt::Loop(inner)), ..] = body else {
+        return None;
+    };
+
+    let [(_, Stmt::If(Cond::Bool(_), _, _, _)), (_, binding @ (Stmt::Let(..) | Stmt::Unpack(..))), (_, Stmt::Assign(next, _)), rest @ ..] = inner.as_slice() else {
+        return None;
+    };
+
+    if !items_var.temp || !index.temp || next.unique != index.unique {
+        return None;
+    }
+
+    return Some(ForEach { items, binding, body: rest });
+}
+
+/// what `--emit=ir` prints, each file's procs and then the statements outside them
+pub fn print(modules: &[(String, Module)]) -> String {
+    let mut output = String::new();
+
+    for (path, module) in modules {
+        let _ = writeln!(output, "; {path}");
+
+        for (index, item) in module.items.iter().enumerate() {
+            match item {
+                Item::Function(function) => {
+                    let params: Vec<String> = function.params.iter().map(typed).collect();
+                    let returns = if function.returns.is_empty() { String::new() } else { format!(" -> {}", oxide_type(&function.returns)) };
+                    let public = if function.public { "pub " } else { "" };
+
+                    let _ = writeln!(output, "\n{public}proc {}({}){returns}", function.name, params.join(", "));
+                    print_body(&mut output, &function.body, 1, &mut 0);
+                },
+                Item::Import(_, name) => {
+                    let _ = writeln!(output, "\nimport {name}");
+                },
+                Item::Const(constant) => {
+                    // `const`s one after another stay together
+                    if index == 0 || !matches!(module.items[index - 1], Item::Const(_)) {
+                        output.push('\n');
+                    }
+
+                    let _ = writeln!(output, "const {}: {} = {}", constant.name, oxide_type(&constant.type_class), show(&constant.value));
+                },
+            }
+        }
+
+        if !module.top_level.is_empty() {
+            output.push_str("\ntop level\n");
+            print_body(&mut output, &module.top_level, 1, &mut 0);
+        }
+
+        output.push('\n');
+    }
+
+    return output.trim_end().to_string() + "\n";
+}
+
+/// the source line is only shown when it changes
+fn print_body(output: &mut String, body: &Body, depth: usize, last_line: &mut usize) {
+    for (span, stmt) in body {
+        let line = if span.line != *last_line { format!("{}:{}", span.line, span.column) } else { String::new() };
+        *last_line = span.line;
+
+        let text = match stmt {
+            Stmt::Let(var, value) => format!("let {} = {}", typed(var), show(value)),
+            Stmt::Unpack(vars, value) => {
+                let vars: Vec<String> = vars.iter().map(typed).collect();
+                format!("let ({}) = {}", vars.join(", "), show(value))
+            },
+            Stmt::Assign(var, value) => format!("{} = {}", name(var), show(value)),
+            Stmt::Expr(value) => show(value),
+            Stmt::Print(pieces, args) => {
+                let mut args: Vec<String> = args.iter().map(show).collect();
+                args.insert(0, format!("{:?}", format_string(pieces, true)));
+                format!("print({})", args.join(", "))
+            },
+            Stmt::Return(Some(value)) => format!("return {}", show(value)),
+            Stmt::Return(None) => String::from("return"),
+            Stmt::If(Cond::Bool(condition), ..) => format!("if {}", show(condition)),
+            Stmt::If(Cond::Some(var, value), ..) => format!("if let some({}) = {}", typed(var), show(value)),
+            Stmt::Loop(_) => String::from("loop"),
+            Stmt::Break => String::from("break"),
+            Stmt::Block(_) => String::from("block"),
+        };
+
+        let _ = writeln!(output, "    {line:<8} {}{text}", "    ".repeat(depth - 1));
+
+        match stmt {
+            Stmt::If(_, then, otherwise, _) => {
+                print_body(output, then, depth + 1, last_line);
+                if !otherwise.is_empty() {
+                    let _ = writeln!(output, "    {:<8} {}else", "", "    ".repeat(depth - 1));
+                    print_body(output, otherwise, depth + 1, last_line);
+                }
+            },
+            Stmt::Loop(inner) | Stmt::Block(inner) => print_body(output, inner, depth + 1, last_line),
+            _ => (),
+        }
+    }
+}
+
+/// temps are `%items_1`, nothing in the source can be called that
+fn name(var: &Var) -> String {
+    return if var.temp { format!("%{}", var.unique) } else { var.unique.clone() };
+}
+
+fn typed(var: &Var) -> String {
+    if var.type_class.is_empty() {
+        return name(var);
+    }
+
+    return format!("{}: {}", name(var), oxide_type(&var.type_class));
+}
+
+pub(crate) fn show(expr: &Expr) -> String {
+    let list = |items: &[Expr]| items.iter().map(show).collect::<Vec<String>>().join(", ");
+
+    return match expr {
+        Expr::Int(value) => value.to_string(),
+        Expr::Str(value) => format!("{value:?}"),
+        Expr::Bool(value) => value.to_string(),
+        Expr::None => String::from("none"),
+        Expr::Local(var) => name(var),
+        Expr::Const(name, _) => name.clone(),
+        Expr::Tuple(items) if items.len() == 1 => format!("({},)", show(&items[0])),
+        Expr::Tuple(items) => format!("({})", list(items)),
+        Expr::Map(entries) => {
+            let entries: Vec<String> = entries.iter().map(|(key, value)| format!("{}: {}", show(key), show(value))).collect();
+            format!("{{{}}}", entries.join(", "))
+        },
+        Expr::Some(inner) => format!("some({})", show(inner)),
+        Expr::Ok(inner) => format!("ok({})", show(inner)),
+        Expr::Fail(inner) => format!("fail({})", show(inner)),
+        Expr::Call(name, args, _) => format!("{name}({})", list(args)),
+        Expr::Method(method, receiver, args) => format!("{}.{}({})", operand(receiver, show(receiver), false), method.name(), list(args)),
+        Expr::Field(tuple, index) => format!("{}.{index}", operand(tuple, show(tuple), false)),
+        Expr::Try(inner) => format!("{}?", operand(inner, show(inner), false)),
+        Expr::Neg(inner) => format!("-{}", operand(inner, show(inner), true)),
+        Expr::Not(inner) => format!("!{}", operand(inner, show(inner), true)),
+        Expr::Binary(op, left, right) => infix(expr, op.symbol(), left, right, &mut show),
+        Expr::And(left, right) => infix(expr, "&&", left, right, &mut show),
+        Expr::Or(left, right) => infix(expr, "||", left, right, &mut show),
+        Expr::Items(inner) => format!("items({})", show(inner)),
+        Expr::Len(inner) => format!("len({})", show(inner)),
+        Expr::Index(list, index) => format!("{}[{}]", operand(list, show(list), false), show(index)),
+    };
+}
@@ -0,0 +1,326 @@
+use crate::backend::{Backend, Linked};
+use crate::expression::{BinaryOp, format_string};
+use crate::emitter::Emitter;
+use crate::generator::Unit;
+use crate::ir::{Body, Cond, Expr, Function, Method, Stmt, Var};
+use crate::parser::Span;
+use crate::sourcemap::tag_markers;
+
+/// what every generated module starts with, see `js_runtime.js`
+pub(crate) const RUNTIME: &str = include_str!("js_runtime.js");
+
+/// writes an es module against the runtime in `js_runtime.js`. imported
+/// files are bundled into the same module, the entry file's `pub proc`s
+/// are its exports
+pub(crate) struct Js {
+    unit: Unit,
+    /// `math__` in a file imported as `math`, procs share one namespace
+    prefix: String,
+    has_main: bool,
+    temps: usize,
+}
+
+impl Js {
+    pub(crate) fn new(unit: Unit, module: Option<&str>) -> Js {
+        let prefix = match module {
+            Some(module) => format!("{}__", js_identifier(module)),
+            None => String::new(),
+        };
+
+        return Js { unit, prefix, has_main: false, temps: 0 };
+    }
+
+    fn temp(&mut self, name: &str) -> String {
+        self.temps += 1;
+        return format!("ox_{name}_{}", self.temps);
+    }
+
+    /// the name of a proc in the bundle, `math::double` is `math__double`
+    fn proc_name(&self, name: &str) -> String {
+        if let Some((module, name)) = name.rsplit_once("::") {
+            let module = module.rsplit("::").next().unwrap_or(module);
+            return format!("{}__{}", js_identifier(module), js_identifier(name));
+        }
+
+        return format!("{}{}", self.prefix, js_identifier(name));
+    }
+
+    /// records where a runtime error would be reported
+    fn at(&self, span: Span) -> String {
+        return format!("ox.at({}OX_FILE, {}, {})", self.prefix, span.line, span.column);
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        let list = |items: &[Expr]| items.iter().map(|item| self.expr(item)).collect::<Vec<String>>().join(", ");
+
+        match expr {
+            Expr::Int(value) => value.to_string(),
+            Expr::Str(value) => format!("\"{}\"", escape(value)),
+            Expr::Bool(value) => value.to_string(),
+            Expr::None => String::from("ox.none"),
+            Expr::Local(var) => name(var),
+            Expr::Const(_, value) => self.expr(value),
+            Expr::Tuple(items) => format!("ox.tuple({})", list(items)),
+            Expr::Map(entries) => {
+                let pairs: Vec<String> = entries.iter()
+                    .map(|(key, value)| format!("{}, {}", self.expr(key), self.expr(value)))
+                    .collect();
+
+                format!("ox.map({})", pairs.join(", "))
+            },
+            Expr::Some(inner) => format!("ox.some({})", self.expr(inner)),
+            Expr::Ok(inner) => format!("ox.ok({})", self.expr(inner)),
+            Expr::Fail(inner) => format!("ox.fail({})", self.expr(inner)),
+            Expr::Call(callee, args, _) => format!("{}({})", self.proc_name(callee), list(args)),
+            Expr::Method(method, receiver, args) => {
+                let mut args: Vec<String> = args.iter().map(|arg| self.expr(arg)).collect();
+                let receiver = self.expr(receiver);
+
+                // `insert` and `remove` hand the variable its new map
+                if matches!(method, Method::Insert | Method::Remove) {
+                    args.push(format!("(map) => {receiver} = map"));
+                }
+
+                args.insert(0, receiver);
+                format!("ox.{}({})", method.name(), args.join(", "))
+            },
+            Expr::Field(tuple, index) => format!("ox.field({}, {index})", self.expr(tuple)),
+            Expr::Try(inner) => format!("ox.attempt({})", self.expr(inner)),
+            Expr::Neg(inner) => format!("ox.neg({})", self.expr(inner)),
+            Expr::Not(inner) => format!("ox.not({})", self.expr(inner)),
+            Expr::Binary(op, left, right) => {
+                let (left, right) = (self.expr(left), self.expr(right));
+
+                match op {
+                    BinaryOp::Add => format!("ox.add({left}, {right})"),
+                    BinaryOp::Sub => format!("ox.sub({left}, {right})"),
+                    BinaryOp::Mul => format!("ox.mul({left}, {right})"),
+                    BinaryOp::Div => format!("ox.div({left}, {right})"),
+                    BinaryOp::Rem => format!("ox.rem({left}, {right})"),
+                    BinaryOp::Eq => format!("(ox.compare({left}, {right}) === 0)"),
+                    BinaryOp::Ne => format!("(ox.compare({left}, {right}) !== 0)"),
+                    op => format!("(ox.compare({left}, {right}) {} 0)", op.symbol()),
+                }
+            },
+            Expr::And(left, right) => format!("(ox.cond({}) && ox.cond({}))", self.expr(left), self.expr(right)),
+            Expr::Or(left, right) => format!("(ox.cond({}) || ox.cond({}))", self.expr(left), self.expr(right)),
+            Expr::Items(inner) => format!("ox.iterate({})", self.expr(inner)),
+            Expr::Len(inner) => format!("{}.length", self.expr(inner)),
+            Expr::Index(list, index) => format!("{}[{}]", self.expr(list), self.expr(index)),
+        }
+    }
+
+    /// a statement with where it came from in front
+    fn line(&self, out: &mut Emitter, span: Span, line: &str) {
+        out.line(&format!("{}; {line}", self.at(span)));
+    }
+
+    fn statements(&mut self, out: &mut Emitter, body: &Body) {
+        for (span, stmt) in body {
+            out.at(Some(*span));
+            self.statement(out, *span, stmt);
+        }
+    }
+
+    fn statement(&mut self, out: &mut Emitter, span: Span, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let(var, value) => self.line(out, span, &format!("let {} = {};", name(var), self.expr(value))),
+            Stmt::Unpack(vars, value) => {
+                let names: Vec<String> = vars.iter().map(name).collect();
+                self.line(out, span, &format!("let [{}] = ox.unpack({}, {});", names.join(", "), self.expr(value), names.len()));
+            },
+            Stmt::Assign(var, value) => self.line(out, span, &format!("{} = {};", name(var), self.expr(value))),
+            Stmt::Expr(value) => self.line(out, span, &format!("{};", self.expr(value))),
+            Stmt::Print(pieces, args) => {
+                let mut values: Vec<String> = args.iter().map(|arg| self.expr(arg)).collect();
+                values.insert(0, format!("\"{}\"", escape(&format_string(pieces, true))));
+                self.line(out, span, &format!("ox.print({});", values.join(", ")));
+            },
+            Stmt::Return(Some(value)) => self.line(out, span, &format!("return {};", self.expr(value))),
+            Stmt::Return(None) => self.line(out, span, "return;"),
+            Stmt::If(..) => self.branch(out, span, stmt, false),
+            Stmt::Loop(body) => {
+                out.open("for (;;) {");
+                self.statements(out, body);
+                out.close();
+            },
+            Stmt::Break => out.line("break;"),
+            Stmt::Block(body) => {
+                out.open("{");
+                self.statements(out, body);
+                out.close();
+            },
+        }
+    }
+
+    /// an `if`, `chained` when it's all there is in the `else` before it
+    fn branch(&mut self, out: &mut Emitter, span: Span, stmt: &Stmt, chained: bool) {
+        let Stmt::If(condition, then, otherwise, else_at) = stmt else {
+            return;
+        };
+
+        let wrapped = chained && matches!(condition, Cond::Some(..));
+
+        match condition {
+            Cond::Bool(value) => {
+                let head = format!("if (ox.cond(({}, {}))) {{", self.at(span), self.expr(value));
+                if chained {
+                    out.chain(&format!("else {head}"));
+                } else {
+                    out.open(&head);
+                }
+            },
+            Cond::Some(var, value) => {
+                // the option has to be looked at before the `if`
+                if wrapped {
+                    out.chain("else {");
+                }
+
+                let opt = self.temp("opt");
+                self.line(out, span, &format!("const {opt} = {};", self.expr(value)));
+                out.open(&format!("if (ox.isSome({opt})) {{"));
+                out.line(&format!("let {} = {opt}.value;", name(var)));
+            },
+        }
+
+        self.statements(out, then);
+        out.close();
+
+        match otherwise.as_slice() {
+            [] => (),
+            [(span, stmt @ Stmt::If(..))] => {
+                out.at(*else_at);
+                self.branch(out, *span, stmt, true);
+            },
+            _ => {
+                out.at(*else_at);
+                out.chain("else {");
+                self.statements(out, otherwise);
+                out.close();
+            },
+        }
+
+        if wrapped {
+            out.close();
+        }
+    }
+}
+
+impl Backend for Js {
+    fn function(&mut self, out: &mut Emitter, function: &Function) {
+        let proc_name = self.proc_name(&function.name);
+        let params: Vec<String> = function.params.iter().map(name).collect();
+
+        if function.name == "main" && self.unit == Unit::Program {
+            self.has_main = true;
+        }
+
+        // the entry file's `pub proc`s and its main can be imported from javascript
+        let export = self.unit != Unit::Import && (function.public || self.unit == Unit::Include || proc_name == "main");
+        let export = if export { "export " } else { "" };
+        out.open(&format!("{export}function {proc_name}({}) {{", params.join(", ")));
+
+        // a `?` that fails throws, the proc gives back the `fail`
+        let fails = function.returns.starts_with("Result<");
+        if fails {
+            out.open("try {");
+        }
+
+        self.statements(out, &function.body);
+
+        if fails {
+            out.close();
+            out.chain("catch (error) {");
+            out.line("return ox.propagated(error);");
+            out.close();
+        }
+
+        out.close();
+    }
+
+    fn finish(&mut self, mut module: Emitter, top_level: &Body) -> String {
+        if self.unit == Unit::Program {
+            // top level statements run from a generated main so their names stay out of the module's scope
+            if !self.has_main {
+                module.blank_line();
+                module.open("function ox_main() {");
+                self.statements(&mut module, top_level);
+                module.at(None);
+                module.close();
+            }
+
+            module.blank_line();
+            if !self.has_main {
+                module.line("export { ox_main as main };");
+            }
+
+            // importing the module doesn't run it
+            let main = if self.has_main { "main" } else { "ox_main" };
+            module.open("if (await ox.isEntry(import.meta.url)) {");
+            module.line(&format!("ox.run({main});"));
+            module.close();
+        }
+
+        return module.finish();
+    }
+
+    /// imported files go first, each reporting runtime errors against its own path
+    fn link(&self, entry: String, entry_path: &str, imports: Vec<Linked>) -> String {
+        let mut output = String::new();
+
+        for import in imports {
+            output.push_str(&format!("\nconst {}__OX_FILE = \"{}\";\n\n", js_identifier(&import.name), escape(&import.path)));
+            output.push_str(&tag_markers(&import.source, &import.path));
+        }
+
+        output.push_str(&format!("\nconst OX_FILE = \"{}\";\n\n", escape(entry_path)));
+        output.push_str(&entry);
+        return output;
+    }
+}
+
+/// reserved words and globals a name can't shadow
+const RESERVED: [&str; 49] = [
+    "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+    "delete", "do", "else", "enum", "export", "extends", "false", "finally", "for",
+    "function", "if", "implements", "import", "in", "instanceof", "interface", "let", "new",
+    "null", "package", "private", "protected", "public", "return", "static", "super",
+    "switch", "this", "throw", "true", "try", "typeof", "var", "void", "while", "with",
+    "yield", "arguments", "eval", "undefined",
+];
+
+/// the name an oxide variable or proc goes by in the generated javascript.
+/// the runtime is `ox` and its names start with `ox_` or `OX_`, so
+/// anything that could clash gets an `ox_` prefix
+fn js_identifier(name: &str) -> String {
+    if RESERVED.contains(&name) || name == "ox" || name.starts_with("ox_") || name.starts_with("OX_") {
+        return format!("ox_{name}");
+    }
+
+    return name.to_string();
+}
+
+/// temps the lowering made up are `ox_`, which `js_identifier` keeps
+/// clear of oxide names
+fn name(var: &Var) -> String {
+    return if var.temp { format!("ox_{}", var.unique) } else { js_identifier(&var.unique) };
+}
+
+/// the inside of a javascript string literal
+fn escape(text: &str) -> String {
+    let mut output = String::new();
+
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            '\r' => output.push_str("\\r"),
+            c if c.is_control() => output.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => output.push(c),
+        }
+    }
+
+    return output;
+}
@@ -0,0 +1,417 @@
+// the oxide runtime for generated javascript. ints are numbers checked
+// after every operation so they overflow and divide the way rust's i32
+// does, strings and vectors are plain strings and arrays. runtime errors
+// are reported with the oxide file, line and column set by `ox.at`
+
+const ox = (() => {
+    const MIN = -2147483648;
+    const MAX = 2147483647;
+    const where = { file: "<source>", line: 0, column: 0 };
+
+    class Panic extends Error {}
+
+    // thrown by `?` and caught by the proc it's in, see `attempt`
+    class Propagate {
+        constructor(value) {
+            this.value = value;
+        }
+    }
+
+    class Tuple {
+        constructor(items) {
+            this.items = items;
+        }
+    }
+
+    // `some`, `none`, `ok` and `fail`
+    class Tagged {
+        constructor(tag, value) {
+            this.tag = tag;
+            this.value = value;
+        }
+    }
+
+    // entries are kept sorted by key, a map is never changed once made
+    class OxMap {
+        constructor(entries) {
+            this.entries = entries;
+        }
+    }
+
+    const none = new Tagged("none", undefined);
+
+    function at(file, line, column) {
+        where.file = file;
+        where.line = line;
+        where.column = column;
+    }
+
+    function panic(message) {
+        throw new Panic(message);
+    }
+
+    function checked(result, message) {
+        if (result < MIN || result > MAX) {
+            panic(message);
+        }
+
+        return result;
+    }
+
+    // the same order as the interpreter's values
+    function rank(value) {
+        switch (typeof value) {
+            case "number": return 0;
+            case "string": return 1;
+            case "boolean": return 2;
+            case "undefined": return 8;
+        }
+
+        if (value instanceof Tuple) return 3;
+        if (value instanceof Tagged) return value.tag === "some" || value.tag === "none" ? 4 : 5;
+        if (value instanceof OxMap) return 6;
+        return 7;
+    }
+
+    function compareLists(a, b) {
+        for (let i = 0; i < a.length && i < b.length; i++) {
+            const order = compare(a[i], b[i]);
+            if (order !== 0) {
+                return order;
+            }
+        }
+
+        return a.length < b.length ? -1 : a.length > b.length ? 1 : 0;
+    }
+
+    function compare(a, b) {
+        const ranks = rank(a) - rank(b);
+        if (ranks !== 0) {
+            return ranks < 0 ? -1 : 1;
+        }
+
+        if (typeof a !== "object") {
+            return a < b ? -1 : a > b ? 1 : 0;
+        }
+
+        if (a instanceof Tuple) {
+            return compareLists(a.items, b.items);
+        }
+
+        if (a instanceof Tagged) {
+            // none before some, ok before fail
+            const tags = ["none", "some", "ok", "fail"];
+            const order = tags.indexOf(a.tag) - tags.indexOf(b.tag);
+            return order !== 0 ? Math.sign(order) : compare(a.value, b.value);
+        }
+
+        if (a instanceof OxMap) {
+            return compareLists(a.entries.flat(), b.entries.flat());
+        }
+
+        return compareLists(a, b);
+    }
+
+    function cond(value) {
+        if (typeof value !== "boolean") {
+            panic("conditions have to be a bool");
+        }
+
+        return value;
+    }
+
+    function add(a, b) {
+        if (typeof a === "string") {
+            return a + b;
+        }
+
+        return checked(a + b, "attempt to add with overflow");
+    }
+
+    function sub(a, b) {
+        return checked(a - b, "attempt to subtract with overflow");
+    }
+
+    function mul(a, b) {
+        // a product too big to be exact is still too big for an i32
+        return checked(a * b, "attempt to multiply with overflow");
+    }
+
+    function div(a, b) {
+        if (b === 0) {
+            panic("attempt to divide by zero");
+        }
+
+        return checked(Math.trunc(a / b), "attempt to divide with overflow");
+    }
+
+    function rem(a, b) {
+        if (b === 0) {
+            panic("attempt to calculate the remainder with a divisor of zero");
+        }
+
+        if (a === MIN && b === -1) {
+            panic("attempt to calculate the remainder with overflow");
+        }
+
+        return a % b;
+    }
+
+    function neg(a) {
+        return checked(-a, "attempt to negate with overflow");
+    }
+
+    function not(a) {
+        return typeof a === "boolean" ? !a : ~a;
+    }
+
+    function tuple(...items) {
+        return new Tuple(items);
+    }
+
+    function field(value, index) {
+        if (!(value instanceof Tuple) || index >= value.items.length) {
+            panic(`there's no field \`${index}\``);
+        }
+
+        return value.items[index];
+    }
+
+    // the fields of a tuple being split into `count` names
+    function unpack(value, count) {
+        if (!(value instanceof Tuple) || value.items.length !== count) {
+            panic(`${show(value, true)} can't be split into ${count}`);
+        }
+
+        return value.items;
+    }
+
+    function find(map, key) {
+        const index = map.entries.findIndex(([existing]) => compare(existing, key) >= 0);
+        const at = index === -1 ? map.entries.length : index;
+        return [at, at < map.entries.length && compare(map.entries[at][0], key) === 0];
+    }
+
+    function map(...pairs) {
+        let built = new OxMap([]);
+        for (let i = 0; i < pairs.length; i += 2) {
+            insert(built, pairs[i], pairs[i + 1], (next) => built = next);
+        }
+
+        return built;
+    }
+
+    function get(map, key) {
+        const [at, found] = find(map, key);
+        return found ? some(map.entries[at][1]) : none;
+    }
+
+    function contains(map, key) {
+        return find(map, key)[1];
+    }
+
+    // `set` gets the new map, anything else holding the old one still sees it as it was
+    function insert(map, key, value, set) {
+        const [at, found] = find(map, key);
+        const entries = map.entries.slice();
+        entries.splice(at, found ? 1 : 0, [key, value]);
+        set(new OxMap(entries));
+        return found ? some(map.entries[at][1]) : none;
+    }
+
+    function remove(map, key, set) {
+        const [at, found] = find(map, key);
+        if (!found) {
+            return none;
+        }
+
+        const entries = map.entries.slice();
+        entries.splice(at, 1);
+        set(new OxMap(entries));
+        return some(map.entries[at][1]);
+    }
+
+    function keys(map) {
+        return map.entries.map(([key]) => key);
+    }
+
+    function values(map) {
+        return map.entries.map(([, value]) => value);
+    }
+
+    // the items a for loop goes over, key and value pairs for a map
+    function iterate(value) {
+        if (value instanceof OxMap) {
+            return value.entries.map(([key, entry]) => new Tuple([key, entry]));
+        }
+
+        if (Array.isArray(value)) {
+            return value.slice();
+        }
+
+        panic("only maps and vectors can be looped over");
+    }
+
+    function some(value) {
+        return new Tagged("some", value);
+    }
+
+    function ok(value) {
+        return new Tagged("ok", value);
+    }
+
+    function fail(value) {
+        return new Tagged("fail", value);
+    }
+
+    function isSome(value) {
+        if (!(value instanceof Tagged) || (value.tag !== "some" && value.tag !== "none")) {
+            panic("`if let` only works on an `opt`");
+        }
+
+        return value.tag === "some";
+    }
+
+    // `?`, a `fail` leaves the proc
+    function attempt(value) {
+        if (!(value instanceof Tagged) || (value.tag !== "ok" && value.tag !== "fail")) {
+            panic("`?` only works on a `result`");
+        }
+
+        if (value.tag === "fail") {
+            throw new Propagate(value);
+        }
+
+        return value.value;
+    }
+
+    // what a proc using `?` gives back when one of them failed
+    function propagated(error) {
+        if (error instanceof Propagate) {
+            return error.value;
+        }
+
+        throw error;
+    }
+
+    function escape(text) {
+        return text.replace(/[\\"\n\t\r]/g, (c) => ({ "\\": "\\\\", "\"": "\\\"", "\n": "\\n", "\t": "\\t", "\r": "\\r" })[c]);
+    }
+
+    // `{}` and `{:?}` the same as rust prints them
+    function show(value, debug) {
+        switch (rank(value)) {
+            case 0:
+            case 2:
+                return String(value);
+            case 1:
+                return debug ? `"${escape(value)}"` : value;
+            case 3:
+                return value.items.length === 1
+                    ? `(${show(value.items[0], true)},)`
+                    : `(${value.items.map((item) => show(item, true)).join(", ")})`;
+            case 4:
+            case 5: {
+                const names = { some: "Some", ok: "Ok", fail: "Err" };
+                return value.tag === "none" ? "None" : `${names[value.tag]}(${show(value.value, true)})`;
+            }
+            case 6:
+                return `{${value.entries.map(([key, entry]) => `${show(key, true)}: ${show(entry, true)}`).join(", ")}}`;
+            case 7:
+                return `[${value.map((item) => show(item, true)).join(", ")}]`;
+            default:
+                return "()";
+        }
+    }
+
+    // `value` padded and cut the way rust does for the spec after the
+    // `:` in `{0:*>5}`, the same parts `Spec` in the compiler has
+    function pad(value, spec) {
+        const [, fill, align, sign, zero, width, precision] = /^(?:(.)?([<^>]))?(\+)?(0)?(\d+)?(?:\.(\d+))?$/u.exec(spec);
+        const number = typeof value === "number";
+        let text = [...show(value, false)];
+        let prefix = "";
+
+        if (number && text[0] === "-") {
+            prefix = text.shift();
+        } else if (number && sign) {
+            prefix = "+";
+        } else if (!number && precision !== undefined) {
+            text = text.slice(0, Number(precision));
+        }
+
+        const padding = Math.max(0, Number(width ?? 0) - prefix.length - text.length);
+        if (number && zero) {
+            return prefix + "0".repeat(padding) + text.join("");
+        }
+
+        const before = { "<": 0, "^": Math.floor(padding / 2), ">": padding }[align ?? (number ? ">" : "<")];
+        return (fill ?? " ").repeat(before) + prefix + text.join("") + (fill ?? " ").repeat(padding - before);
+    }
+
+    // `format` only has `{{`, `}}` and `{0}` with an optional spec, the
+    // generator numbers every argument
+    function print(format, ...args) {
+        const line = format.replace(/\{\{|\}\}|\{(\d+)(?::([^}]*))?\}/g, (piece, index, spec) => {
+            if (index === undefined) {
+                return piece[0];
+            }
+
+            if (Number(index) >= args.length) {
+                panic(`nothing to fill in argument ${index} with`);
+            }
+
+            const value = args[Number(index)];
+            return spec === "?" ? show(value, true) : pad(value, spec ?? "");
+        });
+
+        console.log(line);
+    }
+
+    function exit(code) {
+        if (typeof process !== "undefined") {
+            process.exitCode = code;
+        }
+    }
+
+    // whether the module at `url` is the one node was asked to run
+    async function isEntry(url) {
+        if (typeof process === "undefined" || !process.argv[1]) {
+            return false;
+        }
+
+        try {
+            const { realpathSync } = await import("node:fs");
+            const { pathToFileURL } = await import("node:url");
+            return pathToFileURL(realpathSync(process.argv[1])).href === url;
+        } catch {
+            return false;
+        }
+    }
+
+    // runs main, printing runtime errors and a failing result the way the compiled program does
+    function run(main) {
+        try {
+            const result = main();
+
+            if (result instanceof Tagged && result.tag === "fail") {
+                console.error(`error: ${show(result.value, false)}`);
+                exit(1);
+            }
+        } catch (error) {
+            if (!(error instanceof Panic)) {
+                throw error;
+            }
+
+            console.error(`error: ${where.file}:${where.line}:${where.column}: ${error.message}`);
+            // what a rust panic exits with
+            exit(101);
+        }
+    }
+
+    return {
+        none, at, cond, add, sub, mul, div, rem, neg, not, tuple, field, unpack, map, get, contains,
+        insert, remove, keys, values, iterate, some, ok, fail, isSome, attempt, propagated,
+        compare, print, isEntry, run,
+    };
+})();
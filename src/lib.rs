@@ -0,0 +1,200 @@
+#![allow(clippy::needless_return)]
+
+//! the oxide compiler as a library, `compile` goes straight from oxide
+//! source to rust, or each stage can be run on its own
+
+use std::fmt;
+
+mod tokeniser;
+mod checker;
+mod emitter;
+mod generator;
+mod backend;
+mod c_backend;
+mod js_backend;
+mod wat_backend;
+pub mod expression;
+pub mod ir;
+mod optimiser;
+mod linter;
+pub mod parser;
+pub mod sourcemap;
+pub mod runner;
+pub mod formatter;
+pub mod project;
+pub mod build_script;
+mod program;
+mod naming;
+pub mod interpreter;
+pub mod bytecode;
+pub mod vm;
+
+pub use tokeniser::Token;
+pub use parser::{Parsed, Span};
+pub use sourcemap::SourceMap;
+pub use backend::Target;
+use generator::Unit;
+
+pub use program::compile_file;
+pub use interpreter::interpret_file;
+pub use linter::lint_file;
+
+/// how the rust is generated
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// back `map<K, V>` with a `BTreeMap` so iteration order is deterministic
+    pub ordered_maps: bool,
+    /// run the generated rust through rustfmt if it's installed
+    pub rustfmt: bool,
+    /// keep the `// ox:line:col` markers in the generated rust
+    pub source_map: bool,
+    /// generate a module to `include!` into a rust crate, procs are `pub`
+    /// and there's no `fn main`
+    pub module: bool,
+    /// the language to generate
+    pub target: Target,
+    /// fold constants and drop code that can never run, `-O`
+    pub optimise: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// the generated source, rust unless `Options::target` asks for c,
+    /// javascript or webassembly text
+    pub code: String,
+    /// maps lines of `code` back to the oxide source
+    pub source_map: SourceMap,
+    /// oxide names that had to be renamed in the rust, as (oxide, rust)
+    pub renamed: Vec<(String, String)>,
+}
+
+/// an error in the oxide source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub span: Option<Span>,
+    pub message: String,
+    /// the imported file the error is in, `None` for the file being compiled
+    pub file: Option<String>,
+}
+
+impl Diagnostic {
+    /// `error: file.oxi:3:5: message`, `path` is the file being compiled
+    pub fn render(&self, path: &str) -> String {
+        return format!("error: {}", self.located(path));
+    }
+
+    /// `file.oxi:3:5: message`, for tools that add their own `error:`
+    pub fn located(&self, path: &str) -> String {
+        let path = self.file.as_deref().unwrap_or(path);
+
+        return match self.span {
+            Some(span) => format!("{path}:{}:{}: {}", span.line, span.column, self.message),
+            None => format!("{path}: {}", self.message),
+        };
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{}:{}: {}", span.line, span.column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// code that compiles but is likely a mistake, `code` names the lint
+/// so a `# allow(code)` comment can turn it off
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub span: Span,
+    pub code: &'static str,
+    pub message: String,
+    /// the imported file the warning is in, `None` for the file being linted
+    pub file: Option<String>,
+}
+
+impl Warning {
+    /// `warning: file.oxi:3:5: message [code]`, `path` is the file being linted
+    pub fn render(&self, path: &str) -> String {
+        let path = self.file.as_deref().unwrap_or(path);
+        return format!("warning: {path}:{}:{}: {} [{}]", self.span.line, self.span.column, self.message, self.code);
+    }
+}
+
+/// the tokens of `source`, or where the first thing that isn't oxide is
+pub fn tokenise(source: &str) -> Result<Vec<Token>, Diagnostic> {
+    return tokeniser::tokeniser(source.to_string());
+}
+
+/// the statements the tokens make up, `source` gives each its column
+pub fn parse(tokens: Vec<Token>, source: &str) -> Result<Vec<(Span, Parsed)>, Diagnostic> {
+    return parser::parser(tokens, source);
+}
+
+/// checks the statements and lowers them to the ir every backend is
+/// generated from, folded when `Options::optimise` is set
+pub fn check(parsed: &[(Span, Parsed)], options: &Options) -> Result<ir::Module, Vec<Diagnostic>> {
+    let diagnostics = |errors: Vec<(Span, String)>| -> Vec<Diagnostic> {
+        return errors.into_iter().map(|(span, message)| Diagnostic { span: Some(span), message, file: None }).collect();
+    };
+
+    let mut errors = checker::checker(parsed, options.module);
+    if options.target == Target::Wat {
+        errors.extend(wat_backend::unsupported(parsed));
+    }
+
+    if !errors.is_empty() {
+        return Err(diagnostics(errors));
+    }
+
+    let mut module = ir::lower(parsed.to_vec()).map_err(diagnostics)?;
+
+    // arithmetic that's folded has to be known not to fail
+    if options.optimise {
+        let errors = optimiser::optimise(&mut module);
+        if !errors.is_empty() {
+            return Err(diagnostics(errors));
+        }
+    }
+
+    return Ok(module);
+}
+
+/// rust for a checked file, imports aren't followed, see `compile_file`
+pub fn generate(module: &ir::Module, options: &Options) -> Output {
+    let renamed = generator::renamed(module);
+    let unit = if options.module { Unit::Include } else { Unit::Program };
+    let rust = generator::generator(module, options, unit, None);
+    let rust = generator::link(options, rust, "<source>", Vec::new());
+
+    return finish(rust, options, renamed);
+}
+
+/// formats the generated code and builds its source map
+fn finish(mut code: String, options: &Options, renamed: Vec<(String, String)>) -> Output {
+    match options.target {
+        Target::C => code = format!("{}\n{code}", c_backend::RUNTIME),
+        Target::Js => code = format!("{}\n{code}", js_backend::RUNTIME),
+        Target::Rust if options.rustfmt => code = emitter::rustfmt(code),
+        Target::Rust | Target::Wat => (),
+    }
+
+    if options.source_map {
+        let source_map = SourceMap::from_markers(&code);
+        return Output { code, source_map, renamed };
+    }
+
+    let (code, source_map) = sourcemap::strip_markers(&code);
+    return Output { code, source_map, renamed };
+}
+
+/// oxide source to rust, running every stage. `import`s can't be followed
+/// without knowing where the source is, see `compile_file`
+pub fn compile(source: &str, options: &Options) -> Result<Output, Vec<Diagnostic>> {
+    let tokens = tokenise(source).map_err(|error| vec![error])?;
+    let parsed = parse(tokens, source).map_err(|error| vec![error])?;
+    let module = check(&parsed, options)?;
+
+    return Ok(generate(&module, options));
+}
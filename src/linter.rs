@@ -0,0 +1,358 @@
+use crate::{Diagnostic, Options, Token, Warning};
+use crate::ir::{Body, Cond, Expr, Item, Module, Stmt, Var};
+use crate::optimiser;
+use crate::parser::{Parsed, Span};
+use crate::program;
+
+/// a variable that's never read
+const UNUSED_VARIABLE: &str = "unused_variable";
+/// a proc parameter that's never read
+const UNUSED_PARAM: &str = "unused_param";
+/// a proc nothing calls, `main` and `pub` procs are left alone
+const UNUSED_PROC: &str = "unused_proc";
+/// a name declared again while the first one is still in scope
+const SHADOWED: &str = "shadowed";
+/// an `if` or `orif` that always goes the same way. a condition on a
+/// `const` is left alone, that's how code gets switched on and off
+const CONSTANT_CONDITION: &str = "constant_condition";
+/// an `if`, `if let`, `orif` or `else` with nothing in it
+const EMPTY_IF: &str = "empty_if";
+/// code after a `return`
+const UNREACHABLE: &str = "unreachable";
+/// a `# allow(code)` naming a lint that doesn't exist
+const UNKNOWN_LINT: &str = "unknown_lint";
+
+/// a warning before it knows which file it's in
+type Found = (Span, &'static str, String);
+
+/// every lint code, these don't change so `# allow(code)` keeps working
+const CODES: [&str; 8] = [UNUSED_VARIABLE, UNUSED_PARAM, UNUSED_PROC, SHADOWED, CONSTANT_CONDITION, EMPTY_IF, UNREACHABLE, UNKNOWN_LINT];
+
+/// the warnings for the program at `path` and every file it imports,
+/// imported files first. a file that doesn't compile gives its errors
+/// instead
+pub fn lint_file(path: &str, source: &str, options: &Options) -> Result<Vec<Warning>, Vec<Diagnostic>> {
+    // lints are about the code as it's written, not as `-O` leaves it
+    let modules = program::load(path, source, &Options { optimise: false, ..options.clone() })?;
+    let entry = modules.len() - 1;
+    let mut warnings = Vec::new();
+
+    for (index, module) in modules.into_iter().enumerate() {
+        let file = if index == entry { None } else { Some(module.path.clone()) };
+        let (allowed, mut found) = allows(&module.source);
+
+        empty_ifs(&module.parsed, &mut found);
+        let lowered = module.lowered;
+
+        // every proc in an included module is called from the rust around it
+        let mut linter = Linter { found };
+        linter.module(&lowered, options.module && index == entry);
+
+        let procs = proc_lines(&lowered);
+        let mut found: Vec<Found> = linter.found.into_iter().filter(|(span, code, _)| {
+            let on_line = |line: usize| allowed.iter().any(|(allowed_line, allowed_code)| *allowed_line == line && allowed_code == code);
+            let in_proc = procs.iter().find(|(start, end)| *start <= span.line && span.line <= *end);
+            !on_line(span.line) && !in_proc.is_some_and(|(start, _)| on_line(*start))
+        }).collect();
+
+        found.sort_by_key(|(span, _, _)| (span.line, span.column));
+        warnings.extend(found.into_iter().map(|(span, code, message)| Warning { span, code, message, file: file.clone() }));
+    }
+
+    return Ok(warnings);
+}
+
+/// the lints each line allows, from `# allow(code, ...)` comments. a
+/// comment after some code allows them on its own line, a comment on a
+/// line by itself on the line below. a code that isn't a lint is a
+/// warning of its own
+fn allows(source: &str) -> (Vec<(usize, String)>, Vec<Found>) {
+    let mut allowed = Vec::new();
+    let mut unknown = Vec::new();
+    let tokens = crate::tokenise(source).unwrap_or_default();
+    let lines: Vec<&str> = source.lines().collect();
+    let mut line = 1;
+    let mut has_code = false;
+
+    for token in tokens {
+        let text = match token {
+            Token::NewLine(_) => {
+                line += 1;
+                has_code = false;
+                continue;
+            },
+            Token::Comment((_, text)) => text,
+            _ => {
+                has_code = true;
+                continue;
+            },
+        };
+
+        let codes = text.trim_start_matches('#').trim().strip_prefix("allow(").and_then(|rest| rest.strip_suffix(')'));
+        let Some(codes) = codes else {
+            continue;
+        };
+
+        let target = if has_code { line } else { line + 1 };
+        let column = lines.get(line - 1).and_then(|text| text.find('#')).unwrap_or_default() + 1;
+
+        for code in codes.split(',').map(str::trim) {
+            if !CODES.contains(&code) {
+                unknown.push((Span { line, column }, UNKNOWN_LINT, format!("there's no lint called `{code}`")));
+            }
+
+            allowed.push((target, code.to_string()));
+        }
+    }
+
+    return (allowed, unknown);
+}
+
+/// where each proc starts and the last line with a statement in it, a
+/// `# allow(code)` on a proc covers everything inside it
+fn proc_lines(module: &Module) -> Vec<(usize, usize)> {
+    fn last_line(body: &Body) -> usize {
+        return body.iter().map(|(span, stmt)| {
+            stmt.bodies().into_iter().map(last_line).fold(span.line, usize::max)
+        }).max().unwrap_or_default();
+    }
+
+    return module.items.iter().filter_map(|item| match item {
+        Item::Function(function) => Some((function.span.line, last_line(&function.body).max(function.span.line))),
+        _ => None,
+    }).collect();
+}
+
+/// a block that's closed straight after it's opened. the ir can't tell
+/// an `orif` from an `if` inside an `else`, so this goes by the parser's
+/// statements
+fn empty_ifs(parsed: &[(Span, Parsed)], found: &mut Vec<Found>) {
+    for pair in parsed.windows(2) {
+        let [(span, head), (_, Parsed::RSquirly)] = pair else {
+            continue;
+        };
+
+        let keyword = match head {
+            Parsed::If(_) => "if",
+            Parsed::IfLet(_) => "if let",
+            Parsed::OrIf(_) => "orif",
+            Parsed::Else => "else",
+            _ => continue,
+        };
+
+        found.push((*span, EMPTY_IF, format!("this `{keyword}` is empty")));
+    }
+}
+
+struct Linter {
+    found: Vec<Found>,
+}
+
+impl Linter {
+    fn warn(&mut self, span: Span, code: &'static str, message: String) {
+        self.found.push((span, code, message));
+    }
+
+    fn module(&mut self, module: &Module, every_proc_public: bool) {
+        let mut called = Vec::new();
+
+        for item in &module.items {
+            let Item::Function(function) = item else {
+                continue;
+            };
+
+            // a proc calling itself doesn't count
+            called.extend(calls(&function.body).into_iter().filter(|name| *name != function.name));
+
+            let read = reads(&function.body);
+            for param in &function.params {
+                if !read.contains(&param.unique) && !param.name.starts_with('_') {
+                    self.warn(function.span, UNUSED_PARAM, format!("the parameter `{}` is never used", param.name));
+                }
+            }
+
+            let params = function.params.iter().map(|param| (param.name.clone(), function.span)).collect();
+            self.body(&function.body, &mut vec![params]);
+        }
+
+        called.extend(calls(&module.top_level));
+        self.body(&module.top_level, &mut vec![Vec::new()]);
+
+        for item in &module.items {
+            if let Item::Function(function) = item {
+                if !function.public && !every_proc_public && function.name != "main" && !called.contains(&function.name) {
+                    self.warn(function.span, UNUSED_PROC, format!("the proc `{}` is never called", function.name));
+                }
+            }
+        }
+    }
+
+    /// a proc's body or the top level, `scopes` already has the params
+    fn body(&mut self, body: &Body, scopes: &mut Vec<Vec<(String, Span)>>) {
+        let read = reads(body);
+        let mut declared = Vec::new();
+        declarations(body, &mut declared);
+
+        for (span, var) in declared {
+            if !read.contains(&var.unique) && !var.name.starts_with('_') {
+                self.warn(span, UNUSED_VARIABLE, format!("`{}` is never used", var.name));
+            }
+        }
+
+        self.statements(body, scopes);
+    }
+
+    /// shadowing, conditions that never change and code after a `return`
+    fn statements(&mut self, body: &Body, scopes: &mut Vec<Vec<(String, Span)>>) {
+        for (index, (span, stmt)) in body.iter().enumerate() {
+            if index > 0 && returns(&body[index - 1].1) {
+                self.warn(*span, UNREACHABLE, String::from("this never runs, the code before it always returns"));
+            }
+
+            match stmt {
+                Stmt::Let(var, _) => self.declare(*span, var, scopes),
+                Stmt::Unpack(vars, _) => {
+                    for var in vars {
+                        self.declare(*span, var, scopes);
+                    }
+                },
+                _ => (),
+            }
+
+            match stmt {
+                Stmt::If(condition, then, otherwise, _) => {
+                    if let Cond::Bool(condition) = condition {
+                        self.condition(*span, condition);
+                    }
+
+                    scopes.push(Vec::new());
+                    if let Cond::Some(var, _) = condition {
+                        self.declare(*span, var, scopes);
+                    }
+
+                    self.statements(then, scopes);
+                    scopes.pop();
+
+                    scopes.push(Vec::new());
+                    self.statements(otherwise, scopes);
+                    scopes.pop();
+                },
+                Stmt::Loop(inner) | Stmt::Block(inner) => {
+                    scopes.push(Vec::new());
+                    self.statements(inner, scopes);
+                    scopes.pop();
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn declare(&mut self, span: Span, var: &Var, scopes: &mut [Vec<(String, Span)>]) {
+        if var.temp {
+            return;
+        }
+
+        let earlier = scopes.iter().flatten().rev().find(|(name, _)| *name == var.name);
+        if let Some((_, earlier)) = earlier {
+            let message = format!("`{}` shadows the `{}` declared on line {}", var.name, var.name, earlier.line);
+            self.warn(span, SHADOWED, message);
+        }
+
+        if let Some(scope) = scopes.last_mut() {
+            scope.push((var.name.clone(), span));
+        }
+    }
+
+    fn condition(&mut self, span: Span, condition: &Expr) {
+        let mut mentions_const = false;
+        condition.visit(&mut |expr| mentions_const = mentions_const || matches!(expr, Expr::Const(..)));
+
+        if mentions_const {
+            return;
+        }
+
+        if let Expr::Bool(value) = folded(condition) {
+            self.warn(span, CONSTANT_CONDITION, format!("this condition is always {value}"));
+        }
+    }
+}
+
+/// an expression with everything that can be worked out before it runs
+/// worked out, arithmetic that fails is left as it is
+fn folded(expr: &Expr) -> Expr {
+    let mut expr = expr.clone();
+    for child in expr.children_mut() {
+        *child = folded(child);
+    }
+
+    return match optimiser::fold(&expr) {
+        Ok(Some(folded)) => folded,
+        _ => expr,
+    };
+}
+
+/// whether nothing after the statement can run
+fn returns(stmt: &Stmt) -> bool {
+    let body_returns = |body: &Body| body.iter().any(|(_, stmt)| returns(stmt));
+
+    return match stmt {
+        Stmt::Return(_) => true,
+        Stmt::If(_, then, otherwise, _) => !otherwise.is_empty() && body_returns(then) && body_returns(otherwise),
+        Stmt::Block(body) => body_returns(body),
+        _ => false,
+    };
+}
+
+/// every variable the body declares, in blocks inside it too
+fn declarations<'a>(body: &'a Body, found: &mut Vec<(Span, &'a Var)>) {
+    for (span, stmt) in body {
+        match stmt {
+            Stmt::Let(var, _) => found.push((*span, var)),
+            Stmt::Unpack(vars, _) => found.extend(vars.iter().map(|var| (*span, var))),
+            Stmt::If(Cond::Some(var, _), _, _, _) => found.push((*span, var)),
+            _ => (),
+        }
+
+        for inner in stmt.bodies() {
+            declarations(inner, found);
+        }
+    }
+
+    found.retain(|(_, var)| !var.temp);
+}
+
+/// the unique name of every variable the body reads
+fn reads(body: &Body) -> Vec<String> {
+    let mut read = Vec::new();
+    visit(body, &mut |expr| {
+        if let Expr::Local(var) = expr {
+            read.push(var.unique.clone());
+        }
+    });
+
+    return read;
+}
+
+/// every proc the body calls, as it's written
+fn calls(body: &Body) -> Vec<String> {
+    let mut called = Vec::new();
+    visit(body, &mut |expr| {
+        if let Expr::Call(name, _, _) = expr {
+            called.push(name.clone());
+        }
+    });
+
+    return called;
+}
+
+fn visit(body: &Body, visit_expr: &mut impl FnMut(&Expr)) {
+    for (_, stmt) in body {
+        for expr in stmt.exprs() {
+            expr.visit(visit_expr);
+        }
+
+        for inner in stmt.bodies() {
+            visit(inner, visit_expr);
+        }
+    }
+}
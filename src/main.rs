@@ -1,317 +1,318 @@
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::fs;
-use parser::parser;
-use generator::generator;
-
-mod parser;
-mod generator;
-
-// while / for loops, maybe more ints, return values, vectors, mut
-//
-
-#[derive(Debug, Clone)]
-pub enum Token {
-    Plus((String, String)),
-    Minus((String, String)),
-    Multiply((String, String)),
-    Divide((String, String)),
-    LParen((String, String)),
-    RParen((String, String)),
-    LSquare((String, String)),
-    RSquare((String, String)),
-    LSquirly((String, String)),
-    RSquirly((String, String)),
-    SglQuote((String, String)),
-    DblQuote((String, String)),
-    EqualsTo((String, String)),
-    Equality((String, String)),
-    Number((String, i32)),
-    Strings((String, String)),
-    Boolean((String, bool)),
-    LetInt((String, String)),
-    LetString((String, String)),
-    LetBool((String, String)),
-    VarName((String, String)),
-    Semicolon((String, String)),
-    Comma((String, String)),
-    Function((String, String)),
-    FuncName((String, String)),
-    Print((String, String)),
-    Parameters((String, String)),
-    NewLine((String, String)),
-    Comment((String, String)),
-    If((String, String)),
-    OrIf((String, String)),
-    Else((String, String)),
-    Vector((String, Vec<Token>)),
-}
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use oxide::{Diagnostic, Options, SourceMap, Target, Warning, bytecode, formatter, ir, project, runner};
+use cli::{Args, Command, Emit};
 
-impl Token {
-    fn validate_vector(tokens: Vec<Token>) -> Option<Vec<Token>> {
-        let allowed_tokens: Vec<Token> = tokens.clone().into_iter()
-            .filter(|token| {
-                matches!(
-                    token,
-                    Token::Boolean(_) | Token::Strings(_) | Token::Number(_)
-                )
-            })
-            .collect();
-
-        if allowed_tokens.len() == tokens.len() {
-            return Some(allowed_tokens);
-        } else {
-            return None;
-        }
-    }
-}
+mod cli;
+mod repl;
 
-fn handle_ending_value(tokens: &mut Vec<Token>, current_token: &mut String, making_string: &mut i8, making_params: &mut i8, making_comment: &mut bool) {
-    if current_token.is_empty() {
-        return;
-    }
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args = match cli::parse_args(&args) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{}", cli::USAGE);
+            process::exit(cli::EXIT_USAGE);
+        },
+    };
 
-    if *making_comment == true {
-        tokens.push(Token::Comment((String::from("COMMENT"), current_token.to_owned())));
-        *making_comment = false;
-        current_token.clear();
+    if args.command == Command::New {
+        new(&args);
         return;
     }
 
-    if *making_params >= 1 {
-        tokens.push(Token::Parameters((String::from("PARAMETERS"), current_token.to_string()))); 
-        current_token.clear();
+    if args.command == Command::Repl {
+        repl::repl(args.quiet);
         return;
     }
 
-    let mut named = Vec::new();
-    // let finding_names_token = tokens;
+    // diagnostics name the file, or `<stdin>` when the source was piped in
+    let path = if args.input == "-" { String::from("<stdin>") } else { args.input.clone() };
+    let contents = match read_input(&args.input) {
+        Ok(contents) => contents,
+        Err(error) => {
+            eprintln!("error: couldn't read {path}: {error}");
+            process::exit(cli::EXIT_USAGE);
+        },
+    };
+
+    match args.command {
+        Command::Remap => remap(&args, &path),
+        Command::Fmt => fmt(&args, &contents),
+        Command::Interp => interp(&path, &contents),
+        Command::Vm => vm(&path, &contents),
+        _ => compile(&args, &path, contents),
+    }
+}
 
-    for token in tokens.clone() {
-        match token {
-            Token::VarName(name) | Token::FuncName(name) => named.push(name),
-            _ => (),
-        }
+fn read_input(input: &str) -> io::Result<String> {
+    if input == "-" {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        return Ok(contents);
     }
 
-    let new_token_result = current_token.parse::<i32>();
-    match new_token_result {
-        Ok(new_token) => {
-            if *making_params >= 1 {
-                tokens.push(Token::Parameters((String::from("PARAMETERS"), current_token.to_string())));
-            } else {
-                tokens.push(Token::Number((String::from("NUMBER"), new_token)));
+    return fs::read_to_string(input);
+}
+
+/// writes to `-o` if it was given, stdout otherwise
+fn write_output(args: &Args, output: &str) {
+    match &args.output {
+        Some(path) => {
+            if let Err(error) = fs::write(path, output) {
+                eprintln!("error: couldn't write {path}: {error}");
+                process::exit(cli::EXIT_USAGE);
             }
-        },
-        Err(_) => {
-            if current_token == "=" {
-                tokens.push(Token::EqualsTo((String::from("EQUALSTO"), current_token.to_string())))
-            } else if current_token == "==" {
-                tokens.push(Token::Equality((String::from("EQUALITY"), current_token.to_string())))
-            } else if current_token == "int" {
-                tokens.push(Token::LetInt((String::from("LETINT"), current_token.to_string())))
-            } else if current_token == "string" {
-                tokens.push(Token::LetString((String::from("LetString"), current_token.to_string())))
-            } else if current_token == "bool" {
-                tokens.push(Token::LetBool((String::from("LetBool"), current_token.to_string())))
-            } else if current_token == "proc" {
-                tokens.push(Token::Function((String::from("FUNCTION"), current_token.to_string())))
-            } else if current_token == "print" {
-                tokens.push(Token::Print((String::from("PRINT"), current_token.to_string())))
-            } else if current_token == "if" {
-                tokens.push(Token::If((String::from("IF"), current_token.to_string())))
-            } else if current_token == "orif" {
-                tokens.push(Token::OrIf((String::from("ORIF"), current_token.to_string())))
-            } else if current_token == "else" {
-                tokens.push(Token::Else((String::from("ELSE"), current_token.to_string())))
-            } else {
-                match tokens.last().unwrap() {
-                    Token::LetInt(_) | Token::LetString(_) | Token::LetBool(_) => tokens.push(Token::VarName((String::from("VARNAME"), current_token.to_string()))),
-                    Token::Function(_) => tokens.push(Token::FuncName((String::from("FUNCNAME"), current_token.to_string()))),
-                    Token::LParen(_) => {
-                        tokens.push(Token::Parameters((String::from("PARAMETERS"), current_token.to_string())));
-                    },
-                    Token::DblQuote(_) => {
-                        if *making_string == 1 {
-                            tokens.push(Token::Strings((String::from("STRINGS"), current_token.to_string())));
-                        } else if *making_string == 2 {
-                            tokens.push(Token::Strings((String::from("STRINGS"), current_token.to_string())));
-                            *making_string = 0;
-                        }
-                    },
-                    _ => {
-                        if current_token.to_owned() == String::from("true") || current_token.to_owned() == String::from("false") {
-                            match current_token.as_str() {
-                                "true" => tokens.push(Token::Boolean((String::from("BOOLEAN"), true))),
-                                "false" => tokens.push(Token::Boolean((String::from("BOOLEAN"), false))),
-                                _ => panic!("that shouldn't have happened")
-                            }
-
-                            current_token.clear();
-                            return;
-                        }
-
-                        let mut found = false;
-
-                        for (type_class, value) in named {
-                            if current_token.to_owned() == value && type_class == String::from("VARNAME") {
-                                tokens.push(Token::VarName((String::from("VARNAME"), current_token.to_string())));
-                                found = true;
-                            } else if current_token.to_owned() == value && type_class == String::from("FUNCNAME") {
-                                tokens.push(Token::FuncName((String::from("FUNCNAME"), current_token.to_string())));
-                                found = true;
-                            }
-                        }
-
-                        if !found {
-                            panic!("invalid characters: {},  making params: {}, making string: {}, last token: {:?} tokens: {:?}", current_token, making_params, making_string, tokens.last().unwrap(), tokens);
-                        }
-                    },
-                }
+
+            if args.verbose {
+                eprintln!("wrote {path}");
             }
         },
+        None => print!("{output}"),
+    }
+}
+
+/// `rustc gen.rs 2>&1 | oxide remap file.oxi gen.rs` points rustc's errors
+/// and panics back at the oxide source, gen.rs needs to have been
+/// generated with --source-map. the oxide source is read first so it
+/// can't come from stdin here
+fn remap(args: &Args, path: &str) {
+    let rust_path = args.remap.as_deref().unwrap_or_default();
+    let rust = match fs::read_to_string(rust_path) {
+        Ok(rust) => rust,
+        Err(error) => {
+            eprintln!("error: couldn't read {rust_path}: {error}");
+            process::exit(cli::EXIT_USAGE);
+        },
     };
 
-    current_token.clear();
+    let mut diagnostics = String::new();
+    if let Err(error) = io::stdin().read_to_string(&mut diagnostics) {
+        eprintln!("error: couldn't read diagnostics from stdin: {error}");
+        process::exit(cli::EXIT_USAGE);
+    }
+
+    write_output(args, &SourceMap::from_markers(&rust).rewrite(&diagnostics, rust_path, path));
 }
 
-fn tokeniser(content: String) -> Vec<Token> {
-    let mut tokens = Vec::new();
-    let mut current_token = String::new();
-    let mut making_string: i8 = 0;
-    let mut making_params: i8 = 0;
-    let mut making_square_params: i8 = 0;
-    let mut making_comment: bool = false;
-
-    // fix single quotes
-    for c in content.chars() {
-        if making_comment {
-            match c {
-                '\n' => {
-                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-                    tokens.push(Token::NewLine((String::from("NEWLINE"), String::from(c))));
-                },
-                _ => current_token.push(c),
-            }
-        } else if c.is_digit(10) || c.is_alphabetic() || (making_string > 0 && (c != '"' || c == ' ')) || (making_params >= 1 && c != ')' && c != '(') || (making_params >= 1 && c == ' ') {
-            current_token.push(c);
-        } else if c == '+' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::Plus((String::from("PLUS"), String::from(c))));
-        } else if c == '-' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::Minus((String::from("MINUS"), String::from(c))));
-        } else if c == '*' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::Multiply((String::from("MULTIPLY"), String::from(c))));
-        } else if c == '/' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::Divide((String::from("DIVIDE"), String::from(c))));
-        } else if c == '"' {
-            making_string += 1;
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::DblQuote((String::from("DBLQUOTE"), String::from(c))));
-        } else if c == '\'' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::SglQuote((String::from("SGLQUOTE"), String::from(c))));
-        } else if c == '(' {
-            match making_params {
-                0 => {
-                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-                    making_params += 1;
-                    tokens.push(Token::LParen((String::from("LPAREN"), String::from(c))));
-                },
-                _ => {
-                    making_params += 1;
-                    current_token.push(c);
+fn new(args: &Args) {
+    match project::new_project(Path::new(&args.input)) {
+        Ok(written) => {
+            if !args.quiet {
+                for path in written {
+                    println!("created {}", path.display());
                 }
-
             }
-        } else if c == ')' {
-            match making_params {
-                0 => {
-                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-                    tokens.push(Token::RParen((String::from("RPAREN"), String::from(c))));
-                },
-                1 => {
-                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-                    making_params -= 1;
-                    tokens.push(Token::RParen((String::from("RPAREN"), String::from(c))));
-                },
-                _ => {
-                    current_token.push(c);
-                    making_params -= 1;
-                },
-                
+        },
+        Err(error) => {
+            eprintln!("error: couldn't create {}: {error}", args.input);
+            process::exit(cli::EXIT_USAGE);
+        },
+    }
+}
+
+/// formats in place, or to `-o`, or to stdout when reading from stdin
+fn fmt(args: &Args, contents: &str) {
+    let formatted = formatter::format(contents);
+
+    if args.output.is_none() && args.input != "-" {
+        if formatted != contents {
+            if let Err(error) = fs::write(&args.input, &formatted) {
+                eprintln!("error: couldn't write {}: {error}", args.input);
+                process::exit(cli::EXIT_USAGE);
             }
-        } else if c == '{' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::LSquirly((String::from("LSQUIRLY"), String::from(c))));
-        } else if c == '}' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::RSquirly((String::from("RSQUIRLY"), String::from(c))));
-        } else if c == '[' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::LSquare((String::from("LSQUARE"), String::from(c))));
-        } else if c == ']' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::RSquare((String::from("RSQUARE"), String::from(c))));
-        } else if c == ',' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::Comma((String::from("COMMA"), String::from(c))));
-        } else if c == '\n' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::NewLine((String::from("NEWLINE"), String::from(c))));
-        } else if c == ' ' {
-            match making_params {
-                0 => {
-                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-                },
-                _ => current_token.push(c),
+
+            if !args.quiet {
+                println!("formatted {}", args.input);
             }
-            // tokens.push(Token::WhiteSpace((String::from("WHITESPACE"), String::from(c))));
-        } else if c == ';' {
-            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment);
-            tokens.push(Token::Semicolon((String::from("SEMICOLON"), String::from(c))))
-        } else if c == '=' {
-            current_token.push(c);
-        } else if c == '#' {
-            current_token.push(c);
-            making_comment = true;
-        } else {
-            current_token.push(c);
         }
+
+        return;
     }
 
-    println!("buffer: {}, making params: {}, making strings: {}", current_token, making_params, making_string);
-    return tokens;
+    write_output(args, &formatted);
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// runs the program without generating any rust, exiting with what the
+/// compiled program would have exited with
+fn interp(path: &str, contents: &str) {
+    match oxide::interpret_file(path, contents, &mut io::stdout(), &mut io::stderr()) {
+        Ok(code) => process::exit(code),
+        Err(errors) => fail(path, errors),
+    }
+}
 
-    let contents_result = fs::read_to_string(args[1].clone());
-    let contents = match contents_result {
-        Ok(contents) => contents,
-        Err(_) => String::from("couldn't read"),
+fn fail(path: &str, errors: Vec<Diagnostic>) -> ! {
+    for error in &errors {
+        eprintln!("{}", error.render(path));
+    }
+
+    eprintln!("{} error{} found", errors.len(), if errors.len() == 1 { "" } else { "s" });
+    process::exit(cli::EXIT_ERRORS);
+}
+
+/// prints the warnings, `lint` counts them. `--deny-warnings` turns any
+/// warning into a failure
+fn warn(args: &Args, path: &str, warnings: &[Warning]) {
+    if !args.quiet || args.deny_warnings {
+        for warning in warnings {
+            eprintln!("{}", warning.render(path));
+        }
+    }
+
+    if args.command == Command::Lint && !args.quiet {
+        match warnings.len() {
+            0 => println!("{path}: no warnings"),
+            count => eprintln!("{count} warning{} found", if count == 1 { "" } else { "s" }),
+        }
+    }
+
+    if args.deny_warnings && !warnings.is_empty() {
+        process::exit(cli::EXIT_ERRORS);
+    }
+}
+
+/// like `interp` but through the bytecode vm
+fn vm(path: &str, contents: &str) {
+    match bytecode::compile_file(path, contents) {
+        Ok(program) => process::exit(oxide::vm::run(&program, path, &mut io::stdout(), &mut io::stderr())),
+        Err(errors) => fail(path, errors),
+    }
+}
+
+fn compile(args: &Args, path: &str, contents: String) {
+    let fail = |errors: Vec<Diagnostic>| -> ! { fail(path, errors) };
+
+    let tokenised = oxide::tokenise(&contents).unwrap_or_else(|error| fail(vec![error]));
+
+    if args.verbose {
+        for token in &tokenised {
+            eprintln!("{:?}", token);
+        }
+    }
+
+    if args.command == Command::Emit && args.emit == Emit::Tokens {
+        let tokens: Vec<String> = tokenised.iter().map(|token| format!("{:?}\n", token)).collect();
+        write_output(args, &tokens.concat());
+        return;
+    }
+
+    let parsed = oxide::parse(tokenised, &contents).unwrap_or_else(|error| fail(vec![error]));
+
+    if args.verbose {
+        for parse in &parsed {
+            eprintln!("{:?}", parse);
+        }
+    }
+
+    if args.command == Command::Emit && args.emit == Emit::Ast {
+        let ast: Vec<String> = parsed.iter().map(|parse| format!("{:?}\n", parse)).collect();
+        write_output(args, &ast.concat());
+        return;
+    }
+
+    if args.command == Command::Emit && args.emit == Emit::Bytecode {
+        let program = bytecode::compile_file(path, &contents).unwrap_or_else(|errors| fail(errors));
+        write_output(args, &bytecode::disassemble(&program));
+        return;
+    }
+
+    let options = Options {
+        ordered_maps: args.ordered_maps,
+        rustfmt: args.rustfmt,
+        source_map: args.source_map && args.command == Command::Emit,
+        module: false,
+        target: args.target,
+        optimise: args.optimise,
     };
 
-    let tokenised = tokeniser(contents);
+    if args.command == Command::Emit && args.emit == Emit::Ir {
+        let modules = ir::lower_file(path, &contents, &options).unwrap_or_else(|errors| fail(errors));
+        write_output(args, &ir::print(&modules));
+        return;
+    }
+
+    if args.command == Command::Lint {
+        let warnings = oxide::lint_file(path, &contents, &options).unwrap_or_else(|errors| fail(errors));
+        warn(args, path, &warnings);
+        return;
+    }
+
+    // imported files are loaded relative to this one, or to the current directory for stdin
+    let output = oxide::compile_file(path, &contents, &options).unwrap_or_else(|errors| fail(errors));
+
+    if args.command == Command::Check {
+        let warnings = oxide::lint_file(path, &contents, &options).unwrap_or_else(|errors| fail(errors));
+        warn(args, path, &warnings);
+
+        if !args.quiet {
+            println!("{path}: ok");
+        }
+
+        return;
+    }
+
+    if args.verbose {
+        for (oxide, rust) in &output.renamed {
+            eprintln!("renamed {oxide} to {rust}");
+        }
+    }
+
+    if args.command == Command::Emit && args.emit == Emit::Cargo {
+        if args.target != Target::Rust {
+            eprintln!("error: --emit=cargo only works with --target=rust");
+            process::exit(cli::EXIT_USAGE);
+        }
+
+        // the project goes next to the source, named after it, unless `-o` says where
+        let dir = match &args.output {
+            Some(dir) => PathBuf::from(dir),
+            None => Path::new(path).parent().unwrap_or(Path::new("")).join(project::crate_name(path)),
+        };
+
+        match project::emit_project(&dir, &output.code, path, &contents) {
+            Ok(written) => {
+                if !args.quiet {
+                    for path in written {
+                        println!("wrote {}", path.display());
+                    }
+                }
+            },
+            Err(error) => {
+                eprintln!("error: couldn't write the project to {}: {error}", dir.display());
+                process::exit(cli::EXIT_USAGE);
+            },
+        }
 
-    for token in &tokenised {
-        println!("{:?}", token);
+        return;
     }
 
-    let parsed = parser(tokenised);
+    if args.command == Command::Emit {
+        write_output(args, &output.code);
+        return;
+    }
 
-    println!("\n");
-    for parse in &parsed {
-        println!("{:?}", parse);
+    if args.command == Command::Run && args.target == Target::Wat {
+        eprintln!("error: a wat module can't run by itself, `build` it and load it into a webassembly host");
+        process::exit(cli::EXIT_USAGE);
     }
 
-    let generated = generator(parsed);
+    // build and run go through rustc, or a c compiler
+    let built = match runner::build(&output.code, &output.source_map, path, args.output.as_deref(), args.target, &mut io::stderr()) {
+        Some(built) => built,
+        None => process::exit(cli::EXIT_ERRORS),
+    };
+
+    if args.command == Command::Run {
+        process::exit(runner::run(&built, &args.program_args, &output.source_map, path, &mut io::stderr()));
+    }
 
-    match fs::write("./gen.rs", generated) {
-        Ok(_) => println!("produced gen.rs"),
-        Err(_) => panic!("error writing to file"),
+    if !args.quiet {
+        println!("built {}", built.binary.display());
     }
 }
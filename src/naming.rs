@@ -0,0 +1,56 @@
+use std::path::Path;
+
+/// the name a file is imported under, `import "utils/math-2.oxi";` is
+/// called as `math_2`. it's also a name rustc and cargo take for a crate
+pub(crate) fn module_name(path: &str) -> String {
+    let name: String = Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+
+    return match name.trim_matches('_') {
+        "" => String::from("main"),
+        name => name.to_string(),
+    };
+}
+
+/// rust keywords that are fine as oxide names, these become raw
+/// identifiers. `gen` is only reserved from the 2024 edition on
+const KEYWORDS: [&str; 48] = [
+    "as", "break", "const", "continue", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final",
+    "macro", "override", "priv", "typeof", "unsized", "virtual", "yield", "try", "gen",
+];
+
+/// names that can't be raw identifiers, or that rust would read as a
+/// pattern instead of a binding, these get an `ox_` prefix instead. so do
+/// names that already start with `ox_`, so nothing clashes with them
+const MANGLED: [&str; 8] = ["self", "Self", "super", "crate", "Some", "None", "Ok", "Err"];
+
+/// the name an oxide variable or proc goes by in the generated rust
+pub(crate) fn rust_identifier(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        return format!("r#{name}");
+    }
+
+    if MANGLED.contains(&name) || name.starts_with("ox_") {
+        return format!("ox_{name}");
+    }
+
+    return name.to_string();
+}
+
+/// the oxide name behind a generated identifier, the reverse of `rust_identifier`
+pub(crate) fn oxide_identifier(name: &str) -> Option<&str> {
+    if let Some(name) = name.strip_prefix("r#") {
+        return KEYWORDS.contains(&name).then_some(name);
+    }
+
+    let name = name.strip_prefix("ox_")?;
+    return (MANGLED.contains(&name) || name.starts_with("ox_")).then_some(name);
+}
@@ -0,0 +1,191 @@
+use crate::expression::BinaryOp;
+use crate::ir::{Body, Cond, Expr, Item, Module, Stmt, show};
+use crate::parser::Span;
+
+/// folds constant expressions and drops code that can never run, what
+/// `-O` does before the backends see the ir. gives back the expressions
+/// that are certain to fail when they run, dividing by zero or
+/// overflowing, as errors
+pub(crate) fn optimise(module: &mut Module) -> Vec<(Span, String)> {
+    let mut optimiser = Optimiser { constants: Vec::new(), errors: Vec::new() };
+
+    for item in &mut module.items {
+        if let Item::Function(function) = item {
+            optimiser.constants.clear();
+            optimiser.body(&mut function.body);
+        }
+    }
+
+    optimiser.constants.clear();
+    optimiser.body(&mut module.top_level);
+
+    return optimiser.errors;
+}
+
+struct Optimiser {
+    /// the `int`s and `bool`s declared with a constant and never changed,
+    /// by unique name
+    constants: Vec<(String, Expr)>,
+    errors: Vec<(Span, String)>,
+}
+
+impl Optimiser {
+    fn body(&mut self, body: &mut Body) {
+        let mut optimised = Vec::new();
+
+        for (span, stmt) in std::mem::take(body) {
+            self.statement(span, stmt, &mut optimised);
+
+            // nothing after a `return` or a `break` can run
+            if optimised.last().is_some_and(|(_, stmt)| diverges(stmt)) {
+                break;
+            }
+        }
+
+        *body = optimised;
+    }
+
+    fn statement(&mut self, span: Span, mut stmt: Stmt, out: &mut Body) {
+        match &mut stmt {
+            Stmt::Let(_, value) | Stmt::Unpack(_, value) | Stmt::Assign(_, value) | Stmt::Expr(value) | Stmt::Return(Some(value)) => {
+                self.expr(span, value);
+            },
+            Stmt::Print(_, args) => {
+                for arg in args {
+                    self.expr(span, arg);
+                }
+            },
+            Stmt::If(Cond::Bool(condition), then, otherwise, _) => {
+                self.expr(span, condition);
+
+                // only the branch that's taken is left, an `orif` after a
+                // false condition is the `if` now
+                match literal(condition) {
+                    Expr::Bool(true) => return self.inline(span, std::mem::take(then), out),
+                    Expr::Bool(false) => return self.inline(span, std::mem::take(otherwise), out),
+                    _ => (),
+                }
+
+                self.body(then);
+                self.body(otherwise);
+            },
+            Stmt::If(Cond::Some(_, value), then, otherwise, _) => {
+                self.expr(span, value);
+                self.body(then);
+                self.body(otherwise);
+            },
+            Stmt::Loop(body) => self.body(body),
+            Stmt::Block(body) => return self.inline(span, std::mem::take(body), out),
+            Stmt::Return(None) | Stmt::Break => (),
+        }
+
+        // every use of a constant is replaced with its value, so it doesn't need declaring
+        if let Stmt::Let(var, value @ (Expr::Int(_) | Expr::Bool(_))) = &stmt {
+            if !var.mutable {
+                self.constants.push((var.unique.clone(), value.clone()));
+                return;
+            }
+        }
+
+        out.push((span, stmt));
+    }
+
+    /// a block that always runs. its statements go straight into the
+    /// body around it unless it declares something that has to stay in
+    /// a scope of its own
+    fn inline(&mut self, span: Span, mut body: Body, out: &mut Body) {
+        self.body(&mut body);
+
+        if body.iter().any(|(_, stmt)| matches!(stmt, Stmt::Let(..) | Stmt::Unpack(..))) {
+            out.push((span, Stmt::Block(body)));
+        } else {
+            out.extend(body);
+        }
+    }
+
+    /// folds the inside of an expression first, then the expression
+    fn expr(&mut self, span: Span, expr: &mut Expr) {
+        for child in expr.children_mut() {
+            self.expr(span, child);
+        }
+
+        match fold(expr) {
+            Ok(Some(folded)) => *expr = folded,
+            Ok(None) => (),
+            Err(message) => self.errors.push((span, format!("`{}` always fails: {message}", show(expr)))),
+        }
+
+        if let Expr::Local(var) = expr {
+            if let Some((_, value)) = self.constants.iter().find(|(unique, _)| *unique == var.unique) {
+                *expr = value.clone();
+            }
+        }
+    }
+}
+
+/// what an expression with constant operands works out to, `None` when
+/// it can't be worked out before it runs. arithmetic fails the way it
+/// would when it runs
+pub(crate) fn fold(expr: &Expr) -> Result<Option<Expr>, String> {
+    let folded = match expr {
+        Expr::Neg(inner) => match literal(inner) {
+            Expr::Int(value) => Expr::Int(value.checked_neg().ok_or("attempt to negate with overflow")?),
+            _ => return Ok(None),
+        },
+        Expr::Not(inner) => match literal(inner) {
+            Expr::Bool(value) => Expr::Bool(!value),
+            Expr::Int(value) => Expr::Int(!value),
+            _ => return Ok(None),
+        },
+        // the checker has made sure the types line up, so the only way
+        // for this to fail is the arithmetic
+        Expr::Binary(op, left, right) => match (literal(left), literal(right)) {
+            (Expr::Int(left), Expr::Int(right)) => match op.arithmetic(*left, *right) {
+                Some(result) => Expr::Int(result?),
+                None => Expr::Bool(op.compare(left.cmp(right)).unwrap_or_default()),
+            },
+            (Expr::Str(left), Expr::Str(right)) if *op == BinaryOp::Add => Expr::Str(format!("{left}{right}")),
+            (Expr::Str(left), Expr::Str(right)) => match op.compare(left.cmp(right)) {
+                Some(result) => Expr::Bool(result),
+                None => return Ok(None),
+            },
+            (Expr::Bool(left), Expr::Bool(right)) => match op.compare(left.cmp(right)) {
+                Some(result) => Expr::Bool(result),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        },
+        // the right side only goes when the left one doesn't decide it
+        Expr::And(left, right) => match (literal(left), literal(right)) {
+            (Expr::Bool(false), _) => Expr::Bool(false),
+            (Expr::Bool(true), other) | (other, Expr::Bool(true)) => other.clone(),
+            _ => return Ok(None),
+        },
+        Expr::Or(left, right) => match (literal(left), literal(right)) {
+            (Expr::Bool(true), _) => Expr::Bool(true),
+            (Expr::Bool(false), other) | (other, Expr::Bool(false)) => other.clone(),
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    return Ok(Some(folded));
+}
+
+/// a `const`'s literal, anything else as it is. a `const` on its own
+/// keeps its name, it's only folded into something bigger
+fn literal(expr: &Expr) -> &Expr {
+    return match expr {
+        Expr::Const(_, value) => value,
+        _ => expr,
+    };
+}
+
+/// whether nothing after the statement in the same body can run
+fn diverges(stmt: &Stmt) -> bool {
+    return match stmt {
+        Stmt::Return(_) | Stmt::Break => true,
+        Stmt::Block(body) => body.last().is_some_and(|(_, stmt)| diverges(stmt)),
+        _ => false,
+    };
+}
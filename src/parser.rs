@@ -1,80 +1,271 @@
-use crate::Token;
+use crate::{Diagnostic, Token};
+use crate::naming::{self, rust_identifier};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Parsed {
     VariableDeclare(VariableDeclare),
+    Destructure(Destructure),
     FunctionDeclare(FunctionDeclare),
     FunctionCall(FunctionCall),
     Print(String),
-    RSquirly(String),
+    RSquirly,
+    Return(String),
     If(String),
+    IfLet(IfLet),
+    For(ForLoop),
     OrIf(String),
     Else,
+    Import(Import),
+    /// `const int MAX = 100;`, only outside of any proc
+    Const(VariableDeclare),
 }
 
-#[derive(Debug)]
+impl Parsed {
+    /// the variables the statement declares, a proc's are its params
+    pub fn names(&self) -> Vec<String> {
+        return match self {
+            Parsed::VariableDeclare(declare) => vec![declare.name.clone()],
+            Parsed::Destructure(destructure) => destructure.names.clone(),
+            Parsed::FunctionDeclare(declare) => declare.params().into_iter().map(|(name, _)| name).collect(),
+            Parsed::IfLet(if_let) => vec![if_let.binding.clone()],
+            Parsed::For(for_loop) => for_loop.bindings.split(',').map(|name| name.trim().to_string()).collect(),
+            _ => Vec::new(),
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct VariableDeclare {
     pub type_class: String,
     pub name: String,
     pub value: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct Destructure {
+    pub names: Vec<String>,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
 pub struct FunctionDeclare {
     pub name: String,
     pub parameters: String, 
+    pub return_type: String,
+    /// `pub proc`, callable from files that import this one
+    pub public: bool,
+}
+
+/// `import "path/to/utils.oxi";`, the path is relative to the importing file
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: String,
+    /// what the file's procs are called through, `utils.double(2)`
+    pub name: String,
 }
 
 impl FunctionDeclare {
-    pub fn sanitise_params(&self, content_change: Vec<(String, String)>) -> String {
-        let seperated: Vec<_> = self.parameters.split(',').collect();
+    /// splits `int x, opt<string> y` into `(name, rust type)` pairs
+    pub fn params(&self) -> Vec<(String, String)> {
+        let mut params = Vec::new();
+
+        for elem in split_top_level(&self.parameters) {
+            if let Some((type_class, name)) = elem.trim().rsplit_once(' ') {
+                params.push((name.trim().to_string(), rust_type(type_class)));
+            }
+        }
+
+        return params;
+    }
+
+    pub fn sanitise_params(&self) -> String {
         let mut re_params = String::new();
 
-        for elem in seperated {
-            for (from, to) in &content_change {
-                if elem.trim().contains(*&from) {
-                    let new: Vec<&str> = elem.trim().split(*&from).collect();
-                    let new_param = if re_params.is_empty() {
-                        format!("{}: {}", new[1], to)
-                    } else {
-                        format!(", {}: {}", new[1], to)
-                    };
+        for (name, type_class) in self.params() {
+            let name = rust_identifier(&name);
+            let new_param = if re_params.is_empty() {
+                format!("{}: {}", name, type_class)
+            } else {
+                format!(", {}: {}", name, type_class)
+            };
 
-                    re_params.push_str(&new_param);
-                }
-            }
+            re_params.push_str(&new_param);
         }
 
         return re_params;
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FunctionCall {
     pub name: String,
     pub parameters: String, 
+    pub propagate: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfLet {
+    pub binding: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForLoop {
+    pub bindings: String,
+    pub iterable: String,
 }
 
-pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
+/// maps an oxide type to the rust type the generator emits
+pub fn rust_type(type_class: &str) -> String {
+    let type_class = type_class.trim();
+
+    if let Some(inner) = type_class.strip_prefix("opt<").and_then(|t| t.strip_suffix('>')) {
+        return format!("Option<{}>", rust_type(inner));
+    }
+
+    if let Some(inner) = type_class.strip_prefix("result<").and_then(|t| t.strip_suffix('>')) {
+        let types: Vec<String> = split_top_level(inner).iter().map(|t| rust_type(t)).collect();
+        return format!("Result<{}>", types.join(", "));
+    }
+
+    if let Some(inner) = type_class.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        let types: Vec<String> = split_top_level(inner).iter().map(|t| rust_type(t)).collect();
+        return format!("({})", types.join(", "));
+    }
+
+    if let Some(inner) = type_class.strip_prefix("map<").and_then(|t| t.strip_suffix('>')) {
+        let types: Vec<String> = split_top_level(inner).iter().map(|t| rust_type(t)).collect();
+        return format!("HashMap<{}>", types.join(", "));
+    }
+
+    match type_class {
+        "int" => String::from("i32"),
+        "string" => String::from("String"),
+        "bool" => String::from("bool"),
+        other => other.to_string(),
+    }
+}
+
+/// splits on commas that aren't nested inside `<>`, `()` or quotes
+pub fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut inside_quotes = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => inside_quotes = !inside_quotes,
+            '<' | '(' | '[' | '{' if !inside_quotes => depth += 1,
+            '>' | ')' | ']' | '}' if !inside_quotes => depth -= 1,
+            ',' if !inside_quotes && depth == 0 => {
+                parts.push(current.clone());
+                current.clear();
+                continue;
+            },
+            _ => (),
+        }
+
+        current.push(c);
+    }
+
+    if !current.trim().is_empty() {
+        parts.push(current);
+    }
+
+    return parts;
+}
+
+/// spaces out binary operators, leaving a leading `-` alone
+fn push_operator(value: &mut String, operator: &str) {
+    if value.is_empty() || value.ends_with('(') || value.ends_with(' ') {
+        value.push_str(operator);
+    } else {
+        value.push_str(&format!(" {operator} "));
+    }
+}
+
+/// turns the tokens of a value back into oxide source, e.g. `some(5)`
+fn render_value(tokens: &[Token]) -> String {
+    let mut value = String::new();
+
+    for token in tokens {
+        match token {
+            Token::VarName((_, text)) | Token::FuncName((_, text)) |
+            Token::Parameters((_, text)) | Token::LParen((_, text)) |
+            Token::RParen((_, text)) | Token::Some((_, text)) |
+            Token::None((_, text)) | Token::Ok((_, text)) |
+            Token::Fail((_, text)) | Token::Question((_, text)) |
+            Token::LSquirly((_, text)) | Token::RSquirly((_, text)) |
+            Token::Colon((_, text)) | Token::Comma((_, text)) => value.push_str(text),
+            Token::Equality((_, text)) | Token::Plus((_, text)) |
+            Token::Minus((_, text)) | Token::Multiply((_, text)) |
+            Token::Divide((_, text)) => push_operator(&mut value, text),
+            Token::Number((_, num)) => value.push_str(&num.to_string()),
+            Token::Boolean((_, v)) => value.push_str(&v.to_string()),
+            Token::Strings((_, text)) => value.push_str(&format!("\"{text}\"")),
+            _ => (),
+        }
+    }
+
+    return value;
+}
+
+/// where a statement starts in the oxide source, both 1-based
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+fn syntax(span: Span, message: &str) -> Diagnostic {
+    return Diagnostic { span: Some(span), message: message.to_string(), file: None };
+}
+
+/// `source` is only used to find the column each statement starts at
+pub fn parser(tokens: Vec<Token>, source: &str) -> Result<Vec<(Span, Parsed)>, Diagnostic> {
     let mut lines = Vec::new();
     let mut starting_point: usize = 0;
-    let mut parsed_lines: Vec<Parsed> = Vec::new();
+    let mut parsed_lines: Vec<(Span, Parsed)> = Vec::new();
+    let source_lines: Vec<&str> = source.lines().collect();
+
+    // the line each statement starts on, a map literal can go over several
+    let mut line_number = 0;
+    let mut newlines = 0;
+    let mut depth = 0;
 
     for (index, token) in tokens.iter().enumerate() {
+        // a block's `{` follows its head, a map's follows `=`
+        let after_equals = index > 0 && matches!(tokens[index - 1], Token::EqualsTo(_));
+
         match token {
+            Token::LSquirly(_) if after_equals || depth > 0 => depth += 1,
+            Token::RSquirly(_) if depth > 0 => depth -= 1,
+            Token::NewLine(_) if depth > 0 => newlines += 1,
             Token::NewLine(_) => {
-                lines.push(&tokens[starting_point..index]);
+                lines.push((line_number, &tokens[starting_point..index]));
                 starting_point = index;
+                newlines += 1;
+                line_number = newlines;
             },
             _ => (),
         }
     }
 
+    if depth > 0 {
+        let indent = source_lines.get(line_number).map(|text| text.len() - text.trim_start().len()).unwrap_or_default();
+        return Err(syntax(Span { line: line_number + 1, column: indent + 1 }, "this value's `{` is never closed with a `}`"));
+    }
+
     if starting_point != tokens.len() {
-        lines.push(&tokens[starting_point..tokens.len()]);
+        lines.push((line_number, &tokens[starting_point..tokens.len()]));
     }
 
-    for line in lines {
+    for (index, line) in lines.into_iter() {
+        let indent = source_lines.get(index).map(|text| text.len() - text.trim_start().len()).unwrap_or_default();
+        let span = Span { line: index + 1, column: indent + 1 };
+        let declared = parsed_lines.len();
+
         let mut i = 0;
         while i < line.len() {
             match &line[i] {
@@ -87,7 +278,7 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                 Token::SglQuote(_) => (),
                 Token::DblQuote(_) => (),
                 Token::LSquirly(_) => (),
-                Token::RSquirly(_) => parsed_lines.push(Parsed::RSquirly(String::from("}"))),
+                Token::RSquirly(_) => parsed_lines.push((span, Parsed::RSquirly)),
                 Token::EqualsTo(_) => (),
                 Token::Equality(_) => (),
                 Token::Number(_) => (),
@@ -113,17 +304,17 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                             },
                             Token::EqualsTo(_) => is_assigned = true,
                             Token::Semicolon(_) => is_semicoloned = true,
-                            Token::Number((_, num)) => {
-                                if is_assigned && !is_semicoloned {
-                                    value.push_str(&num.to_string());
-                                }
-                            }
+                            Token::Number((_, num)) if is_assigned && !is_semicoloned => {
+                                value.push_str(&num.to_string());
+                            },
                             Token::Divide((_, expr)) | Token::Minus((_, expr)) |
-                            Token::Plus((_, expr)) | Token::Multiply((_, expr)) |
-                            Token::LParen((_, expr)) | Token::RParen((_, expr)) => {
-                                if is_assigned && !is_semicoloned {
-                                    value.push_str(&expr.to_string());
-                                }
+                            Token::Plus((_, expr)) | Token::Multiply((_, expr)) if is_assigned && !is_semicoloned => {
+                                push_operator(&mut value, expr);
+                            },
+                            Token::LParen((_, expr)) | Token::RParen((_, expr)) |
+                            Token::FuncName((_, expr)) | Token::Parameters((_, expr)) |
+                            Token::Question((_, expr)) if is_assigned && !is_semicoloned => {
+                                value.push_str(&expr.to_string());
                             },
                             _ => (),
                         }
@@ -132,99 +323,99 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                     }
 
                     if is_assigned && is_semicoloned {
-                        parsed_lines.push(Parsed::VariableDeclare(VariableDeclare {
+                        parsed_lines.push((span, Parsed::VariableDeclare(VariableDeclare {
                             type_class: String::from("i32"),
                             name,
                             value,
-                        }));
+                        })));
                         i = j;
                     } else {
-                        panic!("invalid syntax")
+                        return Err(syntax(span, "an `int` needs a value and a `;`, like `int x = 1;`"));
                     }
                 },
-                Token::LetString(_) => {
+                Token::LetString(_) | Token::LetBool(_) => {
+                    let (type_class, example) = match &line[i] {
+                        Token::LetString(_) => ("String", "a `string` needs a value, like `string name = \"amy\";`"),
+                        _ => ("bool", "a `bool` needs a value, like `bool done = false;`"),
+                    };
+
                     let mut name = String::new();
-                    let mut found_first_name = false;
-                    let mut value = String::new();
-                    let mut is_assigned = false;
+                    let mut value_start = None;
+                    let mut value_end = line.len();
                     let mut j = i + 1;
 
                     while j < line.len() {
                         match &line[j] {
-                            Token::VarName((_, var_name)) => {
-                                if !found_first_name {
-                                    name = var_name.to_owned();
-                                    found_first_name = true;
-                                } else {
-                                    value.push_str(var_name);
-                                }
-                            },
-                            Token::EqualsTo(_) => is_assigned = true,
-                            Token::Strings((_, v)) => value = v.to_owned(),
+                            Token::VarName((_, var_name)) if name.is_empty() => name = var_name.to_owned(),
+                            Token::EqualsTo(_) if value_start.is_none() => value_start = Some(j + 1),
+                            Token::Semicolon(_) => value_end = j,
                             _ => (),
                         }
 
                         j += 1;
                     }
 
-                    if is_assigned {
-                        parsed_lines.push(Parsed::VariableDeclare(VariableDeclare {
-                            type_class: String::from("String"),
-                            name,
-                            value,
-                        }));
-                        
-                        i = j;
-                    } else {
-                        panic!("invalid syntax");
+                    match value_start {
+                        Some(start) if start < value_end => {
+                            parsed_lines.push((span, Parsed::VariableDeclare(VariableDeclare {
+                                type_class: String::from(type_class),
+                                name,
+                                value: render_value(&line[start..value_end]),
+                            })));
+
+                            i = j;
+                        },
+                        _ => return Err(syntax(span, example)),
                     }
                 },
-                Token::LetBool(_) => {
+                Token::LetOpt((_, type_class)) | Token::LetResult((_, type_class)) | Token::LetMap((_, type_class)) | Token::LetTuple((_, type_class)) => {
                     let mut name = String::new();
-                    let mut found_first_name = false;
-                    let mut value = String::new();
-                    let mut is_assigned = false;
+                    let mut value_start = None;
+                    let mut value_end = line.len();
                     let mut j = i + 1;
 
                     while j < line.len() {
                         match &line[j] {
-                            Token::VarName((_, var_name)) => {
-                                if !found_first_name {
-                                    name = var_name.to_owned();
-                                    found_first_name = true;
-                                } else {
-                                    value.push_str(var_name);
-                                }
-                            },
-                            Token::EqualsTo(_) => is_assigned = true,
-                            Token::Equality(_) => {
-                                if is_assigned {
-                                    value.push_str("==");
-                                }
-                            },
-                            Token::Boolean((_, v)) => {
-                                match v {
-                                    true => value = String::from("true"),
-                                    false => value = String::from("false"),
-                                }
-                            },
+                            Token::VarName((_, var_name)) if name.is_empty() => name = var_name.to_owned(),
+                            Token::EqualsTo(_) if value_start.is_none() => value_start = Some(j + 1),
+                            Token::Semicolon(_) => value_end = j,
                             _ => (),
                         }
 
                         j += 1;
                     }
 
-                    if is_assigned {
-                        parsed_lines.push(Parsed::VariableDeclare(VariableDeclare {
-                            type_class: String::from("bool"),
-                            name,
-                            value
-                        }));
+                    match value_start {
+                        Some(start) if start < value_end => {
+                            parsed_lines.push((span, Parsed::VariableDeclare(VariableDeclare {
+                                type_class: rust_type(type_class),
+                                name,
+                                value: render_value(&line[start..value_end]),
+                            })));
+
+                            i = j;
+                        },
+                        _ => return Err(syntax(span, &format!("a `{type_class}` needs a name and a value, like `{type_class} x = ...;`"))),
+                    }
+                },
+                Token::VarName((_, name)) if name.contains('.') && matches!(line.get(i + 1), Some(Token::LParen(_))) => {
+                    let mut parameters = String::new();
+                    let mut j = i + 1;
 
-                        i = j
-                    } else {
-                        panic!("invalid syntax");
+                    while j < line.len() {
+                        if let Token::Parameters((_, value)) = &line[j] {
+                            parameters.push_str(value);
+                        }
+
+                        j += 1;
                     }
+
+                    parsed_lines.push((span, Parsed::FunctionCall(FunctionCall {
+                        name: name.to_owned(),
+                        parameters,
+                        propagate: false,
+                    })));
+                    i = j;
                 },
                 Token::VarName(_) => (),
                 Token::Semicolon(_) => (),
@@ -232,32 +423,36 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                 Token::Function(_) => {
                     let mut name = String::new();
                     let mut parameters = String::new();
+                    let mut return_type = String::new();
                     let mut j = i + 1;
 
                     while j < line.len() {
                         match &line[j] {
                             Token::FuncName((_, func_name)) => name = func_name.to_owned(),
                             Token::Parameters((_, value)) => parameters.push_str(value),
+                            Token::ReturnType((_, value)) => return_type = rust_type(value),
                             _ => (),
                         }
 
                         j += 1;
                     }
 
-                    parsed_lines.push(Parsed::FunctionDeclare(FunctionDeclare {
+                    parsed_lines.push((span, Parsed::FunctionDeclare(FunctionDeclare {
                         name,
-                        parameters
-                    }));
+                        parameters,
+                        return_type,
+                        public: i > 0 && matches!(line[i - 1], Token::Pub(_)),
+                    })));
                     i = j;
                 },
                 Token::FuncName((_, name)) => {
                     let mut is_declare = false;
                     let mut is_calling = false;
+                    let mut propagate = false;
                     let mut parameters = String::new();
 
-                    match &line[i-1] {
-                        Token::Function(_) => is_declare = true,
-                        _ => (),
+                    if i > 0 && matches!(line[i - 1], Token::Function(_)) {
+                        is_declare = true;
                     }
 
                     if !is_declare {
@@ -268,6 +463,7 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                                     is_calling = true;
                                 },
                                 Token::Parameters((_, value)) => parameters.push_str(value),
+                                Token::Question(_) => propagate = true,
                                 _ => (),
                             }
 
@@ -275,12 +471,13 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                         }
 
                         if is_calling {
-                            parsed_lines.push(Parsed::FunctionCall(FunctionCall {
+                            parsed_lines.push((span, Parsed::FunctionCall(FunctionCall {
                                 name: name.to_owned(),
                                 parameters,
-                            }));
+                                propagate,
+                            })));
                         } else {
-                            panic!("invalid syntax");
+                            return Err(syntax(span, &format!("`{name}` is a proc, call it like `{name}(...);`")));
                         }
                         i = j;
                     }
@@ -290,19 +487,47 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                     let mut j = i + 1;
 
                     while j < line.len() {
-                        match &line[j] {
-                            Token::Parameters((_, value)) => body.push_str(value),
-                            _ => (),
+                        if let Token::Parameters((_, value)) = &line[j] {
+                            body.push_str(value);
                         }
 
                         j += 1;
                     }
 
-                    parsed_lines.push(Parsed::Print(body));
+                    parsed_lines.push((span, Parsed::Print(body)));
                 },
                 Token::NewLine(_) => (),
                 Token::Parameters(_) => (),
                 Token::Comment(_) => (),
+                Token::If(_) if matches!(line.get(i + 1), Some(Token::Let(_))) => {
+                    let mut binding = String::new();
+                    let mut value_start = None;
+                    let mut value_end = line.len();
+                    let mut j = i + 1;
+
+                    while j < line.len() {
+                        match &line[j] {
+                            Token::VarName((_, var_name)) if binding.is_empty() => binding = var_name.to_owned(),
+                            Token::EqualsTo(_) => value_start = Some(j + 1),
+                            Token::LSquirly(_) => value_end = j,
+                            _ => (),
+                        }
+
+                        j += 1;
+                    }
+
+                    match value_start {
+                        Some(start) if !binding.is_empty() && start < value_end => {
+                            parsed_lines.push((span, Parsed::IfLet(IfLet {
+                                binding,
+                                value: render_value(&line[start..value_end]),
+                            })));
+                        },
+                        _ => return Err(syntax(span, "`if let` needs a name and a value, like `if let some(x) = value {`")),
+                    }
+
+                    i = j;
+                },
                 Token::If(_) => {
                     let mut is_opened = false;
                     let mut parameter = String::new();
@@ -318,7 +543,7 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                     }
                     
                     if is_opened {
-                        parsed_lines.push(Parsed::If(parameter));
+                        parsed_lines.push((span, Parsed::If(parameter)));
                     }
 
                     i = j;
@@ -338,20 +563,226 @@ pub fn parser(tokens: Vec<Token>) -> Vec<Parsed> {
                     }
                     
                     if is_opened {
-                        parsed_lines.push(Parsed::OrIf(parameter));
+                        parsed_lines.push((span, Parsed::OrIf(parameter)));
                     }
 
                     i = j;
                 },
-                Token::Else(_) => parsed_lines.push(Parsed::Else),
+                Token::Else(_) => parsed_lines.push((span, Parsed::Else)),
                 Token::Vector(_) => (),
                 Token::LSquare(_) => (),
                 Token::RSquare(_) => (),
+                Token::Some(_) => (),
+                Token::None(_) => (),
+                Token::Let(_) => {
+                    let mut names = Vec::new();
+                    let mut value_start = None;
+                    let mut value_end = line.len();
+                    let mut j = i + 1;
+
+                    while j < line.len() {
+                        match &line[j] {
+                            Token::Parameters((_, value)) if value_start.is_none() => {
+                                names = split_top_level(value).iter().map(|name| name.trim().to_string()).collect();
+                            },
+                            Token::EqualsTo(_) if value_start.is_none() => value_start = Some(j + 1),
+                            Token::Semicolon(_) => value_end = j,
+                            _ => (),
+                        }
+
+                        j += 1;
+                    }
+
+                    match value_start {
+                        Some(start) if !names.is_empty() && start < value_end => {
+                            parsed_lines.push((span, Parsed::Destructure(Destructure {
+                                names,
+                                value: render_value(&line[start..value_end]),
+                            })));
+                        },
+                        _ => return Err(syntax(span, "`let` needs names and a tuple, like `let (a, b) = pair;`")),
+                    }
+
+                    i = j;
+                },
+                Token::Return(_) => {
+                    let mut value_end = line.len();
+                    let mut j = i + 1;
+
+                    while j < line.len() {
+                        if let Token::Semicolon(_) = &line[j] {
+                            value_end = j;
+                        }
+
+                        j += 1;
+                    }
+
+                    parsed_lines.push((span, Parsed::Return(render_value(&line[i + 1..value_end]))));
+                    i = j;
+                },
+                Token::Ok(_) => (),
+                Token::Fail(_) => (),
+                Token::Question(_) => (),
+                Token::Arrow(_) => (),
+                Token::ReturnType(_) => (),
+                Token::For(_) => {
+                    let mut bindings = String::new();
+                    let mut iterable_start = None;
+                    let mut iterable_end = line.len();
+                    let mut j = i + 1;
+
+                    while j < line.len() {
+                        match &line[j] {
+                            Token::VarName((_, value)) | Token::Parameters((_, value)) if iterable_start.is_none() => bindings.push_str(value),
+                            Token::In(_) => iterable_start = Some(j + 1),
+                            Token::LSquirly(_) => iterable_end = j,
+                            _ => (),
+                        }
+
+                        j += 1;
+                    }
+
+                    match iterable_start {
+                        Some(start) if !bindings.is_empty() && start < iterable_end => {
+                            parsed_lines.push((span, Parsed::For(ForLoop {
+                                bindings,
+                                iterable: render_value(&line[start..iterable_end]),
+                            })));
+                        },
+                        _ => return Err(syntax(span, "`for` needs a name and something to loop over, like `for (key, value) in ages {`")),
+                    }
+
+                    i = j;
+                },
+                Token::In(_) => (),
+                Token::Colon(_) => (),
+                Token::Import(_) => {
+                    let mut path = None;
+                    let mut is_semicoloned = false;
+                    let mut j = i + 1;
+
+                    while j < line.len() {
+                        match &line[j] {
+                            Token::Strings((_, value)) if path.is_none() => path = Some(value.to_owned()),
+                            Token::Semicolon(_) => is_semicoloned = true,
+                            _ => (),
+                        }
+
+                        j += 1;
+                    }
+
+                    match path {
+                        Some(path) if is_semicoloned => {
+                            let name = naming::module_name(&path);
+                            parsed_lines.push((span, Parsed::Import(Import { path, name })));
+                        },
+                        _ => return Err(syntax(span, "`import` needs a path and a `;`, like `import \"utils.oxi\";`")),
+                    }
+
+                    i = j;
+                },
+                Token::Pub(_) => (),
+                Token::Const(_) => (),
             }
 
             i += 1;
         }
+
+        // `const` goes in front of a declaration and nothing else
+        if line.iter().any(|token| matches!(token, Token::Const(_))) {
+            match parsed_lines.pop() {
+                Some((span, Parsed::VariableDeclare(declare))) if parsed_lines.len() == declared => {
+                    parsed_lines.push((span, Parsed::Const(declare)));
+                },
+                _ => return Err(syntax(span, "`const` has to go in front of a declaration, like `const int MAX = 100;`")),
+            }
+        }
+    }
+
+    qualify_imports(&mut parsed_lines);
+    return Ok(parsed_lines);
+}
+
+/// raw parameter text isn't tokenised, so `math.double(math.double(1))`
+/// still has an inner call on an imported file to turn into `math::double`
+fn qualify_imports(parsed: &mut [(Span, Parsed)]) {
+    let modules: Vec<String> = parsed.iter().filter_map(|(_, elem)| match elem {
+        Parsed::Import(import) => Some(import.name.to_owned()),
+        _ => None,
+    }).collect();
+
+    if modules.is_empty() {
+        return;
+    }
+
+    for (_, elem) in parsed.iter_mut() {
+        let value = match elem {
+            Parsed::VariableDeclare(VariableDeclare { value, .. }) | Parsed::Const(VariableDeclare { value, .. }) |
+            Parsed::Destructure(Destructure { value, .. }) | Parsed::FunctionCall(FunctionCall { parameters: value, .. }) |
+            Parsed::Print(value) | Parsed::Return(value) |
+            Parsed::If(value) | Parsed::OrIf(value) | Parsed::IfLet(IfLet { value, .. }) |
+            Parsed::For(ForLoop { iterable: value, .. }) => value,
+            _ => continue,
+        };
+
+        let mut output = String::new();
+        let mut word = String::new();
+        let mut inside_quotes = false;
+
+        for c in value.chars().chain(std::iter::once('\0')) {
+            if !inside_quotes && (c.is_alphanumeric() || c == '_') {
+                word.push(c);
+                continue;
+            }
+
+            output.push_str(&word);
+            if c == '.' && !inside_quotes && modules.contains(&word) {
+                output.push_str("::");
+            } else if c != '\0' {
+                output.push(c);
+            }
+
+            if c == '"' {
+                inside_quotes = !inside_quotes;
+            }
+
+            word.clear();
+        }
+
+        *value = output;
+    }
+}
+
+/// a statement, or one that opens a block along with everything inside it
+pub(crate) enum Node {
+    Statement(Span, Parsed),
+    Block(Span, Parsed, Vec<Node>),
+}
+
+/// the flat statements from the parser, nested by their squirlies
+pub(crate) fn nest(parsed: Vec<(Span, Parsed)>) -> Vec<Node> {
+    let mut stack: Vec<(Span, Parsed, Vec<Node>)> = Vec::new();
+    let mut top = Vec::new();
+
+    for (span, elem) in parsed {
+        match elem {
+            Parsed::FunctionDeclare(_) | Parsed::If(_) | Parsed::IfLet(_) |
+            Parsed::OrIf(_) | Parsed::Else | Parsed::For(_) => stack.push((span, elem, Vec::new())),
+            Parsed::RSquirly => {
+                if let Some((span, head, body)) = stack.pop() {
+                    let block = Node::Block(span, head, body);
+                    match stack.last_mut() {
+                        Some((_, _, body)) => body.push(block),
+                        None => top.push(block),
+                    }
+                }
+            },
+            _ => match stack.last_mut() {
+                Some((_, _, body)) => body.push(Node::Statement(span, elem)),
+                None => top.push(Node::Statement(span, elem)),
+            },
+        }
     }
 
-    return parsed_lines;
+    return top;
 }
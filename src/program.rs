@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::{Diagnostic, Options, Output};
+use crate::backend::Linked;
+use crate::naming;
+use crate::generator::{self, Unit};
+use crate::ir;
+use crate::parser::{Parsed, Span, FunctionCall, VariableDeclare, Destructure, IfLet, ForLoop, FunctionDeclare, Import};
+
+/// one oxide file of a program
+pub(crate) struct Module {
+    pub(crate) name: String,
+    /// where the file was found, `canonical` tells files apart
+    pub(crate) path: String,
+    canonical: PathBuf,
+    /// the file as it was read, the parser drops its comments
+    pub(crate) source: String,
+    pub(crate) parsed: Vec<(Span, Parsed)>,
+    /// checked and lowered once, the backends and the linter share it
+    pub(crate) lowered: ir::Module,
+    /// the modules this one imports, by name, as indexes into the loaded modules
+    imports: Vec<(String, usize)>,
+}
+
+/// the file at `path` and every file it imports, loaded and checked.
+/// imported files come before the files importing them, so the entry
+/// file is last
+pub(crate) fn load(path: &str, source: &str, options: &Options) -> Result<Vec<Module>, Vec<Diagnostic>> {
+    let mut loader = Loader { modules: Vec::new(), stack: Vec::new(), errors: Vec::new(), options: options.clone() };
+    let canonical = Path::new(path).canonicalize().unwrap_or(PathBuf::from(path));
+    loader.load(path, canonical, source, None);
+
+    if loader.errors.is_empty() {
+        check_visibility(&loader.modules, &mut loader.errors);
+    }
+
+    if !loader.errors.is_empty() {
+        return Err(loader.errors);
+    }
+
+    return Ok(loader.modules);
+}
+
+/// the program starting at `path`, with every file it imports loaded,
+/// checked and generated as a `mod` of its own
+pub fn compile_file(path: &str, source: &str, options: &Options) -> Result<Output, Vec<Diagnostic>> {
+    let mut modules = load(path, source, options)?;
+    let Some(entry_module) = modules.pop() else {
+        return Err(Vec::new());
+    };
+
+    let mut renamed = Vec::new();
+
+    for module in &modules {
+        renamed.extend(generator::renamed(&module.lowered));
+    }
+
+    renamed.extend(generator::renamed(&entry_module.lowered));
+
+    let unit = if options.module { Unit::Include } else { Unit::Program };
+    let entry = generator::generator(&entry_module.lowered, options, unit, None);
+
+    let imports = modules.into_iter().map(|module| Linked {
+        source: generator::generator(&module.lowered, options, Unit::Import, Some(&module.name)),
+        name: module.name,
+        path: module.path,
+    }).collect();
+
+    let rust = generator::link(options, entry, path, imports);
+
+    return Ok(crate::finish(rust, options, renamed));
+}
+
+struct Loader {
+    modules: Vec<Module>,
+    /// the files being loaded right now, an import of one of these is a cycle
+    stack: Vec<(PathBuf, String)>,
+    errors: Vec<Diagnostic>,
+    /// how the entry file is checked, imported files can only declare procs
+    options: Options,
+}
+
+impl Loader {
+    /// loads a file and everything it imports, imported files come before
+    /// the files importing them. `file` is `None` for the entry file
+    fn load(&mut self, path: &str, canonical: PathBuf, source: &str, file: Option<&str>) -> Option<usize> {
+        let tagged = |mut error: Diagnostic| {
+            error.file = file.map(String::from);
+            error
+        };
+
+        let parsed = match crate::tokenise(source).and_then(|tokens| crate::parse(tokens, source)) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.errors.push(tagged(error));
+                return None;
+            },
+        };
+
+        let options = Options { module: self.options.module || file.is_some(), ..self.options.clone() };
+        // a file with errors is never generated, `load` fails with them
+        let lowered = crate::check(&parsed, &options).unwrap_or_else(|errors| {
+            self.errors.extend(errors.into_iter().map(tagged));
+            ir::Module::default()
+        });
+
+        self.stack.push((canonical.clone(), path.to_string()));
+        let mut imports = Vec::new();
+
+        for (span, elem) in &parsed {
+            let Parsed::Import(Import { path: import_path, name }) = elem else {
+                continue;
+            };
+
+            let error = |message: String| Diagnostic { span: Some(*span), message, file: file.map(String::from) };
+            let found = Path::new(path).parent().unwrap_or(Path::new("")).join(import_path);
+            let found_path = found.to_string_lossy().to_string();
+
+            let import_canonical = match found.canonicalize() {
+                Ok(canonical) => canonical,
+                Err(reason) => {
+                    self.errors.push(error(format!("couldn't import {found_path}: {reason}")));
+                    continue;
+                },
+            };
+
+            if let Some(start) = self.stack.iter().position(|(loading, _)| *loading == import_canonical) {
+                let mut cycle: Vec<&str> = self.stack[start..].iter().map(|(_, path)| path.as_str()).collect();
+                cycle.push(&found_path);
+                self.errors.push(error(format!("import cycle: {}", cycle.join(" -> "))));
+                continue;
+            }
+
+            if let Some(index) = self.modules.iter().position(|module| module.canonical == import_canonical) {
+                imports.push((name.to_owned(), index));
+                continue;
+            }
+
+            if let Some(other) = self.modules.iter().find(|module| module.name == *name) {
+                self.errors.push(error(format!("{found_path} and {} are both called `{name}`, rename one of them", other.path)));
+                continue;
+            }
+
+            let source = match fs::read_to_string(&found) {
+                Ok(source) => source,
+                Err(reason) => {
+                    self.errors.push(error(format!("couldn't import {found_path}: {reason}")));
+                    continue;
+                },
+            };
+
+            if let Some(index) = self.load(&found_path, import_canonical, &source, Some(&found_path)) {
+                imports.push((name.to_owned(), index));
+            }
+        }
+
+        self.stack.pop();
+        self.modules.push(Module {
+            name: naming::module_name(path),
+            path: path.to_string(),
+            canonical,
+            source: source.to_string(),
+            parsed,
+            lowered,
+            imports,
+        });
+
+        return Some(self.modules.len() - 1);
+    }
+}
+
+/// `utils.double(2)` needs `utils` to have a `pub proc double`
+fn check_visibility(modules: &[Module], errors: &mut Vec<Diagnostic>) {
+    for (index, module) in modules.iter().enumerate() {
+        // the entry file is loaded last and its diagnostics name the file it was given as
+        let file = if index == modules.len() - 1 { None } else { Some(module.path.clone()) };
+
+        for (span, elem) in &module.parsed {
+            for (module_name, proc_name) in qualified_calls(elem) {
+                let Some((_, target)) = module.imports.iter().find(|(name, _)| *name == module_name) else {
+                    continue;
+                };
+
+                let target = &modules[*target];
+                let declared = target.parsed.iter().find_map(|(_, elem)| match elem {
+                    Parsed::FunctionDeclare(declare) if declare.name == proc_name => Some(declare),
+                    _ => None,
+                });
+
+                let message = match declared {
+                    Some(FunctionDeclare { public: true, .. }) => continue,
+                    Some(_) => format!("`{proc_name}` isn't `pub` in {}, so it can't be called from here", target.path),
+                    None => format!("{} has no proc called `{proc_name}`", target.path),
+                };
+
+                errors.push(Diagnostic { span: Some(*span), message, file: file.clone() });
+            }
+        }
+    }
+}
+
+/// every `module::proc` a statement calls
+fn qualified_calls(elem: &Parsed) -> Vec<(String, String)> {
+    let text = match elem {
+        Parsed::VariableDeclare(VariableDeclare { value, .. }) | Parsed::Destructure(Destructure { value, .. }) |
+        Parsed::Return(value) | Parsed::If(value) | Parsed::OrIf(value) | Parsed::Print(value) |
+        Parsed::IfLet(IfLet { value, .. }) | Parsed::For(ForLoop { iterable: value, .. }) => value.to_owned(),
+        Parsed::FunctionCall(FunctionCall { name, parameters, .. }) => format!("{name}({parameters})"),
+        _ => return Vec::new(),
+    };
+
+    // `::` in a string isn't a call
+    let text: String = text.split('"').step_by(2).collect::<Vec<&str>>().join(" ");
+
+    let is_name = |c: char| c.is_alphanumeric() || c == '_';
+    let mut calls = Vec::new();
+    let mut rest = text.as_str();
+
+    while let Some(index) = rest.find("::") {
+        let module = rest[..index].rsplit(|c| !is_name(c)).next().unwrap_or_default();
+        let after = &rest[index + 2..];
+        let proc_name = after.split(|c| !is_name(c)).next().unwrap_or_default();
+
+        if !module.is_empty() && !proc_name.is_empty() {
+            calls.push((module.to_string(), proc_name.to_string()));
+        }
+
+        rest = after;
+    }
+
+    return calls;
+}
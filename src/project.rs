@@ -0,0 +1,112 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::Options;
+use crate::naming;
+
+/// the program `oxide new` starts a project with
+const HELLO: &str = "proc main() {
+    print(\"hello world\");
+}
+";
+
+/// a name rustc and cargo will take for a crate, made from a file or
+/// directory name
+pub fn crate_name(path: &str) -> String {
+    return naming::module_name(path);
+}
+
+/// starts a project in `dir` with a hello world `src/main.oxi` and the
+/// rust generated from it. gives back the files it wrote
+pub fn new_project(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let source = dir.join("src").join("main.oxi");
+
+    if source.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", source.display())));
+    }
+
+    fs::create_dir_all(dir.join("src"))?;
+    fs::write(&source, HELLO)?;
+
+    let output = match crate::compile(HELLO, &Options::default()) {
+        Ok(output) => output,
+        Err(_) => unreachable!("the hello world program compiles"),
+    };
+
+    let mut written = vec![source];
+    written.extend(write_project(dir, &output.code, "src/main.oxi")?);
+    return Ok(written);
+}
+
+/// `--emit=cargo`, a project around the rust generated from `source`,
+/// the contents of `path`. the oxide is copied to `src/main.oxi` so the
+/// project builds the same way as one `new` started
+pub fn emit_project(dir: &Path, rust: &str, path: &str, source: &str) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir.join("src"))?;
+
+    let copy = dir.join("src").join("main.oxi");
+    let mut written = Vec::new();
+
+    // a project built from its own `src/main.oxi` already has it
+    if !copy.exists() || copy.canonicalize()? != Path::new(path).canonicalize().unwrap_or_default() {
+        fs::write(&copy, source)?;
+        written.push(copy);
+    }
+
+    written.extend(write_project(dir, rust, "src/main.oxi")?);
+    return Ok(written);
+}
+
+/// writes a cargo project around the generated rust. Cargo.toml, the
+/// README and .gitignore are only written the first time, after that
+/// just `src/main.rs` is regenerated. `source` names the oxide file in
+/// the header of `src/main.rs`. gives back the files it wrote
+pub fn write_project(dir: &Path, rust: &str, source: &str) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir.join("src"))?;
+
+    let name = crate_name(&dir.canonicalize()?.to_string_lossy());
+    let mut written = Vec::new();
+
+    let scaffolding = [
+        ("Cargo.toml", cargo_toml(&name)),
+        ("README.md", readme(&name)),
+        (".gitignore", String::from("/target\n")),
+    ];
+
+    for (file, contents) in scaffolding {
+        let path = dir.join(file);
+        if !path.exists() {
+            fs::write(&path, contents)?;
+            written.push(path);
+        }
+    }
+
+    let main = dir.join("src").join("main.rs");
+    fs::write(&main, format!("// generated by oxide from {source}, edit that instead\n\n{rust}"))?;
+    written.push(main);
+
+    return Ok(written);
+}
+
+fn cargo_toml(name: &str) -> String {
+    return format!("[package]
+name = \"{name}\"
+version = \"0.1.0\"
+edition = \"2021\"
+
+[dependencies]
+");
+}
+
+fn readme(name: &str) -> String {
+    return format!("# {name}
+
+written in oxide. `src/main.oxi` is the source, `src/main.rs` is
+generated from it and is overwritten on every build.
+
+```
+oxide emit --emit=cargo src/main.oxi -o .
+cargo run
+```
+");
+}
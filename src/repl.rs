@@ -0,0 +1,172 @@
+use std::io::{self, BufRead, Write};
+use oxide::{Diagnostic, Options, Token};
+use oxide::interpreter::Session;
+
+const HELP: &str = "enter oxide statements, procs or an expression to see its value
+    :type <expr>    the type of an expression, without running it for real
+    :ast [code]     the parsed statements of the code, or of the whole session
+    :rust [code]    the rust the code adds to the session, or all of its rust
+    :reset          forget every variable and proc
+    :help           this message
+    :quit           leave, so does ctrl-d
+";
+
+/// `oxide repl`, reads from stdin until it runs out or `:quit`
+pub fn repl(quiet: bool) {
+    let stdin = io::stdin();
+    let mut session = Session::new(Box::new(io::stdout()));
+    let mut buffer = String::new();
+
+    if !quiet {
+        println!("oxide repl, :help for help");
+    }
+
+    loop {
+        print!("{}", if buffer.is_empty() { "> " } else { ". " });
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                println!();
+                return;
+            },
+            Ok(_) => (),
+        }
+
+        if buffer.is_empty() && line.trim_start().starts_with(':') {
+            if !meta_command(line.trim(), &mut session) {
+                return;
+            }
+
+            continue;
+        }
+
+        buffer.push_str(&line);
+
+        // keep reading until every `{` has its `}`. the earlier inputs
+        // are balanced, but they declare the `const`s a proc can use
+        if unclosed_squirlies(&format!("{}{buffer}", session.source())) > 0 {
+            continue;
+        }
+
+        let input = std::mem::take(&mut buffer);
+        let trimmed = input.trim();
+
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        // anything that isn't a statement or a block is an expression to show
+        if !trimmed.ends_with(';') && !trimmed.ends_with('}') {
+            match session.eval(trimmed) {
+                Ok((value, type_name)) => println!("{}: {type_name}", value.debug()),
+                Err(message) => eprintln!("error: {message}"),
+            }
+
+            continue;
+        }
+
+        if let Err(errors) = session.run(&input) {
+            for error in errors {
+                eprintln!("{}", error.render("<repl>"));
+            }
+        }
+    }
+}
+
+/// how many more `{` than `}` the input has, going by the tokeniser
+fn unclosed_squirlies(input: &str) -> i32 {
+    let Ok(tokens) = oxide::tokenise(input) else {
+        return 0;
+    };
+
+    return tokens.iter().map(|token| match token {
+        Token::LSquirly(_) => 1,
+        Token::RSquirly(_) => -1,
+        _ => 0,
+    }).sum();
+}
+
+/// the rust `code` adds to what `session` generates, only the lines that
+/// differ are shown. errors are at lines of `code`
+fn show_rust(session: &str, code: &str) {
+    let source = format!("{session}{code}\n");
+    let output = match oxide::compile(&source, &Options::default()) {
+        Ok(output) => output,
+        Err(errors) => {
+            report(session, errors);
+            return;
+        },
+    };
+
+    // `:rust` on its own shows everything
+    let before = match oxide::compile(session, &Options::default()) {
+        Ok(before) if !code.is_empty() => before,
+        _ => {
+            print!("{}", output.code);
+            return;
+        },
+    };
+
+    let (old, new): (Vec<&str>, Vec<&str>) = (before.code.lines().collect(), output.code.lines().collect());
+    let start = old.iter().zip(&new).take_while(|(old, new)| old == new).count();
+    let end = old[start..].iter().rev().zip(new[start..].iter().rev()).take_while(|(old, new)| old == new).count();
+
+    for line in &new[start..new.len() - end] {
+        println!("{line}");
+    }
+}
+
+/// the statements `code` adds to `session`, at lines of `code`. with no
+/// `code` every statement of the session is shown
+fn show_ast(session: &str, code: &str) {
+    let source = format!("{session}{code}\n");
+    let parsed = match oxide::tokenise(&source).and_then(|tokens| oxide::parse(tokens, &source)) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            report(session, vec![error]);
+            return;
+        },
+    };
+
+    let lines = if code.is_empty() { 0 } else { session.lines().count() };
+    for (span, elem) in parsed.into_iter().filter(|(span, _)| span.line > lines) {
+        println!("{}:{} {elem:?}", span.line - lines, span.column);
+    }
+}
+
+/// prints errors in `session` followed by more code, the session already
+/// ran so every error is in the new code and at its lines
+fn report(session: &str, errors: Vec<Diagnostic>) {
+    let lines = session.lines().count();
+    for mut error in errors {
+        if let Some(span) = &mut error.span {
+            span.line = span.line.saturating_sub(lines).max(1);
+        }
+
+        eprintln!("{}", error.render("<repl>"));
+    }
+}
+
+/// gives back false to leave the repl
+fn meta_command(line: &str, session: &mut Session) -> bool {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = rest.trim();
+
+    match command {
+        ":type" | ":t" => match session.type_of(rest) {
+            Ok(type_name) => println!("{type_name}"),
+            Err(message) => eprintln!("error: {message}"),
+        },
+        // the snippets can use the session's variables and procs
+        ":ast" => show_ast(session.source(), rest),
+        ":rust" => show_rust(session.source(), rest),
+        ":reset" => *session = Session::new(Box::new(io::stdout())),
+        ":help" | ":h" => print!("{HELP}"),
+        ":quit" | ":q" => return false,
+        other => eprintln!("error: unknown command `{other}`, :help lists them"),
+    }
+
+    return true;
+}
@@ -0,0 +1,125 @@
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use crate::sourcemap::SourceMap;
+use crate::project::crate_name;
+use crate::backend::Target;
+
+/// where `oxide build` leaves the generated source and the binary
+const TARGET_DIR: &str = "target/oxide";
+
+/// a compiled program and the generated source it came from
+pub struct Built {
+    /// for javascript, the module node runs
+    pub binary: PathBuf,
+    pub rust_path: PathBuf,
+    pub language: Target,
+}
+
+/// compiles generated rust with the installed rustc, or generated c with
+/// `$CC` or `cc`, any errors go to `stderr` against the oxide source. the
+/// binary goes to `output` if it's given. javascript and webassembly text
+/// aren't compiled, the module itself goes to `output`
+pub fn build(rust: &str, map: &SourceMap, oxide_path: &str, output: Option<&str>, language: Target, stderr: &mut dyn Write) -> Option<Built> {
+    // rustc names the crate after the file, so it has to be an identifier
+    let name = crate_name(oxide_path);
+
+    let dir = Path::new(TARGET_DIR);
+    if let Err(error) = fs::create_dir_all(dir) {
+        let _ = writeln!(stderr, "error: couldn't create {TARGET_DIR}: {error}");
+        return None;
+    }
+
+    let extension = match language {
+        Target::Rust => "rs",
+        Target::C => "c",
+        Target::Js => "mjs",
+        Target::Wat => "wat",
+    };
+
+    let mut rust_path = dir.join(format!("{name}.{extension}"));
+    let binary = match output {
+        Some(output) => PathBuf::from(output),
+        None => dir.join(&name),
+    };
+
+    if matches!(language, Target::Js | Target::Wat) {
+        rust_path = binary.with_extension(extension);
+    }
+
+    if let Err(error) = fs::write(&rust_path, rust) {
+        let _ = writeln!(stderr, "error: couldn't write {}: {error}", rust_path.display());
+        return None;
+    }
+
+    let (compiler, args) = match language {
+        Target::Rust => (String::from("rustc"), vec!["--edition", "2021", "-o"]),
+        Target::C => (env::var("CC").unwrap_or(String::from("cc")), vec!["-std=c99", "-O2", "-o"]),
+        Target::Js | Target::Wat => return Some(Built { binary: rust_path.clone(), rust_path, language }),
+    };
+
+    let output = Command::new(&compiler)
+        .args(args)
+        .arg(&binary)
+        .arg(&rust_path)
+        .output();
+
+    let output = match output {
+        Ok(output) => output,
+        Err(_) => {
+            let _ = writeln!(stderr, "error: couldn't run {compiler}, is it installed?");
+            return None;
+        },
+    };
+
+    let diagnostics = String::from_utf8_lossy(&output.stderr);
+    let _ = write!(stderr, "{}", map.rewrite(&diagnostics, &rust_path.to_string_lossy(), oxide_path));
+
+    if !output.status.success() {
+        return None;
+    }
+
+    return Some(Built { binary, rust_path, language });
+}
+
+/// runs a built program with the given arguments, its stderr goes to
+/// `stderr` with panics against the oxide source. gives back the
+/// program's exit code
+pub fn run(built: &Built, args: &[String], map: &SourceMap, oxide_path: &str, stderr: &mut dyn Write) -> i32 {
+    let mut command = match built.language {
+        Target::Js => Command::new("node"),
+        _ => Command::new(&built.binary),
+    };
+
+    if built.language == Target::Js {
+        command.arg(&built.binary);
+    }
+
+    let child = command
+        .args(args)
+        .stderr(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(error) => {
+            let _ = writeln!(stderr, "error: couldn't run {}: {error}", built.binary.display());
+            return 1;
+        },
+    };
+
+    let rust_name = built.rust_path.to_string_lossy();
+
+    if let Some(output) = child.stderr.take() {
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            let _ = writeln!(stderr, "{}", map.rewrite(&line, &rust_name, oxide_path));
+        }
+    }
+
+    return match child.wait() {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(_) => 1,
+    };
+}
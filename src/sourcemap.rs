@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::fs;
+use crate::parser::Span;
+use crate::naming::oxide_identifier;
+
+/// the comment the generator leaves on each line it emits for a statement
+pub const MARKER: &str = " // ox:";
+
+/// which oxide statement each line of generated rust came from
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    lines: Vec<Option<Location>>,
+}
+
+/// a statement in the oxide source, `file` is only there for statements
+/// from an imported file, `// ox:utils.oxi:line:col`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub span: Span,
+}
+
+impl SourceMap {
+    /// reads back the `// ox:line:col` markers, these survive rustfmt so
+    /// the map stays right even after the output has been reformatted
+    pub fn from_markers(rust: &str) -> SourceMap {
+        let mut lines: Vec<Option<Location>> = Vec::new();
+
+        for line in rust.lines() {
+            let location = marker_location(line);
+
+            // rustfmt moves a comment after `{` onto a line of its own
+            if is_marker_only(line) {
+                if let Some(above @ None) = lines.last_mut() {
+                    *above = location.clone();
+                }
+            }
+
+            lines.push(location);
+        }
+
+        return SourceMap { lines };
+    }
+
+    /// the statement behind a 1-based line of generated rust, closing
+    /// braces and other generated lines fall back to the nearest line above
+    pub fn lookup(&self, rust_line: usize) -> Option<&Location> {
+        let end = rust_line.min(self.lines.len());
+        return self.lines[..end].iter().rev().find_map(|location| location.as_ref());
+    }
+
+    /// rewrites `gen.rs:12:5` locations, as printed by rustc and by
+    /// panics, into `file.oxi:3:5`, and escaped names like `r#type` back
+    /// into the oxide names they came from. the lines of rust rustc
+    /// quotes under a location are swapped for the oxide lines, read from
+    /// `oxide_path` and the files it imports, or dropped if they can't be
+    pub fn rewrite(&self, text: &str, rust_path: &str, oxide_path: &str) -> String {
+        let file_name = rust_path.rsplit('/').next().unwrap_or(rust_path);
+        let mut sources: HashMap<String, Option<String>> = HashMap::new();
+        let mut lines: Vec<String> = Vec::new();
+        // whether the snippet being read is from the generated file, and
+        // the oxide statement its last line was swapped for
+        let mut ours = false;
+        let mut shown: Option<&Location> = None;
+        // a `help:` quoting the generated file suggests changes to the
+        // rust, which have no oxide to show, so its whole block is dropped
+        let mut help: Option<usize> = None;
+        let mut dropping = false;
+
+        for line in text.split('\n') {
+            let trimmed = line.trim_start();
+            let quoted = in_snippet(line);
+            if dropping && quoted {
+                continue;
+            }
+
+            dropping = false;
+            if trimmed.starts_with("help:") {
+                help = Some(lines.len());
+            } else if !quoted {
+                help = None;
+            }
+
+            if let Some(location) = trimmed.strip_prefix("-->").or(trimmed.strip_prefix(":::")) {
+                let path = location.trim().rsplitn(3, ':').nth(2).unwrap_or_default();
+                ours = path == file_name || path.ends_with(&format!("/{file_name}"));
+                shown = None;
+            }
+
+            match gutter(line) {
+                Some((_, Gutter::Source(_))) if ours && help.is_some() => {
+                    lines.truncate(help.take().unwrap_or_default());
+                    dropping = true;
+                },
+                Some((width, Gutter::Source(rust_line))) if ours => {
+                    let location = self.lookup(rust_line);
+
+                    // every line generated from the statement shows it once
+                    if location.is_some() && location == shown {
+                        continue;
+                    }
+
+                    shown = location;
+                    let file = location.map(|location| location.file.as_deref().unwrap_or(oxide_path));
+                    let source = file.and_then(|file| {
+                        sources.entry(file.to_string()).or_insert_with(|| fs::read_to_string(file).ok()).as_deref()
+                    });
+
+                    match (location, source) {
+                        (Some(location), Some(source)) => {
+                            let code = source.lines().nth(location.span.line - 1).unwrap_or_default();
+                            lines.push(format!("{:>width$} | {code}", location.span.line).trim_end().to_string());
+                        },
+                        _ => shown = None,
+                    }
+                },
+                // the rust's columns mean nothing in the oxide, so the label
+                // points at the start of the statement
+                Some((width, Gutter::Label(label))) if ours => {
+                    if let Some(location) = shown {
+                        lines.push(format!("{:width$} | {}{label}", "", " ".repeat(location.span.column - 1)));
+                    }
+                },
+                _ => {
+                    let words: Vec<String> = line.split(' ')
+                        .map(|word| self.rewrite_location(word, file_name, oxide_path).unwrap_or(word.to_string()))
+                        .collect();
+
+                    lines.push(unescape_identifiers(&words.join(" ")));
+                },
+            }
+        }
+
+        return lines.join("\n");
+    }
+
+    fn rewrite_location(&self, word: &str, file_name: &str, oxide_path: &str) -> Option<String> {
+        let trailing = if word.ends_with(':') { ":" } else { "" };
+        let mut parts = word.trim_end_matches(':').rsplitn(3, ':');
+        let _column: usize = parts.next()?.parse().ok()?;
+        let line: usize = parts.next()?.parse().ok()?;
+        let path = parts.next()?;
+
+        if path != file_name && !path.ends_with(&format!("/{file_name}")) {
+            return None;
+        }
+
+        let location = self.lookup(line)?;
+        let file = location.file.as_deref().unwrap_or(oxide_path);
+        return Some(format!("{file}:{}:{}{trailing}", location.span.line, location.span.column));
+    }
+}
+
+/// a line of a snippet rustc quotes, after the line number gutter
+enum Gutter {
+    /// `12 |     let x: i32 = "a";`
+    Source(usize),
+    /// `   |                  ^^^ expected `i32``, the `^` and its label
+    Label(String),
+}
+
+/// how wide the gutter is and what follows it, `None` for lines that
+/// aren't part of a snippet or don't quote or mark anything
+fn gutter(line: &str) -> Option<(usize, Gutter)> {
+    let (number, rest) = line.split_once(" |")?;
+    let width = number.len();
+
+    if let Ok(rust_line) = number.trim().parse() {
+        return Some((width, Gutter::Source(rust_line)));
+    }
+
+    if !number.trim().is_empty() {
+        return None;
+    }
+
+    // `-` and `+` mark other spans and suggestions, `|_` the end of one
+    // that covers several lines
+    let markers = rest.trim_start();
+    let first = markers.chars().find(|c| matches!(c, '^' | '-' | '+' | '~'))?;
+    let label = markers.trim_start_matches(['^', '-', '+', '~', '|', '_', ' ']);
+    return Some((width, Gutter::Label(format!("{first} {label}").trim_end().to_string())));
+}
+
+/// whether the line is in the gutter of a snippet, marked or not
+fn in_snippet(line: &str) -> bool {
+    return match line.split_once(" |") {
+        Some((number, _)) => number.trim().chars().all(|c| c.is_ascii_digit()),
+        None => line.trim() == "|" || line.trim() == "...",
+    };
+}
+
+/// drops the markers, giving back the clean source and the map for it
+pub fn strip_markers(rust: &str) -> (String, SourceMap) {
+    let mut output = String::new();
+    let mut lines: Vec<Option<Location>> = Vec::new();
+
+    for line in rust.lines() {
+        let location = marker_location(line);
+
+        if is_marker_only(line) {
+            if let Some(above @ None) = lines.last_mut() {
+                *above = location;
+            }
+
+            continue;
+        }
+
+        match line.rsplit_once(MARKER) {
+            Some((code, _)) => output.push_str(code),
+            None => output.push_str(line),
+        }
+
+        output.push('\n');
+        lines.push(location);
+    }
+
+    return (output, SourceMap { lines });
+}
+
+/// `r#type` and `ox_self` back to `type` and `self`
+fn unescape_identifiers(line: &str) -> String {
+    let mut output = String::new();
+    let mut word = String::new();
+
+    for c in line.chars().chain(std::iter::once('\n')) {
+        if c.is_alphanumeric() || c == '_' || c == '#' {
+            word.push(c);
+            continue;
+        }
+
+        output.push_str(oxide_identifier(&word).unwrap_or(&word));
+        word.clear();
+
+        if c != '\n' {
+            output.push(c);
+        }
+    }
+
+    return output;
+}
+
+/// marks every line generated from an imported file with that file
+pub fn tag_markers(rust: &str, file: &str) -> String {
+    return rust.replace(MARKER, &format!("{MARKER}{file}:"));
+}
+
+fn marker_location(line: &str) -> Option<Location> {
+    let (_, marker) = line.rsplit_once(MARKER.trim_start())?;
+    let mut parts = marker.trim().rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next().map(String::from);
+
+    return Some(Location { file, span: Span { line, column } });
+}
+
+fn is_marker_only(line: &str) -> bool {
+    return line.trim_start().starts_with(MARKER.trim_start());
+}
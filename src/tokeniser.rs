@@ -0,0 +1,450 @@
+use crate::Diagnostic;
+use crate::parser::{self, Span};
+use crate::naming;
+
+// while / for loops, maybe more ints, return values, vectors, mut
+//
+
+#[derive(Debug, Clone)]
+pub enum Token {
+    Plus((String, String)),
+    Minus((String, String)),
+    Multiply((String, String)),
+    Divide((String, String)),
+    LParen((String, String)),
+    RParen((String, String)),
+    LSquare((String, String)),
+    RSquare((String, String)),
+    LSquirly((String, String)),
+    RSquirly((String, String)),
+    SglQuote((String, String)),
+    DblQuote((String, String)),
+    EqualsTo((String, String)),
+    Equality((String, String)),
+    Number((String, i32)),
+    Strings((String, String)),
+    Boolean((String, bool)),
+    LetInt((String, String)),
+    LetString((String, String)),
+    LetBool((String, String)),
+    VarName((String, String)),
+    Semicolon((String, String)),
+    Comma((String, String)),
+    Function((String, String)),
+    FuncName((String, String)),
+    Print((String, String)),
+    Parameters((String, String)),
+    NewLine((String, String)),
+    Comment((String, String)),
+    If((String, String)),
+    OrIf((String, String)),
+    Else((String, String)),
+    Vector((String, Vec<Token>)),
+    LetOpt((String, String)),
+    Some((String, String)),
+    None((String, String)),
+    Let((String, String)),
+    LetResult((String, String)),
+    Ok((String, String)),
+    Fail((String, String)),
+    Question((String, String)),
+    Arrow((String, String)),
+    ReturnType((String, String)),
+    Return((String, String)),
+    LetMap((String, String)),
+    Colon((String, String)),
+    For((String, String)),
+    In((String, String)),
+    LetTuple((String, String)),
+    Import((String, String)),
+    Pub((String, String)),
+    Const((String, String)),
+}
+
+impl Token {
+    #[allow(dead_code)]
+    fn validate_vector(tokens: Vec<Token>) -> Option<Vec<Token>> {
+        let allowed_tokens: Vec<Token> = tokens.clone().into_iter()
+            .filter(|token| {
+                matches!(
+                    token,
+                    Token::Boolean(_) | Token::Strings(_) | Token::Number(_)
+                )
+            })
+            .collect();
+
+        if allowed_tokens.len() == tokens.len() {
+            return Some(allowed_tokens);
+        } else {
+            return None;
+        }
+    }
+}
+
+/// `at` is where `current_token` started, for the error when it can't be made sense of
+fn handle_ending_value(tokens: &mut Vec<Token>, current_token: &mut String, making_string: &mut i8, making_params: &mut i8, making_comment: &mut bool, at: Span, declared: &[(String, String)]) -> Result<(), Diagnostic> {
+    if current_token.is_empty() {
+        return Ok(());
+    }
+
+    if *making_comment {
+        tokens.push(Token::Comment((String::from("COMMENT"), current_token.to_owned())));
+        *making_comment = false;
+        current_token.clear();
+        return Ok(());
+    }
+
+    if *making_params >= 1 {
+        // the binding in `if let some(v) = x` is a new name, not a parameter
+        if let [.., Token::Let(_), Token::Some(_), Token::LParen(_)] = tokens.as_slice() {
+            tokens.push(Token::VarName((String::from("VARNAME"), current_token.to_string())));
+        } else {
+            tokens.push(Token::Parameters((String::from("PARAMETERS"), current_token.to_string()))); 
+        }
+        current_token.clear();
+        return Ok(());
+    }
+
+    // procs and `const`s can be used above where they're declared
+    let mut named = declared.to_vec();
+
+    for token in tokens.clone() {
+        match token {
+            Token::VarName(name) | Token::FuncName(name) => named.push(name),
+            _ => (),
+        }
+    }
+
+    // proc parameters and loop or `let` bindings are names too: `proc say(int x, string y)`, `for (k, v) in m`
+    for window in tokens.windows(3) {
+        if let [Token::FuncName(_) | Token::For(_) | Token::Let(_), Token::LParen(_), Token::Parameters((_, params))] = window {
+            for param in parser::split_top_level(params) {
+                if let Some(param_name) = param.split_whitespace().last() {
+                    named.push((String::from("VARNAME"), param_name.to_string()));
+                }
+            }
+        }
+    }
+
+    // `import "utils.oxi";` makes `utils` a name, its procs are called as `utils.double(2)`
+    for window in tokens.windows(3) {
+        if let [Token::Import(_), Token::DblQuote(_), Token::Strings((_, path))] = window {
+            named.push((String::from("MODULE"), naming::module_name(path)));
+        }
+    }
+
+    // `(int, string) pair`, the tuple type was read as a parameter list
+    if let [.., Token::LParen(_), Token::Parameters((_, types)), Token::RParen(_)] = tokens.as_slice() {
+        let starts_line = tokens.len() == 3 || matches!(tokens[tokens.len() - 4], Token::NewLine(_) | Token::LSquirly(_) | Token::Semicolon(_));
+
+        if starts_line {
+            let type_class = format!("({types})");
+            tokens.truncate(tokens.len() - 3);
+            tokens.push(Token::LetTuple((String::from("LETTUPLE"), type_class)));
+            tokens.push(Token::VarName((String::from("VARNAME"), current_token.to_string())));
+            current_token.clear();
+            return Ok(());
+        }
+    }
+
+    // `proc name() -> type`, the '-' has already become a Minus
+    if *current_token == ">" && matches!(tokens.last(), Some(Token::Minus(_))) {
+        tokens.pop();
+        tokens.push(Token::Arrow((String::from("ARROW"), String::from("->"))));
+        current_token.clear();
+        return Ok(());
+    }
+
+    if let Some(Token::Arrow(_)) = tokens.last() {
+        tokens.push(Token::ReturnType((String::from("RETURNTYPE"), current_token.to_string())));
+        current_token.clear();
+        return Ok(());
+    }
+
+    let new_token_result = current_token.parse::<i32>();
+    match new_token_result {
+        Ok(new_token) => {
+            if *making_params >= 1 {
+                tokens.push(Token::Parameters((String::from("PARAMETERS"), current_token.to_string())));
+            } else {
+                tokens.push(Token::Number((String::from("NUMBER"), new_token)));
+            }
+        },
+        Err(_) => {
+            if current_token == "=" {
+                tokens.push(Token::EqualsTo((String::from("EQUALSTO"), current_token.to_string())))
+            } else if current_token == "==" {
+                tokens.push(Token::Equality((String::from("EQUALITY"), current_token.to_string())))
+            } else if current_token == "int" {
+                tokens.push(Token::LetInt((String::from("LETINT"), current_token.to_string())))
+            } else if current_token == "string" {
+                tokens.push(Token::LetString((String::from("LetString"), current_token.to_string())))
+            } else if current_token == "bool" {
+                tokens.push(Token::LetBool((String::from("LetBool"), current_token.to_string())))
+            } else if current_token == "proc" {
+                tokens.push(Token::Function((String::from("FUNCTION"), current_token.to_string())))
+            } else if current_token == "print" {
+                tokens.push(Token::Print((String::from("PRINT"), current_token.to_string())))
+            } else if current_token == "if" {
+                tokens.push(Token::If((String::from("IF"), current_token.to_string())))
+            } else if current_token == "orif" {
+                tokens.push(Token::OrIf((String::from("ORIF"), current_token.to_string())))
+            } else if current_token == "else" {
+                tokens.push(Token::Else((String::from("ELSE"), current_token.to_string())))
+            } else if current_token.starts_with("opt<") && current_token.ends_with('>') {
+                tokens.push(Token::LetOpt((String::from("LETOPT"), current_token.to_string())))
+            } else if current_token == "some" {
+                tokens.push(Token::Some((String::from("SOME"), current_token.to_string())))
+            } else if current_token == "none" {
+                tokens.push(Token::None((String::from("NONE"), current_token.to_string())))
+            } else if current_token == "let" {
+                tokens.push(Token::Let((String::from("LET"), current_token.to_string())))
+            } else if current_token.starts_with("result<") && current_token.ends_with('>') {
+                tokens.push(Token::LetResult((String::from("LETRESULT"), current_token.to_string())))
+            } else if current_token == "ok" {
+                tokens.push(Token::Ok((String::from("OK"), current_token.to_string())))
+            } else if current_token == "fail" {
+                tokens.push(Token::Fail((String::from("FAIL"), current_token.to_string())))
+            } else if current_token == "?" {
+                tokens.push(Token::Question((String::from("QUESTION"), current_token.to_string())))
+            } else if current_token == "return" {
+                tokens.push(Token::Return((String::from("RETURN"), current_token.to_string())))
+            } else if current_token.starts_with("map<") && current_token.ends_with('>') {
+                tokens.push(Token::LetMap((String::from("LETMAP"), current_token.to_string())))
+            } else if current_token == "for" {
+                tokens.push(Token::For((String::from("FOR"), current_token.to_string())))
+            } else if current_token == "in" {
+                tokens.push(Token::In((String::from("IN"), current_token.to_string())))
+            } else if current_token == "import" {
+                tokens.push(Token::Import((String::from("IMPORT"), current_token.to_string())))
+            } else if current_token == "pub" {
+                tokens.push(Token::Pub((String::from("PUB"), current_token.to_string())))
+            } else if current_token == "const" {
+                tokens.push(Token::Const((String::from("CONST"), current_token.to_string())))
+            } else {
+                match tokens.last() {
+                    Some(Token::LetInt(_) | Token::LetString(_) | Token::LetBool(_) | Token::LetOpt(_) | Token::LetResult(_) | Token::LetMap(_) | Token::For(_)) => tokens.push(Token::VarName((String::from("VARNAME"), current_token.to_string()))),
+                    Some(Token::Function(_)) => tokens.push(Token::FuncName((String::from("FUNCNAME"), current_token.to_string()))),
+                    Some(Token::LParen(_)) => {
+                        tokens.push(Token::Parameters((String::from("PARAMETERS"), current_token.to_string())));
+                    },
+                    Some(Token::DblQuote(_)) => {
+                        if *making_string == 1 {
+                            tokens.push(Token::Strings((String::from("STRINGS"), current_token.to_string())));
+                        } else if *making_string == 2 {
+                            tokens.push(Token::Strings((String::from("STRINGS"), current_token.to_string())));
+                            *making_string = 0;
+                        }
+                    },
+                    _ => {
+                        if *current_token == "true" || *current_token == "false" {
+                            tokens.push(Token::Boolean((String::from("BOOLEAN"), *current_token == "true")));
+
+                            current_token.clear();
+                            return Ok(());
+                        }
+
+                        let mut found = false;
+
+                        // `m.get` is a method on the known name `m`
+                        let base = current_token.split('.').next().unwrap_or_default();
+
+                        for (type_class, value) in named {
+                            if base == value && type_class == "VARNAME" {
+                                tokens.push(Token::VarName((String::from("VARNAME"), current_token.to_string())));
+                                found = true;
+                                break;
+                            } else if *current_token == value && type_class == "FUNCNAME" {
+                                tokens.push(Token::FuncName((String::from("FUNCNAME"), current_token.to_string())));
+                                found = true;
+                                break;
+                            } else if base == value && type_class == "MODULE" && current_token.contains('.') {
+                                // a proc from another file keeps its module as a path, `utils::double`
+                                tokens.push(Token::FuncName((String::from("FUNCNAME"), current_token.replacen('.', "::", 1))));
+                                found = true;
+                                break;
+                            }
+                        }
+
+                        if !found {
+                            // methods need types of their own to go on
+                            let message = if base == "impl" {
+                                String::from("`impl` blocks need user-defined types, which oxide doesn't have yet")
+                            } else if base.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                                format!("unknown name `{base}`")
+                            } else {
+                                format!("unexpected `{current_token}`")
+                            };
+
+                            return Err(Diagnostic { span: Some(at), message, file: None });
+                        }
+                    },
+                }
+            }
+        },
+    };
+
+    current_token.clear();
+    return Ok(());
+}
+
+/// generic and tuple types like `result<int, string>` keep their commas and spaces
+fn making_generic(current_token: &str) -> bool {
+    if current_token.starts_with('(') {
+        return current_token.matches('(').count() > current_token.matches(')').count();
+    }
+
+    let is_generic = ["opt<", "result<", "map<"].iter().any(|prefix| current_token.starts_with(prefix));
+    return is_generic && current_token.matches('<').count() > current_token.matches('>').count();
+}
+
+/// the procs and `const`s declared at the top of the file, found before
+/// it's tokenised so they can be used anywhere in it
+fn declarations(content: &str) -> Vec<(String, String)> {
+    let mut declared = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim_start();
+        let line = line.strip_prefix("pub ").unwrap_or(line);
+
+        if let Some(rest) = line.strip_prefix("proc ") {
+            let name = rest.split('(').next().unwrap_or_default().trim();
+            declared.push((String::from("FUNCNAME"), name.to_string()));
+        } else if let Some(rest) = line.strip_prefix("const ") {
+            let name = rest.split('=').next().unwrap_or_default().split_whitespace().last().unwrap_or_default();
+            declared.push((String::from("VARNAME"), name.to_string()));
+        }
+    }
+
+    return declared;
+}
+
+/// the tokens of a whole file, or where the first thing that isn't
+/// oxide is
+pub fn tokeniser(content: String) -> Result<Vec<Token>, Diagnostic> {
+    let declared = declarations(&content);
+    let mut tokens = Vec::new();
+    let mut current_token = String::new();
+    let mut making_string: i8 = 0;
+    let mut making_params: i8 = 0;
+    let mut making_comment: bool = false;
+    let (mut line, mut column) = (1, 0);
+    let mut at = Span { line, column: 1 };
+
+    // fix single quotes
+    for c in content.chars() {
+        column += 1;
+        if current_token.is_empty() {
+            at = Span { line, column };
+        }
+
+        if c == '\n' {
+            line += 1;
+            column = 0;
+        }
+
+        if making_comment {
+            match c {
+                '\n' => {
+                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+                    tokens.push(Token::NewLine((String::from("NEWLINE"), String::from(c))));
+                },
+                _ => current_token.push(c),
+            }
+        } else if c.is_ascii_digit() || c.is_alphabetic() || (making_string > 0 && (c != '"' || c == ' ')) || (making_params >= 1 && c != ')' && c != '(') || (making_params >= 1 && c == ' ') || (making_params == 0 && making_generic(&current_token)) {
+            current_token.push(c);
+        } else if c == '+' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::Plus((String::from("PLUS"), String::from(c))));
+        } else if c == '-' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::Minus((String::from("MINUS"), String::from(c))));
+        } else if c == '*' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::Multiply((String::from("MULTIPLY"), String::from(c))));
+        } else if c == '/' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::Divide((String::from("DIVIDE"), String::from(c))));
+        } else if c == '"' {
+            making_string += 1;
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::DblQuote((String::from("DBLQUOTE"), String::from(c))));
+        } else if c == '\'' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::SglQuote((String::from("SGLQUOTE"), String::from(c))));
+        } else if c == '(' {
+            match making_params {
+                // a tuple return type, `proc f() -> (int, string)`
+                0 if matches!(tokens.last(), Some(Token::Arrow(_))) => current_token.push(c),
+                0 => {
+                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+                    making_params += 1;
+                    tokens.push(Token::LParen((String::from("LPAREN"), String::from(c))));
+                },
+                _ => {
+                    making_params += 1;
+                    current_token.push(c);
+                }
+
+            }
+        } else if c == ')' {
+            match making_params {
+                0 => {
+                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+                    tokens.push(Token::RParen((String::from("RPAREN"), String::from(c))));
+                },
+                1 => {
+                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+                    making_params -= 1;
+                    tokens.push(Token::RParen((String::from("RPAREN"), String::from(c))));
+                },
+                _ => {
+                    current_token.push(c);
+                    making_params -= 1;
+                },
+                
+            }
+        } else if c == '{' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::LSquirly((String::from("LSQUIRLY"), String::from(c))));
+        } else if c == '}' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::RSquirly((String::from("RSQUIRLY"), String::from(c))));
+        } else if c == '[' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::LSquare((String::from("LSQUARE"), String::from(c))));
+        } else if c == ']' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::RSquare((String::from("RSQUARE"), String::from(c))));
+        } else if c == ':' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::Colon((String::from("COLON"), String::from(c))));
+        } else if c == ',' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::Comma((String::from("COMMA"), String::from(c))));
+        } else if c == '\n' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::NewLine((String::from("NEWLINE"), String::from(c))));
+        } else if c == ' ' {
+            match making_params {
+                0 => {
+                    handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+                },
+                _ => current_token.push(c),
+            }
+            // tokens.push(Token::WhiteSpace((String::from("WHITESPACE"), String::from(c))));
+        } else if c == ';' {
+            handle_ending_value(&mut tokens, &mut current_token, &mut making_string, &mut making_params, &mut making_comment, at, &declared)?;
+            tokens.push(Token::Semicolon((String::from("SEMICOLON"), String::from(c))))
+        } else if c == '=' {
+            current_token.push(c);
+        } else if c == '#' {
+            current_token.push(c);
+            making_comment = true;
+        } else {
+            current_token.push(c);
+        }
+    }
+
+    return Ok(tokens);
+}
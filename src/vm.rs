@@ -0,0 +1,222 @@
+use std::io::Write;
+use crate::bytecode::{Program, Op};
+use crate::interpreter::{Value, binary, insert, render};
+
+/// a function being run, its locals start at `base` on the stack
+struct Frame {
+    function: usize,
+    ip: usize,
+    base: usize,
+}
+
+/// runs a program lowered by `bytecode::compile_file`. output goes to
+/// `stdout`, runtime errors and a failing main to `stderr`. `path` is the
+/// entry file errors are reported in. gives back the exit code the
+/// compiled program would have had
+pub fn run(program: &Program, path: &str, stdout: &mut dyn Write, stderr: &mut dyn Write) -> i32 {
+    let result = execute(program, stdout);
+    let _ = stdout.flush();
+
+    match result {
+        Ok(Value::Res(Err(value))) if program.main_fails => {
+            let _ = writeln!(stderr, "error: {}", value.display());
+            return 1;
+        },
+        Ok(_) => return 0,
+        Err((message, function, ip)) => {
+            let function = &program.functions[function];
+            let file = function.file.as_deref().unwrap_or(path);
+            let span = function.spans[ip];
+            let _ = writeln!(stderr, "error: {file}:{}:{}: {message}", span.line, span.column);
+
+            // what a rust panic exits with
+            return 101;
+        },
+    }
+}
+
+/// what the entry function returned, or an error with the function and
+/// instruction it happened at
+fn execute(program: &Program, stdout: &mut dyn Write) -> Result<Value, (String, usize, usize)> {
+    let mut stack: Vec<Value> = Vec::new();
+    let mut frames = vec![Frame { function: program.entry as usize, ip: 0, base: 0 }];
+    stack.resize(program.functions[program.entry as usize].locals as usize, Value::Unit);
+
+    loop {
+        let frame = frames.last_mut().unwrap();
+        let (function, at) = (frame.function, frame.ip);
+        let op = program.functions[function].code[at];
+        frame.ip += 1;
+
+        let failed = |message: String| (message, function, at);
+        let pop = |stack: &mut Vec<Value>| stack.pop().unwrap_or(Value::Unit);
+        let base = frame.base;
+
+        match op {
+            Op::Const(index) => stack.push(program.constants[index as usize].clone()),
+            Op::Load(slot) => stack.push(stack[base + slot as usize].clone()),
+            Op::Store(slot) => stack[base + slot as usize] = pop(&mut stack),
+            Op::Pop => {
+                stack.pop();
+            },
+            Op::Binary(op) => {
+                let right = pop(&mut stack);
+                let left = pop(&mut stack);
+                stack.push(binary(op.symbol(), left, right).map_err(failed)?);
+            },
+            Op::Neg => match pop(&mut stack) {
+                Value::Int(value) => match value.checked_neg() {
+                    Some(value) => stack.push(Value::Int(value)),
+                    None => return Err(failed(String::from("attempt to negate with overflow"))),
+                },
+                _ => return Err(failed(String::from("`-` doesn't work on that"))),
+            },
+            Op::Not => match pop(&mut stack) {
+                Value::Bool(value) => stack.push(Value::Bool(!value)),
+                Value::Int(value) => stack.push(Value::Int(!value)),
+                _ => return Err(failed(String::from("`!` doesn't work on that"))),
+            },
+            Op::Jump(target) => frame.ip = target as usize,
+            Op::JumpIfFalse(target) => match pop(&mut stack) {
+                Value::Bool(true) => (),
+                Value::Bool(false) => frame.ip = target as usize,
+                other => return Err(failed(format!("{} isn't a bool", other.debug()))),
+            },
+            Op::Call(target, count) => {
+                let callee = &program.functions[target as usize];
+                let base = stack.len() - count as usize;
+                stack.resize(base + callee.locals as usize, Value::Unit);
+                frames.push(Frame { function: target as usize, ip: 0, base });
+            },
+            Op::Return => {
+                let value = pop(&mut stack);
+                frames.pop();
+
+                if frames.is_empty() {
+                    return Ok(value);
+                }
+
+                stack.truncate(base);
+                stack.push(value);
+            },
+            Op::Some => {
+                let value = pop(&mut stack);
+                stack.push(Value::Opt(Some(Box::new(value))));
+            },
+            Op::Ok => {
+                let value = pop(&mut stack);
+                stack.push(Value::Res(Ok(Box::new(value))));
+            },
+            Op::Fail => {
+                let value = pop(&mut stack);
+                stack.push(Value::Res(Err(Box::new(value))));
+            },
+            Op::Tuple(count) => {
+                let values = stack.split_off(stack.len() - count as usize);
+                stack.push(Value::Tuple(values));
+            },
+            Op::Map(count) => {
+                let values = stack.split_off(stack.len() - 2 * count as usize);
+                let mut map = Vec::new();
+
+                for pair in values.chunks(2) {
+                    insert(&mut map, pair[0].clone(), pair[1].clone());
+                }
+
+                stack.push(Value::Map(map));
+            },
+            Op::Field(index) => match pop(&mut stack) {
+                Value::Tuple(mut values) if (index as usize) < values.len() => stack.push(values.swap_remove(index as usize)),
+                _ => return Err(failed(format!("there's no field `{index}`"))),
+            },
+            Op::Get | Op::Contains => {
+                let wanted = pop(&mut stack);
+                let Value::Map(map) = pop(&mut stack) else {
+                    return Err(failed(String::from("only maps have `get` and `contains`")));
+                };
+
+                let found = map.into_iter().find(|(key, _)| *key == wanted);
+                stack.push(match op {
+                    Op::Get => Value::Opt(found.map(|(_, value)| Box::new(value))),
+                    _ => Value::Bool(found.is_some()),
+                });
+            },
+            Op::Keys | Op::Values => {
+                let Value::Map(map) = pop(&mut stack) else {
+                    return Err(failed(String::from("only maps have `keys` and `values`")));
+                };
+
+                let keys = op == Op::Keys;
+                stack.push(Value::Vector(map.into_iter().map(|(key, value)| if keys { key } else { value }).collect()));
+            },
+            Op::Insert(slot) => {
+                let value = pop(&mut stack);
+                let key = pop(&mut stack);
+                let Value::Map(map) = &mut stack[base + slot as usize] else {
+                    return Err(failed(String::from("only maps have `insert`")));
+                };
+
+                let old = insert(map, key, value);
+                stack.push(Value::Opt(old.map(Box::new)));
+            },
+            Op::Remove(slot) => {
+                let key = pop(&mut stack);
+                let Value::Map(map) = &mut stack[base + slot as usize] else {
+                    return Err(failed(String::from("only maps have `remove`")));
+                };
+
+                let position = map.iter().position(|(existing, _)| *existing == key);
+                let old = position.map(|position| Box::new(map.remove(position).1));
+                stack.push(Value::Opt(old));
+            },
+            Op::Try => match pop(&mut stack) {
+                Value::Res(Ok(value)) => stack.push(*value),
+                Value::Res(Err(value)) => {
+                    // the same as `return fail(value)`
+                    frames.pop();
+
+                    if frames.is_empty() {
+                        return Ok(Value::Res(Err(value)));
+                    }
+
+                    stack.truncate(base);
+                    stack.push(Value::Res(Err(value)));
+                },
+                _ => return Err(failed(String::from("`?` only works on a `result`"))),
+            },
+            Op::MatchSome(target) => match pop(&mut stack) {
+                Value::Opt(Some(value)) => stack.push(*value),
+                Value::Opt(None) => frame.ip = target as usize,
+                _ => return Err(failed(String::from("`if let` only works on an `opt`"))),
+            },
+            Op::Items => match pop(&mut stack) {
+                Value::Map(map) => stack.push(Value::Vector(map.into_iter().map(|(key, value)| Value::Tuple(vec![key, value])).collect())),
+                Value::Vector(values) => stack.push(Value::Vector(values)),
+                other => return Err(failed(format!("{} can't be looped over", other.debug()))),
+            },
+            Op::Len => match pop(&mut stack) {
+                Value::Vector(values) => stack.push(Value::Int(values.len() as i32)),
+                other => return Err(failed(format!("{} has no length", other.debug()))),
+            },
+            Op::Index => {
+                let index = pop(&mut stack);
+                match (pop(&mut stack), index) {
+                    (Value::Vector(mut values), Value::Int(index)) if (index as usize) < values.len() => stack.push(values.swap_remove(index as usize)),
+                    _ => return Err(failed(String::from("the loop lost its place"))),
+                }
+            },
+            Op::Unpack(count) => match pop(&mut stack) {
+                Value::Tuple(values) if values.len() == count as usize => stack.extend(values),
+                other => return Err(failed(format!("{} can't be split into {count}", other.debug()))),
+            },
+            Op::Print(format, count) => {
+                let values = stack.split_off(stack.len() - count as usize);
+                let line = render(&program.formats[format as usize], &values).map_err(failed)?;
+
+                if writeln!(stdout, "{line}").is_err() {
+                    return Err(failed(String::from("couldn't write to stdout")));
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use crate::backend::{Backend, Linked};
+use crate::checker::oxide_type;
+use crate::emitter::Emitter;
+use crate::generator::Unit;
+use crate::expression::{self, BinaryOp, parse_expression};
+use crate::ir::{Body, Cond, Expr, Function, Stmt, Var};
+use crate::parser::{Parsed, Span, FunctionCall};
+use crate::sourcemap::tag_markers;
+
+/// the helpers every generated module starts with, see `wat_runtime.wat`
+const RUNTIME: &str = include_str!("wat_runtime.wat");
+
+/// what `--target=wat` can't compile yet. every value has to be an `int`
+/// or a `bool`, both of which are an i32 in webassembly
+pub(crate) fn unsupported(parsed: &[(Span, Parsed)]) -> Vec<(Span, String)> {
+    let mut errors = Vec::new();
+    let mut error = |span: Span, message: String| errors.push((span, format!("{message}, --target=wat only supports `int` and `bool` so far")));
+
+    for (span, elem) in parsed {
+        let mut types = Vec::new();
+        let mut values = Vec::new();
+
+        match elem {
+            Parsed::VariableDeclare(declare) | Parsed::Const(declare) => {
+                types.push(declare.type_class.clone());
+                values.push(declare.value.clone());
+            },
+            Parsed::FunctionDeclare(declare) => {
+                types.extend(declare.params().into_iter().map(|(_, type_class)| type_class));
+                if !declare.return_type.is_empty() {
+                    types.push(declare.return_type.clone());
+                }
+            },
+            Parsed::FunctionCall(FunctionCall { name, parameters, propagate }) => {
+                if *propagate {
+                    error(*span, String::from("`?` needs a `result`"));
+                }
+
+                values.push(format!("{name}({parameters})"));
+            },
+            Parsed::Return(value) if !value.trim().is_empty() => values.push(value.clone()),
+            Parsed::If(value) | Parsed::OrIf(value) => values.push(value.clone()),
+            Parsed::Destructure(_) => error(*span, String::from("tuples can't be split up")),
+            Parsed::IfLet(_) => error(*span, String::from("`if let` needs an `opt`")),
+            Parsed::For(_) => error(*span, String::from("`for` loops go over maps and vectors")),
+            Parsed::Print(_) => error(*span, String::from("`print` needs strings")),
+            _ => (),
+        }
+
+        for type_class in types {
+            if type_class != "i32" && type_class != "bool" {
+                error(*span, format!("`{}` values aren't supported", oxide_type(&type_class)));
+            }
+        }
+
+        for value in values {
+            match parse_expression(&value) {
+                Some(expr) => {
+                    if let Some(message) = unsupported_expr(&expr) {
+                        error(*span, message);
+                    }
+                },
+                None => error(*span, format!("can't make sense of `{value}`")),
+            }
+        }
+    }
+
+    return errors;
+}
+
+fn unsupported_expr(expr: &expression::Expr) -> Option<String> {
+    use expression::Expr;
+
+    let first = |exprs: &[&Expr]| exprs.iter().find_map(|expr| unsupported_expr(expr));
+
+    return match expr {
+        Expr::Int(_) | Expr::Bool(_) => None,
+        Expr::Str(_) => Some(String::from("strings aren't supported")),
+        Expr::Name(name) if name == "none" => Some(String::from("`opt` values aren't supported")),
+        Expr::Name(_) => None,
+        Expr::Tuple(_) => Some(String::from("tuples aren't supported")),
+        Expr::Map(_) | Expr::Method(..) => Some(String::from("maps aren't supported")),
+        Expr::Field(..) => Some(String::from("tuples aren't supported")),
+        Expr::Question(_) => Some(String::from("`?` needs a `result`")),
+        Expr::Call(name, _) if name == "some" => Some(String::from("`opt` values aren't supported")),
+        Expr::Call(name, _) if name == "ok" || name == "fail" => Some(String::from("`result` values aren't supported")),
+        Expr::Call(_, args) => first(&args.iter().collect::<Vec<&Expr>>()),
+        Expr::Unary(_, inner) => unsupported_expr(inner),
+        Expr::Binary(_, left, right) => first(&[left, right]),
+    };
+}
+
+/// writes webassembly text. every proc takes and gives back i32s, a proc
+/// without a return type gives back 0. runtime errors trap, there's no
+/// way to say where from
+pub(crate) struct Wat {
+    unit: Unit,
+    /// `math::` in a file imported as `math`
+    prefix: String,
+    has_main: bool,
+    /// the labels of the loops being written, innermost last
+    loops: Vec<usize>,
+    labels: usize,
+}
+
+impl Wat {
+    pub(crate) fn new(unit: Unit, module: Option<&str>) -> Wat {
+        let prefix = match module {
+            Some(module) => format!("{module}::"),
+            None => String::new(),
+        };
+
+        return Wat { unit, prefix, has_main: false, loops: Vec::new(), labels: 0 };
+    }
+
+    /// the name of a proc in the module, `math::double` stays as it is
+    fn proc_name(&self, name: &str) -> String {
+        if name.contains("::") {
+            let mut parts: Vec<&str> = name.rsplit("::").take(2).collect();
+            parts.reverse();
+            return format!("${}", parts.join("::"));
+        }
+
+        return format!("${}{name}", self.prefix);
+    }
+
+    /// pushes the instructions for an expression
+    fn expr(&self, expr: &Expr, code: &mut Vec<String>) {
+        match expr {
+            Expr::Int(value) => code.push(format!("i32.const {value}")),
+            Expr::Bool(value) => code.push(format!("i32.const {}", *value as u8)),
+            Expr::Local(var) => code.push(format!("local.get {}", name(var))),
+            Expr::Const(_, value) => self.expr(value, code),
+            Expr::Call(callee, args, _) => {
+                for arg in args {
+                    self.expr(arg, code);
+                }
+
+                code.push(format!("call {}", self.proc_name(callee)));
+            },
+            Expr::Neg(inner) => {
+                code.push(String::from("i32.const 0"));
+                self.expr(inner, code);
+                code.push(String::from("call $ox:sub"));
+            },
+            Expr::Not(inner) => {
+                self.expr(inner, code);
+                if inner.type_class() == "bool" {
+                    code.push(String::from("i32.eqz"));
+                } else {
+                    code.push(String::from("i32.const -1"));
+                    code.push(String::from("i32.xor"));
+                }
+            },
+            // only look at the right when the left doesn't decide it
+            Expr::And(left, right) => {
+                self.expr(left, code);
+                code.push(String::from("if (result i32)"));
+                self.expr(right, code);
+                code.push(String::from("else"));
+                code.push(String::from("i32.const 0"));
+                code.push(String::from("end"));
+            },
+            Expr::Or(left, right) => {
+                self.expr(left, code);
+                code.push(String::from("if (result i32)"));
+                code.push(String::from("i32.const 1"));
+                code.push(String::from("else"));
+                self.expr(right, code);
+                code.push(String::from("end"));
+            },
+            Expr::Binary(op, left, right) => {
+                self.expr(left, code);
+                self.expr(right, code);
+
+                let instruction = match op {
+                    BinaryOp::Add => "call $ox:add",
+                    BinaryOp::Sub => "call $ox:sub",
+                    BinaryOp::Mul => "call $ox:mul",
+                    BinaryOp::Div => "i32.div_s",
+                    BinaryOp::Rem => "call $ox:rem",
+                    BinaryOp::Eq => "i32.eq",
+                    BinaryOp::Ne => "i32.ne",
+                    BinaryOp::Lt => "i32.lt_s",
+                    BinaryOp::Gt => "i32.gt_s",
+                    BinaryOp::Le => "i32.le_s",
+                    BinaryOp::Ge => "i32.ge_s",
+                };
+
+                code.push(instruction.to_string());
+            },
+            // `unsupported` has already turned these away
+            _ => code.push(String::from("unreachable")),
+        }
+    }
+
+    /// the instructions for an expression, `&&` and `||` have an `if` of
+    /// their own, indented like any other
+    fn value(&self, out: &mut Emitter, value: &Expr) {
+        let mut code = Vec::new();
+        self.expr(value, &mut code);
+
+        for line in code {
+            match line.as_str() {
+                "else" => out.reopen("else"),
+                "end" => out.close_with("end"),
+                _ if line.starts_with("if") => out.open(&line),
+                _ => out.line(&line),
+            }
+        }
+    }
+
+    fn statements(&mut self, out: &mut Emitter, body: &Body) {
+        for (span, stmt) in body {
+            out.at(Some(*span));
+            self.statement(out, stmt);
+        }
+    }
+
+    fn statement(&mut self, out: &mut Emitter, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let(var, value) | Stmt::Assign(var, value) => {
+                self.value(out, value);
+                out.line(&format!("local.set {}", name(var)));
+            },
+            Stmt::Expr(value) => {
+                self.value(out, value);
+                out.line("drop");
+            },
+            Stmt::Return(value) => {
+                match value {
+                    Some(value) => self.value(out, value),
+                    None => out.line("i32.const 0"),
+                }
+
+                out.line("return");
+            },
+            Stmt::If(Cond::Bool(condition), then, otherwise, _) => {
+                self.value(out, condition);
+                out.open("if");
+                self.statements(out, then);
+
+                if !otherwise.is_empty() {
+                    out.reopen("else");
+                    self.statements(out, otherwise);
+                }
+
+                out.close_with("end");
+            },
+            // runs until a `br` to the block around it
+            Stmt::Loop(body) => {
+                self.labels += 1;
+                let label = self.labels;
+
+                out.open(&format!("block $break_{label}"));
+                out.open(&format!("loop $loop_{label}"));
+                self.loops.push(label);
+                self.statements(out, body);
+                self.loops.pop();
+                out.line(&format!("br $loop_{label}"));
+                out.close_with("end");
+                out.close_with("end");
+            },
+            Stmt::Break => match self.loops.last() {
+                Some(label) => out.line(&format!("br $break_{label}")),
+                None => out.line("unreachable"),
+            },
+            // every variable in a proc already has a local of its own
+            Stmt::Block(body) => self.statements(out, body),
+            // `unsupported` has already turned these away
+            Stmt::Unpack(..) | Stmt::Print(..) | Stmt::If(Cond::Some(..), ..) => out.line("unreachable"),
+        }
+    }
+
+    /// the `(func ...` with its locals and body. a function's locals all
+    /// have to be declared before its body
+    fn function_body(&mut self, out: &mut Emitter, header: &str, body: &Body, last: &str) {
+        let mut locals = Vec::new();
+        declared(body, &mut locals);
+
+        out.open(header);
+        for local in &locals {
+            out.line(&format!("(local {local} i32)"));
+        }
+
+        self.statements(out, body);
+        out.at(None);
+        out.line(last);
+        out.close_with(")");
+    }
+}
+
+impl Backend for Wat {
+    fn function(&mut self, out: &mut Emitter, function: &Function) {
+        let mut header = format!("(func {}", self.proc_name(&function.name));
+
+        if function.name == "main" && self.unit == Unit::Program {
+            self.has_main = true;
+        }
+
+        // the entry file's `pub proc`s and main can be called by the host
+        let export = match self.unit {
+            Unit::Program => function.public || function.name == "main",
+            Unit::Include => true,
+            Unit::Import => false,
+        };
+
+        if export {
+            header.push_str(&format!(" (export \"{}\")", function.name));
+        }
+
+        for param in &function.params {
+            header.push_str(&format!(" (param {} i32)", name(param)));
+        }
+
+        header.push_str(" (result i32)");
+
+        // every path has already returned when there's a return type
+        let last = if function.returns.is_empty() { "i32.const 0" } else { "unreachable" };
+        self.function_body(out, &header, &function.body, last);
+    }
+
+    fn finish(&mut self, mut module: Emitter, top_level: &Body) -> String {
+        // statements outside any proc run from an exported main
+        if self.unit == Unit::Program && !self.has_main && !top_level.is_empty() {
+            module.blank_line();
+            self.function_body(&mut module, "(func $main (export \"main\") (result i32)", top_level, "i32.const 0");
+        }
+
+        return module.finish();
+    }
+
+    /// one module with the runtime, then the imported files, then the entry file
+    fn link(&self, entry: String, _: &str, imports: Vec<Linked>) -> String {
+        let mut body = String::new();
+
+        for import in imports {
+            body.push('\n');
+            body.push_str(&tag_markers(&import.source, &import.path));
+        }
+
+        body.push('\n');
+        body.push_str(&entry);
+
+        let mut output = format!("(module\n{RUNTIME}");
+        for line in body.lines() {
+            if !line.is_empty() {
+                output.push_str("    ");
+                output.push_str(line);
+            }
+
+            output.push('\n');
+        }
+        output.push_str(")\n");
+
+        return output;
+    }
+}
+
+/// temps the lowering made up are `$ox:`, like the runtime's helpers
+fn name(var: &Var) -> String {
+    return if var.temp { format!("$ox:{}", var.unique) } else { format!("${}", var.unique) };
+}
+
+/// the locals a body declares, each variable has a name of its own in
+/// the proc so they can all live as long as the function
+fn declared(body: &Body, locals: &mut Vec<String>) {
+    for (_, stmt) in body {
+        match stmt {
+            Stmt::Let(var, _) => locals.push(name(var)),
+            Stmt::Unpack(vars, _) => locals.extend(vars.iter().map(name)),
+            Stmt::If(Cond::Some(var, _), ..) => locals.push(name(var)),
+            _ => (),
+        }
+
+        for inner in stmt.bodies() {
+            declared(inner, locals);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+    ;; the oxide runtime for generated webassembly text. arithmetic traps
+    ;; where a rust debug build would panic, division already traps on
+    ;; zero and on overflow by itself
+
+    ;; traps unless the result fits in an i32
+    (func $ox:i32 (param $value i64) (result i32)
+        local.get $value
+        local.get $value
+        i32.wrap_i64
+        i64.extend_i32_s
+        i64.ne
+        if
+            unreachable
+        end
+        local.get $value
+        i32.wrap_i64
+    )
+
+    (func $ox:add (param $a i32) (param $b i32) (result i32)
+        local.get $a
+        i64.extend_i32_s
+        local.get $b
+        i64.extend_i32_s
+        i64.add
+        call $ox:i32
+    )
+
+    (func $ox:sub (param $a i32) (param $b i32) (result i32)
+        local.get $a
+        i64.extend_i32_s
+        local.get $b
+        i64.extend_i32_s
+        i64.sub
+        call $ox:i32
+    )
+
+    (func $ox:mul (param $a i32) (param $b i32) (result i32)
+        local.get $a
+        i64.extend_i32_s
+        local.get $b
+        i64.extend_i32_s
+        i64.mul
+        call $ox:i32
+    )
+
+    ;; `i32.rem_s` gives 0 for the minimum over -1 where rust panics
+    (func $ox:rem (param $a i32) (param $b i32) (result i32)
+        local.get $a
+        i32.const -2147483648
+        i32.eq
+        local.get $b
+        i32.const -1
+        i32.eq
+        i32.and
+        if
+            unreachable
+        end
+        local.get $a
+        local.get $b
+        i32.rem_s
+    )
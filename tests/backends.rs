@@ -0,0 +1,933 @@
+//! every program in tests/programs is compiled through rustc, then run
+//! by the interpreter, by the bytecode vm, compiled as c and run as
+//! javascript under node, and has to print the same thing and exit the
+//! same way every time. the c tests are skipped without a c compiler and
+//! the javascript tests without node.
+//! webassembly text can't print, so it's only checked for its shape
+
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use oxide::{Options, Target};
+
+fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let mut programs: Vec<PathBuf> = fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "oxi"))
+        .collect();
+
+    programs.sort();
+    return programs;
+}
+
+/// stdout and the exit code of the program built with rustc, or cc for
+/// c, or run by node for javascript
+fn compiled(path: &Path, target: Target) -> (String, i32) {
+    return built(path, Options { ordered_maps: true, target, ..Options::default() });
+}
+
+fn built(path: &Path, options: Options) -> (String, i32) {
+    let source = fs::read_to_string(path).unwrap();
+    let target = options.target;
+    let output = oxide::compile_file(&path.to_string_lossy(), &source, &options)
+        .unwrap_or_else(|errors| panic!("{} didn't compile: {errors:?}", path.display()));
+
+    let mut name = path.file_stem().unwrap().to_string_lossy().to_string();
+    if options.optimise {
+        name.push_str("-optimised");
+    }
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("backends");
+    fs::create_dir_all(&dir).unwrap();
+
+    let (compiler, args, extension) = match target {
+        Target::Rust => ("rustc", vec!["--edition", "2021", "-A", "warnings", "-o"], "rs"),
+        Target::C => ("cc", vec!["-std=c99", "-o"], "c"),
+        Target::Js => ("node", Vec::new(), "mjs"),
+        Target::Wat => panic!("webassembly text isn't run, it has no runtime to print with"),
+    };
+
+    let source_path = dir.join(format!("{name}.{extension}"));
+    let binary = dir.join(format!("{name}-{extension}"));
+    fs::write(&source_path, output.code).unwrap();
+
+    if target == Target::Js {
+        let run = Command::new("node").arg(&source_path).output().unwrap();
+        return (String::from_utf8_lossy(&run.stdout).to_string(), run.status.code().unwrap_or(-1));
+    }
+
+    let built = Command::new(compiler)
+        .args(args)
+        .arg(&binary)
+        .arg(&source_path)
+        .output()
+        .unwrap();
+
+    assert!(built.status.success(), "{compiler} failed on {}:\n{}", path.display(), String::from_utf8_lossy(&built.stderr));
+
+    let run = Command::new(&binary).output().unwrap();
+    return (String::from_utf8_lossy(&run.stdout).to_string(), run.status.code().unwrap_or(-1));
+}
+
+/// stdout and the exit code of the program run by the interpreter
+fn interpreted(path: &Path) -> (String, i32) {
+    let source = fs::read_to_string(path).unwrap();
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let code = oxide::interpret_file(&path.to_string_lossy(), &source, &mut stdout, &mut stderr)
+        .unwrap_or_else(|errors| panic!("{} didn't load: {errors:?}", path.display()));
+
+    return (String::from_utf8_lossy(&stdout).to_string(), code);
+}
+
+/// stdout and the exit code of the program run on the vm
+fn vm(path: &Path) -> (String, i32) {
+    let source = fs::read_to_string(path).unwrap();
+    let program = oxide::bytecode::compile_file(&path.to_string_lossy(), &source)
+        .unwrap_or_else(|errors| panic!("{} didn't lower to bytecode: {errors:?}", path.display()));
+
+    let mut stdout = Vec::new();
+    let code = oxide::vm::run(&program, &path.to_string_lossy(), &mut stdout, &mut Vec::new());
+    return (String::from_utf8_lossy(&stdout).to_string(), code);
+}
+
+#[test]
+fn interpreter_and_vm_match_rustc() {
+    let programs = programs();
+    assert!(!programs.is_empty());
+
+    for path in programs {
+        let compiled = compiled(&path, Target::Rust);
+        assert_eq!(interpreted(&path), compiled, "{} ran differently in the interpreter", path.display());
+        assert_eq!(vm(&path), compiled, "{} ran differently on the vm", path.display());
+    }
+}
+
+fn has_cc() -> bool {
+    return Command::new("cc").arg("--version").output().is_ok_and(|output| output.status.success());
+}
+
+#[test]
+fn c_matches_rustc() {
+    if !has_cc() {
+        eprintln!("skipping, there's no c compiler");
+        return;
+    }
+
+    for path in programs() {
+        assert_eq!(compiled(&path, Target::C), compiled(&path, Target::Rust), "{} ran differently as c", path.display());
+    }
+}
+
+#[test]
+fn optimised_c_matches_rustc() {
+    if !has_cc() {
+        eprintln!("skipping, there's no c compiler");
+        return;
+    }
+
+    for path in programs() {
+        let optimised = built(&path, Options { ordered_maps: true, target: Target::C, optimise: true, ..Options::default() });
+        assert_eq!(optimised, compiled(&path, Target::Rust), "{} ran differently with -O", path.display());
+    }
+}
+
+fn has_node() -> bool {
+    return Command::new("node").arg("--version").output().is_ok_and(|output| output.status.success());
+}
+
+#[test]
+fn js_matches_rustc() {
+    if !has_node() {
+        eprintln!("skipping, node isn't installed");
+        return;
+    }
+
+    for path in programs() {
+        assert_eq!(compiled(&path, Target::Js), compiled(&path, Target::Rust), "{} ran differently as javascript", path.display());
+    }
+}
+
+#[test]
+fn runtime_errors_point_at_the_source() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs/divide_by_zero.oxi");
+    let source = fs::read_to_string(&path).unwrap();
+    let mut stderr = Vec::new();
+    let code = oxide::interpret_file("divide_by_zero.oxi", &source, &mut Vec::new(), &mut stderr).unwrap();
+
+    assert_eq!(code, 101);
+    assert_eq!(String::from_utf8_lossy(&stderr), "error: divide_by_zero.oxi:2:5: attempt to divide by zero\n");
+
+    let program = oxide::bytecode::compile_file("divide_by_zero.oxi", &source).unwrap();
+    let mut stderr = Vec::new();
+    let code = oxide::vm::run(&program, "divide_by_zero.oxi", &mut Vec::new(), &mut stderr);
+
+    assert_eq!(code, 101);
+    assert_eq!(String::from_utf8_lossy(&stderr), "error: divide_by_zero.oxi:2:5: attempt to divide by zero\n");
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("backends");
+    fs::create_dir_all(&dir).unwrap();
+
+    if has_cc() {
+        let options = Options { target: Target::C, ..Options::default() };
+        let output = oxide::compile_file("divide_by_zero.oxi", &source, &options).unwrap();
+        fs::write(dir.join("runtime_error.c"), output.code).unwrap();
+
+        let built = Command::new("cc")
+            .args(["-std=c99", "-o"])
+            .arg(dir.join("runtime_error"))
+            .arg(dir.join("runtime_error.c"))
+            .status()
+            .unwrap();
+
+        assert!(built.success());
+
+        let run = Command::new(dir.join("runtime_error")).output().unwrap();
+        assert_eq!(run.status.code(), Some(101));
+        assert_eq!(String::from_utf8_lossy(&run.stderr), "error: divide_by_zero.oxi:2:5: attempt to divide by zero\n");
+    }
+
+    if !has_node() {
+        return;
+    }
+
+    let options = Options { target: Target::Js, ..Options::default() };
+    let output = oxide::compile_file("divide_by_zero.oxi", &source, &options).unwrap();
+    fs::write(dir.join("runtime_error.mjs"), output.code).unwrap();
+
+    let run = Command::new("node").arg(dir.join("runtime_error.mjs")).output().unwrap();
+    assert_eq!(run.status.code(), Some(101));
+    assert_eq!(String::from_utf8_lossy(&run.stderr), "error: divide_by_zero.oxi:2:5: attempt to divide by zero\n");
+}
+
+#[test]
+fn js_main_only_runs_as_the_entry() {
+    if !has_node() {
+        eprintln!("skipping, node isn't installed");
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("oxide-js-entry-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let options = Options { target: Target::Js, ..Options::default() };
+    let output = oxide::compile_file("hello.oxi", "print(\"hello\");\n", &options).unwrap();
+    fs::write(dir.join("hello.mjs"), output.code).unwrap();
+    fs::write(dir.join("importer.mjs"), "import { main } from \"./hello.mjs\";\nconsole.log(\"imported\");\nmain();\n").unwrap();
+
+    let run = Command::new("node").arg(dir.join("hello.mjs")).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "hello\n");
+
+    let run = Command::new("node").arg(dir.join("importer.mjs")).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "imported\nhello\n");
+}
+
+#[test]
+fn wat_exports_pub_procs() {
+    let source = "proc twice(int n) -> int {\n    return n * 2;\n}\n\npub proc quad(int n) -> int {\n    return twice(twice(n));\n}\n";
+    let options = Options { target: Target::Wat, ..Options::default() };
+    let output = oxide::compile_file("quad.oxi", source, &options).unwrap();
+
+    assert!(output.code.starts_with("(module\n"));
+    assert!(output.code.contains("(func $twice (param $n i32) (result i32)\n"));
+    assert!(output.code.contains("(func $quad (export \"quad\") (param $n i32) (result i32)\n"));
+    assert!(output.code.contains("call $ox:mul\n"));
+}
+
+#[test]
+fn wat_rejects_strings() {
+    let source = "proc main() {\n    string s = \"hi\";\n}\n";
+    let options = Options { target: Target::Wat, ..Options::default() };
+    let errors = oxide::compile_file("strings.oxi", source, &options).unwrap_err();
+
+    assert!(!errors.is_empty());
+    assert!(errors.iter().all(|error| error.message.ends_with("--target=wat only supports `int` and `bool` so far")));
+    assert_eq!(errors[0].render("strings.oxi"), "error: strings.oxi:2:5: `string` values aren't supported, --target=wat only supports `int` and `bool` so far");
+}
+
+#[test]
+fn ir_lowers_loops_and_chains() {
+    let source = "proc sum(map<string, int> m) -> int {\n    for (k, v) in m {\n        print(\"{k}\");\n    }\n    if (true) {\n        return 1;\n    } orif (false) {\n        return 2;\n    }\n    return 0;\n}\n";
+    let modules = oxide::ir::lower_file("sum.oxi", source, &Options::default()).unwrap();
+    let printed = oxide::ir::print(&modules);
+
+    assert!(printed.contains("let %items_1: Vec<(string, int)> = items(m)\n"));
+    assert!(printed.contains("if !(%index_2 < len(%items_1))\n"));
+    assert!(printed.contains("%index_2 = %index_2 + 1\n"));
+    assert!(printed.contains("print(\"{0}\", k)\n"));
+    assert!(printed.contains("else\n    7:5          if false\n"));
+}
+
+#[test]
+fn optimiser_folds_and_prunes() {
+    let source = "proc hours() -> int {\n    int seconds = 2 * 60 * 60;\n    if (false) {\n        return 0;\n    } orif (seconds > 60) {\n        return seconds / 3600;\n    }\n    print(\"never\");\n    return 1;\n}\n";
+    let options = Options { optimise: true, ..Options::default() };
+    let modules = oxide::ir::lower_file("hours.oxi", source, &options).unwrap();
+
+    assert_eq!(oxide::ir::print(&modules), "; hours.oxi\n\nproc hours() -> int\n    6:9      return 2\n");
+}
+
+#[test]
+fn optimiser_rejects_division_by_zero() {
+    let source = "int zero = 0;\nint x = 10 / zero;\n";
+    let options = Options { optimise: true, ..Options::default() };
+    let errors = oxide::compile_file("zero.oxi", source, &options).unwrap_err();
+
+    assert_eq!(errors[0].render("zero.oxi"), "error: zero.oxi:2:1: `10 / 0` always fails: attempt to divide by zero");
+    assert!(oxide::compile_file("zero.oxi", source, &Options::default()).is_ok());
+}
+
+#[test]
+fn consts_are_rust_consts() {
+    let source = "const int LIMIT = 10;\nconst int AREA = LIMIT * LIMIT;\n\nproc area() -> int {\n    return AREA;\n}\n";
+    let output = oxide::compile_file("area.oxi", source, &Options::default()).unwrap();
+
+    assert!(output.code.starts_with("const LIMIT: i32 = 10;\nconst AREA: i32 = LIMIT * LIMIT;\n\nfn area() -> i32 {\n"));
+
+    let source = "proc limit() -> int {\n    return 10;\n}\n\nconst int LIMIT = limit();\n";
+    let errors = oxide::compile_file("limit.oxi", source, &Options::default()).unwrap_err();
+
+    assert_eq!(errors[0].render("limit.oxi"), "error: limit.oxi:5:1: `LIMIT` needs a value that's known before the program runs: only literals, operators and other `const`s can be used");
+}
+
+#[test]
+fn lint_reports_and_allows() {
+    let source = "proc twice(int n) -> int {\n    return 2;\n    print(\"never\");\n}\n\nint x = 1;\nif (1 < 2) {\n}\n# allow(unused_variable)\nint spare = twice(x);\nint other = 3; # allow(nothing)\n";
+    let warnings = oxide::lint_file("lint.oxi", source, &Options::default()).unwrap();
+    let rendered: Vec<String> = warnings.iter().map(|warning| warning.render("lint.oxi")).collect();
+
+    assert_eq!(rendered, [
+        "warning: lint.oxi:1:1: the parameter `n` is never used [unused_param]",
+        "warning: lint.oxi:3:5: this never runs, the code before it always returns [unreachable]",
+        "warning: lint.oxi:7:1: this `if` is empty [empty_if]",
+        "warning: lint.oxi:7:1: this condition is always true [constant_condition]",
+        "warning: lint.oxi:11:1: `other` is never used [unused_variable]",
+        "warning: lint.oxi:11:16: there's no lint called `nothing` [unknown_lint]",
+    ]);
+}
+
+#[test]
+fn syntax_errors_are_diagnostics() {
+    let source = "proc main() {\n    int x = nope;\n}\n";
+    let errors = oxide::compile_file("nope.oxi", source, &Options::default()).unwrap_err();
+    assert_eq!(errors[0].render("nope.oxi"), "error: nope.oxi:2:13: unknown name `nope`");
+
+    let errors = oxide::compile_file("let.oxi", "let (a, b);\n", &Options::default()).unwrap_err();
+    assert_eq!(errors[0].render("let.oxi"), "error: let.oxi:1:1: `let` needs names and a tuple, like `let (a, b) = pair;`");
+}
+
+#[test]
+fn impl_blocks_are_rejected() {
+    let source = "impl Point {\n    proc len(self) -> int {\n        return 0;\n    }\n}\n";
+    let errors = oxide::compile(source, &Options::default()).unwrap_err();
+    assert_eq!(errors[0].to_string(), "1:1: `impl` blocks need user-defined types, which oxide doesn't have yet");
+
+    // it's still fine as a name
+    assert!(oxide::compile("int impl = 3;\nprint(\"{}\", impl);\n", &Options::default()).is_ok());
+}
+
+#[test]
+fn build_script_transpiles_a_directory() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("build-script");
+    let _ = fs::remove_dir_all(&dir);
+    let out_dir = dir.join("out");
+    fs::create_dir_all(dir.join("src/nested")).unwrap();
+    fs::write(dir.join("src/nested/good.oxi"), "proc twice(int n) -> int {\n    return n * 2;\n}\n").unwrap();
+    fs::write(dir.join("src/notes.txt"), "not oxide\n").unwrap();
+    // OUT_DIR is shared by the whole process, so this is the only test that sets it
+    std::env::set_var("OUT_DIR", &out_dir);
+
+    let written = oxide::build_script::transpile_dir(dir.join("src"), &Options::default()).unwrap();
+    assert_eq!(written, [out_dir.join("nested/good.rs")]);
+
+    // what a crate's main.rs would do with it
+    let main = dir.join("main.rs");
+    fs::write(&main, format!("mod good {{\n    include!({:?});\n}}\n\nfn main() {{\n    println!(\"{{}}\", good::twice(21));\n}}\n", written[0])).unwrap();
+    let binary = dir.join("main");
+    let status = Command::new("rustc").args(["--edition", "2021", "-o"]).arg(&binary).arg(&main).status().unwrap();
+    assert!(status.success());
+    assert_eq!(String::from_utf8_lossy(&Command::new(&binary).output().unwrap().stdout), "42\n");
+
+    fs::write(dir.join("src/bad.oxi"), "proc main() {\n    int x = nope;\n}\n").unwrap();
+    fs::remove_dir_all(&out_dir).unwrap();
+    let error = oxide::build_script::transpile_dir(dir.join("src"), &Options::default()).unwrap_err();
+    assert_eq!(error.to_string(), "1 oxide file failed to compile");
+    assert!(fs::read_to_string(out_dir.join("nested/good.rs")).unwrap().contains("pub fn twice(n: i32) -> i32 {"));
+    assert!(!out_dir.join("bad.rs").exists());
+
+    let errors = oxide::compile_file("bad.oxi", "proc main() {\n    int x = nope;\n}\n", &Options::default()).unwrap_err();
+    assert_eq!(errors[0].located("bad.oxi"), "bad.oxi:2:13: unknown name `nope`");
+
+    let error = oxide::build_script::transpile_dir(dir.join("missing"), &Options::default()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn lowering_errors_fail_check() {
+    let source = "map<string, int> m = { \"a\": };\n";
+    let errors = oxide::compile_file("map.oxi", source, &Options::default()).unwrap_err();
+    assert_eq!(errors[0].render("map.oxi"), "error: map.oxi:1:1: can't make sense of `{\"a\":}`");
+
+    let tokens = oxide::tokenise(source).unwrap();
+    let parsed = oxide::parse(tokens, source).unwrap();
+    assert!(oxide::check(&parsed, &Options::default()).is_err());
+}
+
+#[test]
+fn rust_loops_over_items() {
+    let source = "proc total(map<string, int> m) -> int {\n    int sum = 0;\n    for (k, v) in m {\n        sum = sum + v;\n    }\n    for k in m.keys() {\n        m.remove(k);\n    }\n    return sum;\n}\n";
+    let options = Options { module: true, ..Options::default() };
+    let output = oxide::compile_file("total.oxi", source, &options).unwrap();
+
+    assert!(output.code.contains("    for (k, v) in m.clone() {\n"));
+    assert!(output.code.contains("    for k in m.keys().cloned().collect::<Vec<_>>() {\n"));
+    assert!(!output.code.contains("loop {"));
+}
+
+#[test]
+fn ordered_maps_are_btree_maps() {
+    let source = "map<string, int> m = {\"a\": 1};\nmap<string, int> n = {};\nprint(\"HashMap {:?} {:?}\", m, n);\n";
+    let options = Options { ordered_maps: true, ..Options::default() };
+    let output = oxide::compile(source, &options).unwrap();
+
+    assert!(output.code.starts_with("use std::collections::BTreeMap;\n\n"), "{}", output.code);
+    assert!(output.code.contains("    let m: BTreeMap<String, i32> = BTreeMap::from([(String::from(\"a\"), 1)]);\n"), "{}", output.code);
+    assert!(output.code.contains("    let n: BTreeMap<String, i32> = BTreeMap::new();\n"), "{}", output.code);
+    assert!(output.code.contains("println!(\"HashMap {:?} {:?}\", m, n);"), "{}", output.code);
+}
+
+/// what `oxide <args>` prints to stdout and stderr with `input` on
+/// stdin, and the code it exits with
+fn oxide(args: &[&str], input: &str) -> (String, String, i32) {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_oxide")).args(args)
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+        .spawn().unwrap();
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    return (stdout, stderr, output.status.code().unwrap_or(1));
+}
+
+#[test]
+fn repl_shows_the_rust_a_snippet_adds() {
+    let input = "int x = 5;\n:rust print(\"{}\", x + 1);\n:rust print(\"{}\", x +);\n";
+    let (stdout, stderr, _) = oxide(&["repl", "-q"], input);
+
+    assert_eq!(stdout.replace("> ", ""), "    println!(\"{}\", x + 1);\n\n");
+    assert_eq!(stderr, "error: <repl>:1:1: can't make sense of `x +`\n");
+}
+
+#[test]
+fn repl_shows_the_statements_a_snippet_adds() {
+    let input = "proc f(int a) -> int {\n    return a;\n}\n:ast f(1);\n:ast g(1);\n";
+    let (stdout, stderr, _) = oxide(&["repl", "-q"], input);
+
+    assert_eq!(stdout.replace("> ", "").replace(". ", ""), "1:1 FunctionCall(FunctionCall { name: \"f\", parameters: \"1\", propagate: false })\n\n");
+    assert_eq!(stderr, "error: <repl>:1:1: unknown name `g`\n");
+}
+
+#[test]
+fn check_rejects_what_rustc_would() {
+    let cases = [
+        ("print(\"{}\", zz);\n", "`zz` isn't declared"),
+        ("print(\"{} {}\", 1);\n", "the format string takes 2 values but 1 was given"),
+        ("let (a, b) = 5;\n", "`5` is `int`, only tuples can be destructured"),
+        ("for k in 5 {\n    print(\"{}\", k);\n}\n", "`5` is `int`, only maps can be looped over"),
+        ("print(\"{:x}\", 1);\n", "`{:x}` isn't supported, a format can have a fill and alignment, `+`, `0`, a width and a precision, or be `{:?}`"),
+        ("print(\"{:>5?}\", 1);\n", "`{:>5?}` isn't supported, a format can have a fill and alignment, `+`, `0`, a width and a precision, or be `{:?}`"),
+    ];
+
+    for (source, message) in cases {
+        let errors = oxide::compile(source, &Options::default()).unwrap_err();
+        assert_eq!(errors[0].message, message, "for {source:?}");
+    }
+}
+
+#[test]
+fn opts_are_unwrapped_before_returns_and_conditions() {
+    let source = "proc first(opt<int> y) -> int {\n    if (y == 3) {\n        return 1;\n    }\n    return y;\n}\n";
+    let errors = oxide::compile(source, &Options::default()).unwrap_err();
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    assert_eq!(messages, [
+        "`y` is `opt<int>` and must be unwrapped before it can be used in a condition, try `if let some(value) = y`",
+        "`y` is `opt<int>` and must be unwrapped before it can be returned as `int`",
+    ]);
+
+    // an `opt` can still be compared with one and returned as one
+    let source = "proc same(opt<int> y) -> opt<int> {\n    if (y == none) {\n        return none;\n    }\n    return y;\n}\n";
+    assert!(oxide::compile(source, &Options { module: true, ..Options::default() }).is_ok());
+}
+
+#[test]
+fn unclosed_map_literals_are_syntax_errors() {
+    let source = "map<string, int> m = {\n    \"a\": 1,\nprint(\"{}\", 1);\n";
+    let errors = oxide::compile(source, &Options::default()).unwrap_err();
+    assert_eq!(errors[0].to_string(), "1:1: this value's `{` is never closed with a `}`");
+}
+
+#[test]
+fn rustc_snippets_show_the_oxide() {
+    let dir = std::env::temp_dir().join(format!("oxide-remap-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("moved.oxi");
+    let oxide_path = path.to_string_lossy();
+    fs::write(&path, "proc main() {\n    string a = \"x\";\n    string b = a;\n}\n").unwrap();
+
+    let rust = "fn main() { // ox:1:1\n    let a: String = String::from(\"x\"); // ox:2:5\n    let b: String = a; // ox:3:5\n}\n";
+    let diagnostics = "error[E0382]: use of moved value: `a`\n --> gen.rs:3:21\n  |\n2 |     let a: String = String::from(\"x\");\n  |         - move occurs because `a` has type `String`\n3 |     let b: String = a;\n  |                     ^ value used here after move\n  |\n";
+    let rewritten = oxide::SourceMap::from_markers(rust).rewrite(diagnostics, "gen.rs", &oxide_path);
+
+    assert_eq!(rewritten, format!("error[E0382]: use of moved value: `a`\n --> {oxide_path}:3:5\n  |\n2 |     string a = \"x\";\n  |     - move occurs because `a` has type `String`\n3 |     string b = a;\n  |     ^ value used here after move\n  |\n"));
+
+    // a suggestion changes the rust, which has no oxide to show
+    let diagnostics = "error[E0308]: mismatched types\n --> gen.rs:3:21\n  |\n3 |     let b: String = a;\n  |                     ^ expected `String`\n  |\nhelp: try using a conversion method\n  |\n3 |     let b: String = a.to_string();\n  |                      ++++++++++++\n\nerror: aborting due to 1 previous error\n";
+    let rewritten = oxide::SourceMap::from_markers(rust).rewrite(diagnostics, "gen.rs", &oxide_path);
+
+    assert_eq!(rewritten, format!("error[E0308]: mismatched types\n --> {oxide_path}:3:5\n  |\n3 |     string b = a;\n  |     ^ expected `String`\n  |\n\nerror: aborting due to 1 previous error\n"));
+}
+
+#[test]
+fn else_lines_point_at_the_else() {
+    let source = "proc main() {\n    int x = 3;\n    if (x > 5) {\n        print(\"big\");\n    } orif (x > 2) {\n        print(\"mid\");\n    } else {\n        print(\"small\");\n    }\n}\n";
+
+    for target in [Target::Rust, Target::C, Target::Js] {
+        let options = Options { source_map: true, target, ..Options::default() };
+        let output = oxide::compile(source, &options).unwrap();
+
+        assert!(output.code.contains("} else { // ox:7:5\n"), "{}", output.code);
+        assert!(output.code.lines().any(|line| line.contains("} else if") && line.ends_with("// ox:5:5")), "{}", output.code);
+    }
+}
+
+#[test]
+fn runner_points_rustc_and_panics_at_the_oxide() {
+    let dir = std::env::temp_dir().join(format!("oxide-runner-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("moved.oxi");
+    let oxide_path = path.to_string_lossy();
+    let binary = dir.join("moved").to_string_lossy().into_owned();
+    fs::write(&path, "proc main() {\n    string a = \"x\";\n    string b = a;\n    string c = a;\n}\n").unwrap();
+
+    // what the generator would give if it moved strings
+    let rust = "fn main() { // ox:1:1\n    let a: String = String::from(\"x\"); // ox:2:5\n    let _b: String = a; // ox:3:5\n    let _c: String = a; // ox:4:5\n}\n";
+    let map = oxide::SourceMap::from_markers(rust);
+    let mut stderr = Vec::new();
+    let built = oxide::runner::build(rust, &map, &oxide_path, Some(&binary), Target::Rust, &mut stderr);
+    let stderr = String::from_utf8_lossy(&stderr);
+
+    assert!(built.is_none());
+    assert!(stderr.contains(&format!(" --> {oxide_path}:4:5\n")), "{stderr}");
+    assert!(stderr.contains("4 |     string c = a;\n  |     ^ value used here after move\n"), "{stderr}");
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs/divide_by_zero.oxi");
+    let output = oxide::compile_file(&path.to_string_lossy(), &fs::read_to_string(&path).unwrap(), &Options::default()).unwrap();
+    let built = oxide::runner::build(&output.code, &output.source_map, "divide_by_zero.oxi", Some(&binary), Target::Rust, &mut Vec::new()).unwrap();
+    let mut stderr = Vec::new();
+    let code = oxide::runner::run(&built, &[], &output.source_map, "divide_by_zero.oxi", &mut stderr);
+
+    assert_eq!(code, 101);
+    assert!(String::from_utf8_lossy(&stderr).contains("panicked at divide_by_zero.oxi:2:5:\nattempt to divide by zero"));
+}
+
+#[test]
+fn c_maps_change_in_place_without_sharing() {
+    if !has_cc() {
+        eprintln!("skipping, there's no c compiler");
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("oxide-c-maps-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("shared.oxi");
+    let mut inserts = String::new();
+    for i in 0..2000 {
+        inserts.push_str(&format!("    m.insert({i}, {i});\n"));
+    }
+
+    // rust can't use a map after it's moved, so this only runs as c and in the interpreter
+    let source = format!("proc grow(map<int, int> m) -> bool {{\n    m.insert(-1, 0);\n    return m.contains(-1);\n}}\n\nproc main() {{\n    map<int, int> m = {{}};\n    map<int, int> before = m;\n{inserts}    m.remove(7);\n    bool grew = grow(m);\n    print(\"{{}} {{}} {{}} {{}}\", before.contains(1), m.contains(1999), m.contains(7), grew);\n    print(\"{{}}\", m.contains(-1));\n}}\n");
+    fs::write(&path, &source).unwrap();
+
+    let (stdout, code) = built(&path, Options { target: Target::C, ..Options::default() });
+    assert_eq!((stdout.as_str(), code), ("false true false true\nfalse\n", 0));
+    assert_eq!(interpreted(&path), (stdout, code));
+}
+
+/// every error `compile` gives for `source`, as `line:col: message`
+fn errors(source: &str) -> Vec<String> {
+    return match oxide::compile(source, &Options::default()) {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors.iter().map(ToString::to_string).collect(),
+    };
+}
+
+#[test]
+fn checker_rejects_misused_opts() {
+    assert_eq!(errors("opt<int> x = 5;\n"), ["1:1: `x` is `opt<int>` but was given a plain `int`, wrap it with `some(...)`"]);
+    assert_eq!(errors("opt<int> x = some(5);\nint y = x;\n"), ["2:1: `x` is `opt<int>` and must be unwrapped before it can be used as `int`"]);
+    assert_eq!(errors("int x = 5;\nif let some(v) = x {\n    print(\"{}\", v);\n}\n"), ["2:1: `x` is `int`, only `opt` values can be unwrapped"]);
+    assert_eq!(errors("opt<int> x = some(5);\nopt<string> y = x;\n"), ["2:1: `y` is `opt<string>` but `x` is `opt<int>`"]);
+    assert_eq!(errors("opt<int> x = some(5);\nprint(\"{}\", x);\n"), ["2:1: `x` is `opt<int>` and must be unwrapped before it can be printed"]);
+    assert_eq!(
+        errors("proc f(int n) -> int {\n    return n;\n}\nopt<int> x = some(5);\nf(x);\n"),
+        ["5:1: `x` is `opt<int>` and must be unwrapped before it can be passed to `f`"],
+    );
+
+    let f = "proc f() -> opt<int> {\n    return some(2);\n}\n";
+    assert_eq!(errors(&format!("{f}int y = f();\n")), ["4:1: `f()` gives `opt<int>` and must be unwrapped before it can be used as `int`"]);
+    assert_eq!(
+        errors("opt<int> x = some(5);\nbool nonempty = true;\nif (nonempty && x > 0) {\n    print(\"a\");\n}\n"),
+        ["3:1: `x` is `opt<int>` and must be unwrapped before it can be used in a condition, try `if let some(value) = x`"],
+    );
+
+    assert!(errors("opt<int> x = some(5);\nif let some(v) = x {\n    print(\"{}\", v);\n}\n").is_empty());
+    assert!(errors(&format!("{f}opt<int> y = f();\nif (y == none) {{\n    print(\"none\");\n}}\n")).is_empty());
+}
+
+#[test]
+fn checker_rejects_misused_results() {
+    let g = "proc g() -> result<int, string> {\n    return fail(\"no\");\n}\n";
+
+    assert_eq!(
+        errors(&format!("{g}proc f() -> int {{\n    int x = g()?;\n    return x;\n}}\n")),
+        ["5:5: `?` can only be used inside a proc that returns `result`"],
+    );
+    assert_eq!(
+        errors(&format!("{g}proc f() {{\n    print(\"{{}}\", g()?);\n}}\n")),
+        ["5:5: `?` can only be used inside a proc that returns `result`"],
+    );
+    assert_eq!(
+        errors(&format!("{g}proc f() {{\n    if (g()? > 0) {{\n        print(\"big\");\n    }}\n}}\n")),
+        ["5:5: `?` can only be used inside a proc that returns `result`"],
+    );
+    assert_eq!(errors("proc f() -> int {\n    return ok(1);\n}\n"), ["2:5: `return ok(1)` needs the proc to return a `result`"]);
+    assert_eq!(errors(&format!("{g}int x = g();\n")), ["4:1: `g` returns `result<int, string>`, use `?` to get the value out for `x`"]);
+
+    // a main that fails exits with 1 and prints what it failed with
+    let source = format!("{g}proc main() -> result<int, string> {{\n    int x = g()?;\n    return ok(x);\n}}\n");
+    let mut stderr = Vec::new();
+    let code = oxide::interpret_file("fails.oxi", &source, &mut Vec::new(), &mut stderr).unwrap();
+    assert_eq!((code, String::from_utf8_lossy(&stderr).as_ref()), (1, "error: no\n"));
+}
+
+#[test]
+fn checker_rejects_misused_maps() {
+    let m = "map<string, int> m = { \"a\": 1 };\n";
+
+    assert_eq!(errors(&format!("{m}m.push(1);\n")), ["2:1: maps have no `push`, try one of get, insert, remove, contains, keys, values"]);
+    assert_eq!(
+        errors(&format!("{m}for k in m {{\n    print(\"{{}}\", k);\n}}\n")),
+        ["2:1: looping over `m` gives a key and a value, write `for (key, value) in m`"],
+    );
+    assert_eq!(
+        errors(&format!("{m}for k in m.items() {{\n    print(\"{{}}\", k);\n}}\n")),
+        ["2:1: `m.items()` can't be looped over, try `m.keys()` or `m.values()`"],
+    );
+    assert_eq!(errors(&format!("{m}int x = m.get(\"a\");\n")), ["2:1: `m.get(\"a\")` gives `opt<int>` and must be unwrapped before it can be used as `int`"]);
+    assert_eq!(errors("map<string, int> m = { \"a\" };\n"), ["1:1: can't make sense of `{\"a\"}`"]);
+
+    assert!(errors(&format!("{m}for (k, v) in m {{\n    print(\"{{}} {{}}\", k, v);\n}}\n")).is_empty());
+}
+
+#[test]
+fn checker_rejects_misused_tuples() {
+    let t = "(int, string) t = (1, \"a\");\n";
+
+    assert_eq!(errors(&format!("{t}let (a, b, c) = t;\n")), ["2:1: `t` has 2 values but 3 names were given"]);
+    assert_eq!(errors(&format!("{t}int x = t.2;\n")), ["2:1: `t` has no field `2`, it only has 2"]);
+    assert_eq!(errors(&format!("{t}int x = t.1;\n")), ["2:1: `t.1` is `string` but `x` is `int`"]);
+    assert_eq!(errors(&format!("{t}print(\"{{}}\", t);\n")), ["2:1: `t` is `(int, string)`, print its fields like `t.0` instead"]);
+
+    assert!(errors(&format!("{t}let (a, b) = t;\nprint(\"{{}} {{}}\", a, t.1);\n")).is_empty());
+}
+
+#[test]
+fn top_level_statements_become_main() {
+    let source = "proc f() {\n    print(\"f\");\n}\nf();\n";
+    let output = oxide::compile(source, &Options::default()).unwrap();
+    assert_eq!(output.code, "fn f() {\n    println!(\"f\");\n}\n\nfn main() {\n    f();\n}\n");
+
+    assert_eq!(
+        errors("proc main() {\n    print(\"a\");\n}\nprint(\"b\");\n"),
+        ["4:1: statements outside of a proc can't be mixed with `proc main`, move them into main"],
+    );
+    assert_eq!(errors("return 1;\n"), ["1:1: `return` can only be used inside a proc"]);
+
+    let module = oxide::compile("print(\"b\");\n", &Options { module: true, ..Options::default() });
+    let errors: Vec<String> = module.err().unwrap().iter().map(ToString::to_string).collect();
+    assert_eq!(errors, ["1:1: statements outside of a proc can't go in a module, move them into a proc"]);
+}
+
+#[test]
+fn rust_is_indented_a_line_at_a_time() {
+    let source = "proc sign(int n) -> string {\n    map<int, int> ks = {};\n    bool neg = (n < 0);\n    if (neg) {\n        return \"neg\";\n    } orif (n == 0) {\n        return \"zero\";\n    } else {\n        for k in ks.keys() {\n            print(\"{}\", k);\n        }\n    }\n    return \"pos\";\n}\n";
+    let output = oxide::compile(source, &Options::default()).unwrap();
+
+    assert_eq!(output.code, "\
+use std::collections::HashMap;
+
+fn sign(n: i32) -> String {
+    let ks: HashMap<i32, i32> = HashMap::new();
+    let neg: bool = n < 0;
+    if neg {
+        return String::from(\"neg\");
+    } else if n == 0 {
+        return String::from(\"zero\");
+    } else {
+        for k in ks.keys().cloned().collect::<Vec<_>>() {
+            println!(\"{}\", k);
+        }
+    }
+    return String::from(\"pos\");
+}
+
+fn main() {
+}
+");
+}
+
+#[test]
+fn source_maps_find_the_statement_behind_a_line() {
+    // rustfmt moves a marker after `{` onto its own line
+    let rust = "fn main() {\n    // ox:1:1\n    let a: i32 = 1; // ox:2:5\n    if a > 0 {\n        // ox:3:5\n        println!(\"{}\", a); // ox:utils.oxi:4:9\n    }\n}\n";
+    let map = oxide::SourceMap::from_markers(rust);
+    let at = |line| map.lookup(line).map(|location| (location.file.clone(), location.span.line, location.span.column));
+
+    assert_eq!(at(1), Some((None, 1, 1)));
+    assert_eq!(at(3), Some((None, 2, 5)));
+    assert_eq!(at(4), Some((None, 3, 5)));
+    assert_eq!(at(6), Some((Some(String::from("utils.oxi")), 4, 9)));
+    // closing braces fall back to the nearest statement above
+    assert_eq!(at(8), Some((Some(String::from("utils.oxi")), 4, 9)));
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("remap");
+    fs::create_dir_all(&dir).unwrap();
+    let rust_path = dir.join("gen.rs").to_string_lossy().into_owned();
+    let oxide_path = dir.join("sum.oxi").to_string_lossy().into_owned();
+    fs::write(&rust_path, rust).unwrap();
+    fs::write(&oxide_path, "proc main() {\n    int a = 1;\n}\n").unwrap();
+
+    let panic = "thread 'main' panicked at gen.rs:3:18:\nattempt to add with overflow\n";
+    let (stdout, _, _) = oxide(&["remap", &oxide_path, &rust_path], panic);
+    assert_eq!(stdout, format!("thread 'main' panicked at {oxide_path}:2:5:\nattempt to add with overflow\n"));
+
+    let (_, stderr, code) = oxide(&["remap", &oxide_path, "missing.rs"], "");
+    assert!(stderr.starts_with("error: couldn't read missing.rs: "), "{stderr}");
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn escaped_names_are_reported_as_written() {
+    let output = oxide::compile("proc type(int self) -> int {\n    return self;\n}\n", &Options { module: true, ..Options::default() }).unwrap();
+    assert!(output.code.contains("pub fn r#type(ox_self: i32) -> i32 {\n"), "{}", output.code);
+
+    let diagnostics = "warning: unused variable: `r#match`\nerror[E0425]: cannot find value `ox_self` in this scope\nwarning: unused variable: `ox_ox_Some`\n";
+    let rewritten = oxide::SourceMap::from_markers("").rewrite(diagnostics, "gen.rs", "keywords.oxi");
+    assert_eq!(rewritten, "warning: unused variable: `match`\nerror[E0425]: cannot find value `self` in this scope\nwarning: unused variable: `ox_Some`\n");
+
+    // a name of the user's own that starts with `ox_` can't clash with an escaped one
+    let output = oxide::compile("int self = 1;\nint ox_self = 2;\nint try = 3;\nprint(\"{} {} {}\", self, ox_self, try);\n", &Options::default()).unwrap();
+    assert!(output.code.contains("    println!(\"{} {} {}\", ox_self, ox_ox_self, r#try);\n"), "{}", output.code);
+}
+
+#[test]
+fn run_passes_on_how_the_program_exits() {
+    let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let program = |name: &str| programs.join(name).to_string_lossy().into_owned();
+
+    let (stdout, stderr, code) = oxide(&["run", "-q", &program("results.oxi")], "");
+    assert_eq!((stdout.as_str(), code), ("total 9\nbob\nfirst 9\n", 1));
+    assert!(stderr.ends_with("error: negative age\n"), "{stderr}");
+
+    let (_, stderr, code) = oxide(&["run", "-q", &program("divide_by_zero.oxi")], "");
+    assert_eq!(code, 101);
+    assert!(stderr.contains(&format!("panicked at {}:2:5:\nattempt to divide by zero\n", program("divide_by_zero.oxi"))), "{stderr}");
+
+    let binary = Path::new(env!("CARGO_TARGET_TMPDIR")).join("keywords-built");
+    let (_, _, code) = oxide(&["build", "-q", "-o", &binary.to_string_lossy(), &program("keywords.oxi")], "");
+    assert_eq!(code, 0);
+    let output = Command::new(&binary).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "loop 2\n2 6\n1 2 2 3 4\n");
+
+    let (_, stderr, code) = oxide(&["build", "-"], "proc main() {\n    print(\"{}\", nope);\n}\n");
+    assert_eq!((stderr.as_str(), code), ("error: <stdin>:2:5: `nope` isn't declared\n1 error found\n", 1));
+}
+
+#[test]
+fn cli_commands_and_their_exit_codes() {
+    let source = "int x = 1 + 2;\nprint(\"{}\", x);\n";
+
+    let (_, stderr, code) = oxide(&["bogus", "x.oxi"], "");
+    assert!(stderr.starts_with("error: unknown command `bogus`\n\nusage: oxide"), "{stderr}");
+    assert_eq!(code, 2);
+    let (_, stderr, code) = oxide(&["check", "--frob", "x.oxi"], "");
+    assert!(stderr.starts_with("error: unknown option `--frob`\n"), "{stderr}");
+    assert_eq!(code, 2);
+    let (_, stderr, code) = oxide(&["check", "missing.oxi"], "");
+    assert!(stderr.starts_with("error: couldn't read missing.oxi: "), "{stderr}");
+    assert_eq!(code, 2);
+
+    assert_eq!(oxide(&["check", "-"], source), (String::from("<stdin>: ok\n"), String::new(), 0));
+    assert_eq!(oxide(&["check", "-"], "int x = ;\n").2, 1);
+
+    let (tokens, _, _) = oxide(&["emit", "--emit=tokens", "-"], source);
+    assert!(tokens.starts_with("LetInt((\"LETINT\", \"int\"))\nVarName((\"VARNAME\", \"x\"))\n"), "{tokens}");
+    let (ast, _, _) = oxide(&["emit", "--emit=ast", "-"], source);
+    assert!(ast.starts_with("(Span { line: 1, column: 1 }, VariableDeclare("), "{ast}");
+    let (ir, _, _) = oxide(&["emit", "--emit=ir", "-"], source);
+    assert!(ir.contains("    1:1      let x: int = 1 + 2\n"), "{ir}");
+    let (bytecode, _, _) = oxide(&["emit", "--emit=bytecode", "-"], source);
+    assert!(bytecode.contains("fn 0 <top level> (0 params, 1 locals, entry):\n"), "{bytecode}");
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli");
+    fs::create_dir_all(&dir).unwrap();
+    let rust_path = dir.join("gen.rs");
+    assert_eq!(oxide(&["emit", "-o", &rust_path.to_string_lossy(), "-"], source).2, 0);
+    assert_eq!(fs::read_to_string(&rust_path).unwrap(), "fn main() {\n    let x: i32 = 1 + 2;\n    println!(\"{}\", x);\n}\n");
+
+    let path = dir.join("messy.oxi");
+    fs::write(&path, "proc f() {\nint x = 1;\n        print(\"{}\", x);\n    }\n").unwrap();
+    assert_eq!(oxide(&["fmt", "-q", &path.to_string_lossy()], "").2, 0);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "proc f() {\n    int x = 1;\n    print(\"{}\", x);\n}\n");
+}
+
+#[test]
+fn each_stage_runs_on_its_own() {
+    let source = "proc twice(int n) -> int {\n    return n * 2;\n}\nprint(\"{}\", twice(3));\n";
+    let options = Options::default();
+
+    let tokens = oxide::tokenise(source).unwrap();
+    let parsed = oxide::parse(tokens, source).unwrap();
+    assert_eq!(parsed[0].0, oxide::Span { line: 1, column: 1 });
+    assert_eq!(parsed.last().unwrap().0, oxide::Span { line: 4, column: 1 });
+
+    let module = oxide::check(&parsed, &options).unwrap();
+    let output = oxide::generate(&module, &options);
+    assert_eq!(output.code, oxide::compile(source, &options).unwrap().code);
+    assert_eq!(output.source_map.lookup(2).map(|location| location.span), Some(oxide::Span { line: 2, column: 5 }));
+
+    let error = oxide::tokenise("int x = 1 $ 2;\n").unwrap_err();
+    assert_eq!(error.render("bad.oxi"), "error: bad.oxi:1:11: unexpected `$`");
+
+    let error = oxide::tokenise("int x = y;\n").unwrap_err();
+    assert_eq!(error.to_string(), "1:9: unknown name `y`");
+    let error = oxide::parse(oxide::tokenise("int x;\n").unwrap(), "int x;\n").unwrap_err();
+    assert_eq!(error.to_string(), "1:1: an `int` needs a value and a `;`, like `int x = 1;`");
+
+    let source = "print(\"{}\", y);\n";
+    let errors = oxide::check(&oxide::parse(oxide::tokenise(source).unwrap(), source).unwrap(), &options).unwrap_err();
+    assert_eq!(errors[0].located("bad.oxi"), "bad.oxi:1:1: `y` isn't declared");
+    assert_eq!(errors[0].file, None);
+}
+
+#[test]
+fn new_starts_a_cargo_project() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("hello-app");
+    let _ = fs::remove_dir_all(&dir);
+    let project = dir.to_string_lossy().into_owned();
+
+    let (stdout, _, code) = oxide(&["new", &project], "");
+    assert_eq!(code, 0);
+    let created: Vec<String> = ["src/main.oxi", "Cargo.toml", "README.md", ".gitignore", "src/main.rs"].iter()
+        .map(|file| format!("created {}\n", dir.join(file).display()))
+        .collect();
+    assert_eq!(stdout, created.concat());
+    assert!(fs::read_to_string(dir.join("Cargo.toml")).unwrap().starts_with("[package]\nname = \"hello_app\"\n"));
+    assert_eq!(fs::read_to_string(dir.join("src/main.rs")).unwrap(), "// generated by oxide from src/main.oxi, edit that instead\n\nfn main() {\n    println!(\"hello world\");\n}\n");
+
+    let (_, stderr, code) = oxide(&["new", &project], "");
+    assert_eq!(stderr, format!("error: couldn't create {project}: {} already exists\n", dir.join("src/main.oxi").display()));
+    assert_eq!(code, 2);
+
+    // later builds only regenerate src/main.rs
+    fs::write(dir.join("Cargo.toml"), "edited\n").unwrap();
+    let written = oxide::project::write_project(&dir, "fn main() {\n}\n", "src/main.oxi").unwrap();
+    assert_eq!(written, [dir.join("src/main.rs")]);
+    assert_eq!(fs::read_to_string(dir.join("Cargo.toml")).unwrap(), "edited\n");
+    assert_eq!(fs::read_to_string(dir.join("src/main.rs")).unwrap(), "// generated by oxide from src/main.oxi, edit that instead\n\nfn main() {\n}\n");
+}
+
+#[test]
+fn emit_cargo_puts_the_project_next_to_the_source() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("emit-cargo");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let source = "proc main() {\n    print(\"hi\");\n}\n";
+    fs::write(dir.join("greet.oxi"), source).unwrap();
+
+    let (stdout, _, code) = oxide(&["emit", "--emit=cargo", &dir.join("greet.oxi").to_string_lossy()], "");
+    assert_eq!(code, 0);
+    let project = dir.join("greet");
+    let wrote: Vec<String> = ["src/main.oxi", "Cargo.toml", "README.md", ".gitignore", "src/main.rs"].iter()
+        .map(|file| format!("wrote {}\n", project.join(file).display()))
+        .collect();
+    assert_eq!(stdout, wrote.concat());
+    assert_eq!(fs::read_to_string(project.join("src/main.oxi")).unwrap(), source);
+
+    // what the project's README says to run
+    let main = project.join("src/main.oxi");
+    let (stdout, _, code) = oxide(&["emit", "--emit=cargo", &main.to_string_lossy(), "-o", &project.to_string_lossy()], "");
+    assert_eq!(code, 0);
+    assert_eq!(stdout, format!("wrote {}\n", project.join("src/main.rs").display()));
+}
+
+#[test]
+fn import_errors_name_the_files_involved() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("imports");
+    fs::create_dir_all(dir.join("other")).unwrap();
+    let files = [
+        ("a.oxi", "import \"b.oxi\";\nproc main() {\n}\n"),
+        ("b.oxi", "import \"a.oxi\";\npub proc f() {\n}\n"),
+        ("missing.oxi", "import \"nope.oxi\";\n"),
+        ("u.oxi", "proc hidden() {\n}\n"),
+        ("private.oxi", "import \"u.oxi\";\nu.hidden();\n"),
+        ("other/u.oxi", "pub proc f() {\n}\n"),
+        ("clash.oxi", "import \"u.oxi\";\nimport \"other/u.oxi\";\n"),
+        ("nested.oxi", "proc main() {\n    import \"u.oxi\";\n}\n"),
+    ];
+    for (file, source) in files {
+        fs::write(dir.join(file), source).unwrap();
+    }
+
+    let errors = |file: &str| -> Vec<String> {
+        let path = dir.join(file).to_string_lossy().into_owned();
+        let source = fs::read_to_string(&path).unwrap();
+        let errors = oxide::compile_file(&path, &source, &Options::default()).unwrap_err();
+        let dir = format!("{}/", dir.display());
+        return errors.iter().map(|error| error.located(&path).replace(&dir, "")).collect();
+    };
+
+    assert_eq!(errors("a.oxi"), ["b.oxi:1:1: import cycle: a.oxi -> b.oxi -> a.oxi"]);
+    let missing = errors("missing.oxi");
+    assert!(missing[0].starts_with("missing.oxi:1:1: couldn't import nope.oxi: "), "{missing:?}");
+    assert_eq!(errors("private.oxi"), ["private.oxi:2:1: `hidden` isn't `pub` in u.oxi, so it can't be called from here"]);
+    assert_eq!(errors("clash.oxi"), ["clash.oxi:2:1: other/u.oxi and u.oxi are both called `u`, rename one of them"]);
+    assert_eq!(errors("nested.oxi"), ["nested.oxi:2:5: `import` has to be outside of any proc"]);
+}
+
+#[test]
+fn only_values_of_one_kind_compare() {
+    let source = "proc f() -> opt<int> {\n    return some(1);\n}\n\nproc main() {\n    if (f() > 0) {\n        print(\"yes\");\n    }\n}\n";
+    let mut stderr = Vec::new();
+    let code = oxide::interpret_file("compare.oxi", source, &mut Vec::new(), &mut stderr).unwrap();
+    assert_eq!((code, String::from_utf8_lossy(&stderr).as_ref()), (101, "error: compare.oxi:6:5: `>` doesn't work on Some(1) and 0\n"));
+
+    let program = oxide::bytecode::compile_file("compare.oxi", source).unwrap();
+    let mut stderr = Vec::new();
+    let code = oxide::vm::run(&program, "compare.oxi", &mut Vec::new(), &mut stderr);
+    assert_eq!((code, String::from_utf8_lossy(&stderr).as_ref()), (101, "error: compare.oxi:6:5: `>` doesn't work on Some(1) and 0\n"));
+
+    // `none` compares with any other `opt`
+    let source = "opt<int> x = some(1);\nif (x == none) {\n    print(\"none\");\n} else {\n    print(\"some\");\n}\n";
+    let mut stdout = Vec::new();
+    assert_eq!(oxide::interpret_file("compare.oxi", source, &mut stdout, &mut Vec::new()).unwrap(), 0);
+    assert_eq!(String::from_utf8_lossy(&stdout), "some\n");
+}